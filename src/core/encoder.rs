@@ -1,34 +1,58 @@
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
 use anyhow::{Result, anyhow};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
 use once_cell::sync::Lazy;
 use std::time::Duration;
 
 use crate::core::pipewire::init_pipewire;
+use crate::devices::camera::{default_camera, CameraConfig};
+use crate::devices::screen::session_cursor;
 use crate::extensions::{
-    scene_switcher::{SceneSwitcher, Scene, SceneSource},
-    overlays::OverlayManager,
+    scene_switcher::SceneSwitcher,
+    scene_automation::{AutomationConfig, AutomationEngine},
+    scene_collection::{Canvas, SceneCollection},
+    source_registry::SourceKind,
+    zoom_follow::ZoomFollow,
+    overlays::{OverlayManager, OverlayStack},
+    recording_stamp::RecordingStamp,
+    subtitles::{self, SubtitleConfig},
     streaming::MultiStreamingManager,
     hardware_accel::setup_unified_hardware_accel,
     plugin_system::{PluginManager, GLOBAL_PLUGIN_MANAGER},
 };
 
-// For reading peak audio levels
+// Peak audio levels in dBFS by PipeWire node name, filled by `LevelMonitor`s
 pub static VOLUME_DATA: Lazy<Arc<Mutex<HashMap<String, f64>>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
 // Global references for advanced managers/pipeline
 static mut GLOBAL_PIPELINE: Option<gst::Pipeline> = None;
-static mut GLOBAL_SCENE_SWITCHER: Option<SceneSwitcher> = None;
-static mut GLOBAL_OVERLAY_MANAGER: Option<OverlayManager> = None;
-static mut GLOBAL_STREAMING_MANAGER: Option<MultiStreamingManager> = None;
+static mut GLOBAL_ZOOM_FOLLOW: Option<ZoomFollow> = None;
 
-// A separate pipeline for "recording" to file
-static RECORDING_PIPELINE: Mutex<Option<gst::Pipeline>> = Mutex::new(None);
+// Mutable managers, owned by the main thread and lent out through `with_*`
+thread_local! {
+    static SCENE_SWITCHER: RefCell<Option<SceneSwitcher>> = RefCell::new(None);
+    static OVERLAY_MANAGER: RefCell<Option<OverlayManager>> = RefCell::new(None);
+    static AUTOMATION: RefCell<Option<AutomationEngine>> = RefCell::new(None);
+    static STREAMING_MANAGER: RefCell<Option<MultiStreamingManager>> = RefCell::new(None);
+    // Watermark and metadata stamp of the running recording
+    static RECORDING_OVERLAY_MANAGER: RefCell<Option<OverlayManager>> = RefCell::new(None);
+    // Audio inputs mixed into the program, by PipeWire node name
+    static PROGRAM_AUDIO_INPUTS: RefCell<Vec<ProgramAudioInput>> = RefCell::new(Vec::new());
+}
+
+// How often automation rules are checked
+const AUTOMATION_INTERVAL: Duration = Duration::from_millis(500);
 
-/// Builds a top-level pipeline with PipeWire (screen) + v4l2src (webcam),
+// Camera used by the main pipeline; None when no camera is present or it was disabled
+static ACTIVE_CAMERA: Mutex<Option<CameraConfig>> = Mutex::new(None);
+
+/// Builds a top-level pipeline with PipeWire (screen) + an optional webcam,
 /// a compositor, hardware acceleration, scene switching, overlays, streaming, plugin system
 pub fn init_pipeline_with_advanced_features() -> Result<gst::Pipeline> {
     gst::init()?;
@@ -38,56 +62,73 @@ pub fn init_pipeline_with_advanced_features() -> Result<gst::Pipeline> {
     let pipeline = gst::Pipeline::new(Some("waycord_pipeline"));
 
     // Basic elements
-    let pw_src = gst::ElementFactory::make("pipewiresrc", Some("pw_src"))?;
-    let cam_src = gst::ElementFactory::make("v4l2src", Some("cam_src"))?;
     let compositor = gst::ElementFactory::make("compositor", Some("comp"))?;
+    let canvas_caps = gst::ElementFactory::make("capsfilter", Some("canvas_caps"))?;
+    // Program output; studio mode hangs its program monitor here
+    let program_tee = gst::ElementFactory::make("tee", Some("program_tee"))?;
     let videoconvert = gst::ElementFactory::make("videoconvert", Some("videoconvert"))?;
     let queue = gst::ElementFactory::make("queue", Some("queue"))?;
 
-    pipeline.add_many(&[&pw_src, &cam_src, &compositor, &videoconvert, &queue])?;
-
-    // Link pw_src -> compositor.sink_0
-    let pw_pad = compositor.request_pad_simple("sink_0").ok_or_else(|| anyhow!("No sink_0 pad"))?;
-    pw_pad.set_property("xpos", 0i32);
-    pw_pad.set_property("ypos", 0i32);
-    pw_src.link(&compositor)?;
+    pipeline.add_many(&[&canvas_caps, &program_tee, &videoconvert, &queue])?;
 
-    // Link cam_src -> compositor.sink_1
-    let cam_pad = compositor.request_pad_simple("sink_1").ok_or_else(|| anyhow!("No sink_1 pad"))?;
-    cam_pad.set_property("xpos", 100i32);
-    cam_pad.set_property("ypos", 100i32);
-    cam_src.link(&compositor)?;
-
-    // Link compositor -> videoconvert -> queue
-    gst::Element::link_many(&[&compositor, &videoconvert, &queue])?;
-
-    // Attempt hardware acceleration
-    let _ = setup_unified_hardware_accel(&pipeline, &videoconvert, &queue);
-
-    // Scene Switcher
+    // Scene Switcher (adds the compositor to the pipeline)
     let main_context = glib::MainContext::default();
-    let initial_scene = Scene {
-        name: "Scene0".into(),
-        sources: vec![SceneSource {
-            element: pw_src.clone(),
-            pad_index: 0,
-            x: 0,
-            y: 0,
-            width: 1280,
-            height: 720,
-            alpha: 1.0,
-        }],
-    };
-    let scene_switcher = SceneSwitcher::new(
+    let mut scene_switcher = SceneSwitcher::new(
         pipeline.clone(),
         compositor.clone(),
-        vec![initial_scene],
+        Vec::new(),
         Duration::from_secs(1),
         main_context.clone(),
     );
 
-    // Overlay Manager
-    let overlay_mgr = OverlayManager::new(pipeline.clone(), main_context.clone());
+    // Link compositor -> canvas caps -> [overlays] -> program tee -> videoconvert -> queue
+    compositor.link(&canvas_caps)?;
+    let mut overlay_mgr = OverlayManager::new(pipeline.clone().upcast(), main_context.clone());
+    overlay_mgr.attach(&canvas_caps, &program_tee)?;
+    gst::Element::link_many(&[&program_tee, &videoconvert, &queue])?;
+
+    // Attempt hardware acceleration
+    let _ = setup_unified_hardware_accel(&pipeline, &videoconvert, &queue);
+
+    // Program audio: selected inputs and media file sources mix in here;
+    // recordings and streams hang off the tee. A silent base keeps the mixer
+    // producing while nothing else plays.
+    let audio_mixer = gst::ElementFactory::make("audiomixer", Some("program_mixer"))?;
+    let audio_convert = gst::ElementFactory::make("audioconvert", Some("program_audio_convert"))?;
+    let audio_tee = gst::ElementFactory::make("tee", Some("program_audio_tee"))?;
+    audio_tee.set_property("allow-not-linked", true);
+    let silence = gst::ElementFactory::make("audiotestsrc", Some("program_silence"))?;
+    silence.set_property_from_str("wave", "silence");
+    silence.set_property("is-live", true);
+    pipeline.add_many(&[&silence, &audio_mixer, &audio_convert, &audio_tee])?;
+    gst::Element::link_many(&[&silence, &audio_mixer, &audio_convert, &audio_tee])?;
+    scene_switcher.registry_mut().set_audio_mixer(audio_mixer);
+
+    // Scenes come from the saved collection, or a screen + camera default
+    let camera = default_camera();
+    if camera.is_none() {
+        println!("No camera found, continuing without webcam.");
+    }
+    let collection_path = SceneCollection::default_path();
+    // A collection that fails to load is an error rather than silently replaced
+    // by the default, which would be saved over it on the next edit
+    let collection = if collection_path.exists() {
+        SceneCollection::load_from_file(&collection_path).map_err(|e| {
+            anyhow!("{}; fix or move the file to start with the default scenes", e)
+        })?
+    } else {
+        SceneCollection::default_collection(camera.as_ref())
+    };
+    *ACTIVE_CAMERA.lock().unwrap() = camera;
+    set_canvas_caps(&canvas_caps, collection.canvas);
+    scene_switcher.load_collection(&collection)?;
+
+    // Zoom-and-pan follow mode on the screen pads
+    let zoom_follow = ZoomFollow::new(&main_context);
+    add_screen_zoom_targets(&scene_switcher, &compositor, &zoom_follow);
+
+    // Saved overlays go on top of the program
+    overlay_mgr.load_stack(&OverlayStack::load_or_default())?;
 
     // Multi-streaming
     let stream_mgr = MultiStreamingManager::new(pipeline.clone(), 4000, 1000, 8000);
@@ -99,39 +140,302 @@ pub fn init_pipeline_with_advanced_features() -> Result<gst::Pipeline> {
 
     unsafe {
         GLOBAL_PIPELINE = Some(pipeline.clone());
-        GLOBAL_SCENE_SWITCHER = Some(scene_switcher);
-        GLOBAL_OVERLAY_MANAGER = Some(overlay_mgr);
-        GLOBAL_STREAMING_MANAGER = Some(stream_mgr);
+        GLOBAL_ZOOM_FOLLOW = Some(zoom_follow);
     }
+    SCENE_SWITCHER.with(|s| *s.borrow_mut() = Some(scene_switcher));
+    OVERLAY_MANAGER.with(|m| *m.borrow_mut() = Some(overlay_mgr));
+    AUTOMATION.with(|a| *a.borrow_mut() = Some(AutomationEngine::new(AutomationConfig::load_or_default())));
+    STREAMING_MANAGER.with(|m| *m.borrow_mut() = Some(stream_mgr));
+
+    // Automatic scene switching runs on the main loop, next to the GUI
+    main_context.spawn_local(async {
+        loop {
+            glib::timeout_future(AUTOMATION_INTERVAL).await;
+            with_automation(|engine| with_scene_switcher(|switcher| engine.tick(switcher)));
+        }
+    });
 
     println!("Advanced pipeline initialized.");
     Ok(pipeline)
 }
 
+/// Replace the live scenes with a collection (e.g. an imported one).
+pub fn load_scene_collection(collection: &SceneCollection) -> Result<()> {
+    let pipeline = get_global_pipeline().ok_or_else(|| anyhow!("Pipeline not initialized"))?;
+    if let Some(caps) = pipeline.by_name("canvas_caps") {
+        set_canvas_caps(&caps, collection.canvas);
+    }
+    with_scene_switcher(|switcher| {
+        switcher.load_collection(collection)?;
+        if let (Some(zoom), Some(comp)) = (get_global_zoom_follow(), pipeline.by_name("comp")) {
+            add_screen_zoom_targets(switcher, &comp, zoom);
+        }
+        Ok(())
+    })
+    .ok_or_else(|| anyhow!("Pipeline not initialized"))?
+}
+
+fn set_canvas_caps(capsfilter: &gst::Element, canvas: Canvas) {
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("width", canvas.width)
+        .field("height", canvas.height)
+        .build();
+    capsfilter.set_property("caps", &caps);
+}
+
+// Every screen/window source gets zoomed around its scene geometry
+fn add_screen_zoom_targets(switcher: &SceneSwitcher, compositor: &gst::Element, zoom: &ZoomFollow) {
+    let is_screen = |id: &str| {
+        matches!(
+            switcher.registry().get(id).map(|def| &def.kind),
+            Some(SourceKind::Screen) | Some(SourceKind::Window)
+        )
+    };
+    for scene in switcher.scenes() {
+        for src in scene.sources.iter().filter(|s| is_screen(&s.source_id)) {
+            if let Some(pad) = compositor.static_pad(&format!("sink_{}", src.pad_index)) {
+                zoom.add_target(&pad, (src.x, src.y, src.width, src.height), session_cursor(&src.source_id));
+            }
+        }
+    }
+}
+
 // Global accessors
 pub fn get_global_pipeline() -> Option<&'static gst::Pipeline> {
     unsafe { GLOBAL_PIPELINE.as_ref() }
 }
-pub fn get_global_scene_switcher() -> Option<&'static SceneSwitcher> {
-    unsafe { GLOBAL_SCENE_SWITCHER.as_ref() }
+pub fn get_global_zoom_follow() -> Option<&'static ZoomFollow> {
+    unsafe { GLOBAL_ZOOM_FOLLOW.as_ref() }
+}
+pub fn get_active_camera() -> Option<CameraConfig> {
+    ACTIVE_CAMERA.lock().unwrap().clone()
 }
-pub fn get_global_overlay_manager() -> Option<&'static OverlayManager> {
-    unsafe { GLOBAL_OVERLAY_MANAGER.as_ref() }
+
+/// Run `f` with the scene switcher; None until the pipeline is initialized.
+/// Main thread only, and `f` must not call back into `with_scene_switcher`.
+pub fn with_scene_switcher<R>(f: impl FnOnce(&mut SceneSwitcher) -> R) -> Option<R> {
+    SCENE_SWITCHER.with(|s| s.borrow_mut().as_mut().map(f))
 }
-pub fn get_global_streaming_manager() -> Option<&'static MultiStreamingManager> {
-    unsafe { GLOBAL_STREAMING_MANAGER.as_ref() }
+
+/// Run `f` with the program's overlay manager, like `with_scene_switcher`.
+pub fn with_overlay_manager<R>(f: impl FnOnce(&mut OverlayManager) -> R) -> Option<R> {
+    OVERLAY_MANAGER.with(|m| m.borrow_mut().as_mut().map(f))
 }
 
-// -- Recording pipeline for file output (start/stop/pause/resume) --
+/// Run `f` with the scene automation engine, like `with_scene_switcher`.
+pub fn with_automation<R>(f: impl FnOnce(&mut AutomationEngine) -> R) -> Option<R> {
+    AUTOMATION.with(|a| a.borrow_mut().as_mut().map(f))
+}
 
-pub fn start_recording_with_audio_sources(
-    audio_sources: Vec<String>,
+/// Run `f` with the streaming outputs, like `with_scene_switcher`.
+pub fn with_streaming_manager<R>(f: impl FnOnce(&mut MultiStreamingManager) -> R) -> Option<R> {
+    STREAMING_MANAGER.with(|m| m.borrow_mut().as_mut().map(f))
+}
+
+// -- Program audio inputs: capture devices mixed into what recordings and streams carry --
+
+struct ProgramAudioInput {
+    source: String,
+    /// pwaudiosrc ! audioconvert ! audioresample ! queue
+    bin: gst::Element,
+    mixer_pad: gst::Pad,
+}
+
+/// Mix exactly `sources` (PipeWire node names) into the program audio.
+/// Inputs already mixed in keep running; the others are taken out.
+pub fn set_program_audio_inputs(sources: &[String]) -> Result<()> {
+    let pipeline = get_global_pipeline().ok_or_else(|| anyhow!("Pipeline not initialized"))?;
+    let mixer = pipeline
+        .by_name("program_mixer")
+        .ok_or_else(|| anyhow!("No program audio mixer in the pipeline"))?;
+    PROGRAM_AUDIO_INPUTS.with(|inputs| {
+        let mut inputs = inputs.borrow_mut();
+        let (kept, removed): (Vec<_>, Vec<_>) = inputs.drain(..).partition(|i| sources.contains(&i.source));
+        *inputs = kept;
+        for input in removed {
+            let _ = input.bin.set_state(gst::State::Null);
+            let _ = pipeline.remove(&input.bin);
+            mixer.release_request_pad(&input.mixer_pad);
+            println!("Audio input '{}' left the program.", input.source);
+        }
+        for source in sources {
+            if inputs.iter().any(|i| &i.source == source) {
+                continue;
+            }
+            let bin = gst::parse_bin_from_description(
+                &format!(
+                    "pwaudiosrc target-object=\"{}\" ! audioconvert ! audioresample ! queue",
+                    source
+                ),
+                true,
+            )
+            .map_err(|e| anyhow!("Failed to create audio input {}: {}", source, e))?;
+            let mixer_pad = mixer
+                .request_pad_simple("sink_%u")
+                .ok_or_else(|| anyhow!("Audio mixer refused a new sink pad"))?;
+            pipeline.add(&bin)?;
+            let linked = bin
+                .static_pad("src")
+                .ok_or_else(|| anyhow!("Audio input has no src pad"))
+                .and_then(|src| src.link(&mixer_pad).map_err(|e| anyhow!("Failed to link audio input: {:?}", e)))
+                .and_then(|_| bin.sync_state_with_parent().map_err(|e| anyhow!("Failed to start audio input: {:?}", e)));
+            if let Err(e) = linked {
+                let _ = bin.set_state(gst::State::Null);
+                let _ = pipeline.remove(&bin);
+                mixer.release_request_pad(&mixer_pad);
+                return Err(e);
+            }
+            println!("Audio input '{}' joined the program.", source);
+            inputs.push(ProgramAudioInput {
+                source: source.clone(),
+                bin: bin.upcast(),
+                mixer_pad,
+            });
+        }
+        Ok(())
+    })
+}
+
+// -- Audio levels: measured in a pipeline of their own, recording or not --
+
+// How often each source reports its peak
+const LEVEL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Measures the peak levels of some audio sources into `VOLUME_DATA` while
+/// it is alive. Dropping it stops the measuring and removes its sources'
+/// values, so no stale peak is left behind.
+pub struct LevelMonitor {
+    pipeline: gst::Pipeline,
+    sources: Vec<String>,
+    _watch: gst::bus::BusWatchGuard,
+}
+
+/// Start measuring `sources` (PipeWire node names). Main thread only.
+pub fn monitor_audio_levels(sources: &[String]) -> Result<LevelMonitor> {
+    let mut sources = sources.to_vec();
+    sources.sort();
+    sources.dedup();
+    let pipeline = gst::Pipeline::new(Some("level_monitor"));
+    for source in &sources {
+        let src = gst::ElementFactory::make("pwaudiosrc", None)?;
+        src.set_property("target-object", source);
+        let convert = gst::ElementFactory::make("audioconvert", None)?;
+        // Named after the source, so its messages say whose peak they carry
+        let level = gst::ElementFactory::make("level", Some(source))?;
+        level.set_property("interval", LEVEL_INTERVAL.as_nanos() as u64);
+        level.set_property("post-messages", true);
+        let sink = gst::ElementFactory::make("fakesink", None)?;
+        sink.set_property("sync", false);
+        pipeline.add_many(&[&src, &convert, &level, &sink])?;
+        gst::Element::link_many(&[&src, &convert, &level, &sink])?;
+    }
+
+    let bus = pipeline.bus().ok_or_else(|| anyhow!("Level monitor has no bus"))?;
+    let watch = bus.add_watch_local(|_, msg| {
+        match msg.view() {
+            gst::MessageView::Element(element) => {
+                let peak = element
+                    .structure()
+                    .filter(|s| s.name() == "level")
+                    .and_then(|s| s.get::<glib::ValueArray>("peak").ok())
+                    .map(|peaks| {
+                        peaks
+                            .iter()
+                            .filter_map(|v| v.get::<f64>().ok())
+                            .fold(f64::NEG_INFINITY, f64::max)
+                    });
+                if let (Some(peak), Some(src)) = (peak, msg.src()) {
+                    VOLUME_DATA.lock().unwrap().insert(src.name().to_string(), peak);
+                }
+            }
+            gst::MessageView::Error(err) => {
+                eprintln!("Level monitor error: {}", err.error());
+            }
+            _ => {}
+        }
+        glib::Continue(true)
+    })?;
+    pipeline.set_state(gst::State::Playing)?;
+    Ok(LevelMonitor {
+        pipeline,
+        sources,
+        _watch: watch,
+    })
+}
+
+impl Drop for LevelMonitor {
+    fn drop(&mut self) {
+        let _ = self.pipeline.set_state(gst::State::Null);
+        let mut levels = VOLUME_DATA.lock().unwrap();
+        for source in &self.sources {
+            levels.remove(source);
+        }
+    }
+}
+
+// -- Recording: a branch of the program tee, written to a file (start/stop/pause/resume) --
+
+/// A recording in progress. It encodes branches of `program_tee` and
+/// `program_audio_tee`, so the file shows exactly what Program shows and
+/// plays, at the recording's own size.
+struct Recording {
+    bin: gst::Bin,
+    /// Pads of the program tees feeding the bin: video, then audio
+    tee_pads: Vec<gst::Pad>,
+    /// Where streams enter the recording; their offsets start the file at 0 and leave out pauses
+    inputs: Vec<gst::Pad>,
+    /// Pipeline running time when the recording started
+    started: gst::ClockTime,
+    /// Running time the current pause began at
+    paused_at: Option<gst::ClockTime>,
+    /// Time spent paused so far
+    paused: gst::ClockTime,
+    /// Inputs drop buffers while set
+    dropping: Arc<AtomicBool>,
+}
+
+impl Recording {
+    fn elapsed(&self) -> Duration {
+        let now = self
+            .paused_at
+            .or_else(|| self.bin.current_running_time())
+            .unwrap_or(self.started);
+        Duration::from_nanos(now.saturating_sub(self.started + self.paused).nseconds())
+    }
+
+    fn apply_offsets(&self) {
+        let offset = -((self.started + self.paused).nseconds() as i64);
+        for pad in &self.inputs {
+            pad.set_offset(offset);
+        }
+    }
+}
+
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+// Longest wait for the muxer to finish the file after stopping
+const RECORDING_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Record the program to `filename`.`format`. Its audio is the program
+/// audio: the inputs picked with `set_program_audio_inputs` and media sources.
+pub fn start_recording(
     format: String,
     filename: String,
     resolution: Option<(u32, u32)>,
     framerate: Option<u32>,
     bitrate: Option<u32>,
-) {
+) -> Result<()> {
+    if RECORDING.lock().unwrap().is_some() {
+        return Err(anyhow!("A recording is already running."));
+    }
+    let pipeline = get_global_pipeline().ok_or_else(|| anyhow!("Pipeline not initialized"))?;
+    let program_tee = pipeline
+        .by_name("program_tee")
+        .ok_or_else(|| anyhow!("No program tee in the pipeline"))?;
+    let audio_tee = pipeline
+        .by_name("program_audio_tee")
+        .ok_or_else(|| anyhow!("No program audio tee in the pipeline"))?;
+
     let (width, height) = resolution.unwrap_or((1280, 720));
     let fps = framerate.unwrap_or(30);
     let br = bitrate.unwrap_or(4096);
@@ -146,90 +450,233 @@ pub fn start_recording_with_audio_sources(
         }
     };
 
-    let mut audio_parts = String::new();
-    for source in &audio_sources {
-        audio_parts.push_str(&format!(
-            "pwaudiosrc target-object={} ! level name=level_{} interval=100000000 ! \
-             audioconvert ! audioresample ! queue ! {} ! mux. ",
-            source, source, audio_enc
-        ));
-    }
+    // Compliance stamp, on the recording only
+    let stamp = RecordingStamp::load_or_default();
+    let stamp_part = if stamp.enabled { "videoconvert name=rec_stamp_out ! " } else { "" };
+
+    // Text overlays and captions as a subtitle stream, muxed only into MKV
+    let subtitle_config = SubtitleConfig::load_or_default();
+    let subtitle_part = if subtitle_config.enabled && subtitle_config.embed_mkv && format == "mkv" {
+        "appsrc name=rec_subtitles format=time is-live=true caps=\"text/x-raw,format=(string)utf8\" ! mux. "
+    } else {
+        ""
+    };
 
-    // Additional example: a second feed from v4l2src overlay
-    let pipeline_str = format!(
+    let bin_str = format!(
         concat!(
-            "compositor name=comp sink_1::xpos=100 sink_1::ypos=100 ! ",
-            "videoconvert ! x264enc bitrate={br} tune=zerolatency speed-preset=ultrafast ! queue ! mux. ",
-            "pipewiresrc ! video/x-raw,width={width},height={height},framerate={fps}/1 ! comp.sink_0 ",
-            "v4l2src device=/dev/video0 ! video/x-raw,width=320,height=240 ! comp.sink_1 ",
-            "{audio_parts} ",
-            "{muxer} ! filesink location=\"{filename}.{ext}\""
+            "queue name=rec_queue ! videoconvert ! videoscale ! videorate ! ",
+            "video/x-raw,width={width},height={height},framerate={fps}/1 ! ",
+            "videoconvert name=rec_convert ! {stamp_part}x264enc bitrate={br} tune=zerolatency speed-preset=ultrafast ! queue ! mux. ",
+            "queue name=rec_audio_queue ! audioconvert ! audioresample ! {audio_enc} ! queue ! mux. ",
+            "{subtitle_part}",
+            "{muxer} ! filesink name=rec_sink location=\"{filename}.{ext}\""
         ),
         br=br,
         width=width,
         height=height,
         fps=fps,
-        audio_parts=audio_parts,
+        stamp_part=stamp_part,
+        audio_enc=audio_enc,
+        subtitle_part=subtitle_part,
         muxer=muxer,
         filename=filename,
         ext=format,
     );
+    let bin = gst::parse_bin_from_description(&bin_str, false)
+        .map_err(|e| anyhow!("Failed to create recording branch: {}", e))?;
+    let queue_pad = bin
+        .by_name("rec_queue")
+        .and_then(|q| q.static_pad("sink"))
+        .ok_or_else(|| anyhow!("Recording queue has no sink pad"))?;
+    let video_in = gst::GhostPad::with_target(Some("sink"), &queue_pad)?;
+    bin.add_pad(&video_in)?;
+    let audio_queue_pad = bin
+        .by_name("rec_audio_queue")
+        .and_then(|q| q.static_pad("sink"))
+        .ok_or_else(|| anyhow!("Recording audio queue has no sink pad"))?;
+    let audio_in = gst::GhostPad::with_target(Some("audio_sink"), &audio_queue_pad)?;
+    bin.add_pad(&audio_in)?;
 
-    gst::init().ok();
-    let pipeline = gst::parse_launch(&pipeline_str)
-        .expect("Failed to create recording pipeline")
-        .downcast::<gst::Pipeline>()
-        .unwrap();
-
-    // Watch for audio levels
-    let bus = pipeline.bus().unwrap();
-    bus.add_watch(move |_, msg| {
-        if let gst::MessageView::Element(elem) = msg.view() {
-            if let Some(structure) = elem.structure() {
-                if structure.name() == "level" {
-                    if let Ok(peaks) = structure.get::<gst::List>("peak") {
-                        let elem_name = elem.src().map(|s| s.path_string()).unwrap_or_default();
-                        if elem_name.starts_with("level_") {
-                            let source_name = elem_name.trim_start_matches("level_").to_string();
-                            if let Some(first_peak) = peaks.iter().next() {
-                                if let Ok(peak_val) = first_peak.get::<f64>() {
-                                    VOLUME_DATA.lock().unwrap().insert(source_name, peak_val);
-                                }
-                            }
-                        }
-                    }
-                }
+    if stamp.enabled {
+        let recording_id = glib::uuid_string_random().to_string();
+        match add_recording_stamp(&bin, &stamp, &recording_id) {
+            Ok(overlays) => {
+                RECORDING_OVERLAY_MANAGER.with(|m| *m.borrow_mut() = Some(overlays));
+                println!("Recording ID: {}", recording_id);
             }
+            Err(e) => eprintln!("Recording without stamp: {:?}", e),
         }
-        glib::Continue(true)
-    }).unwrap();
+    }
+
+    let inputs: Vec<gst::Pad> = vec![video_in.clone().upcast(), audio_in.clone().upcast()];
+    let dropping = Arc::new(AtomicBool::new(false));
+    for pad in &inputs {
+        let dropping = dropping.clone();
+        pad.add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+            if dropping.load(Ordering::Relaxed) {
+                gst::PadProbeReturn::Drop
+            } else {
+                gst::PadProbeReturn::Ok
+            }
+        });
+    }
+
+    let started = pipeline
+        .current_running_time()
+        .ok_or_else(|| anyhow!("The program is not running"))?;
+    let video_tee_pad = program_tee
+        .request_pad_simple("src_%u")
+        .ok_or_else(|| anyhow!("Program tee refused a new src pad"))?;
+    let audio_tee_pad = match audio_tee.request_pad_simple("src_%u") {
+        Some(pad) => pad,
+        None => {
+            program_tee.release_request_pad(&video_tee_pad);
+            return Err(anyhow!("Program audio tee refused a new src pad"));
+        }
+    };
+    let recording = Recording {
+        bin: bin.clone(),
+        tee_pads: vec![video_tee_pad, audio_tee_pad],
+        inputs,
+        started,
+        paused_at: None,
+        paused: gst::ClockTime::ZERO,
+        dropping,
+    };
+    recording.apply_offsets();
+
+    pipeline.add(&bin)?;
+    let linked = recording.tee_pads[0]
+        .link(&video_in)
+        .and_then(|_| recording.tee_pads[1].link(&audio_in));
+    if let Err(e) = linked {
+        remove_recording_branch(&Arc::new(Mutex::new(Some((bin, recording.tee_pads)))));
+        return Err(anyhow!("Failed to link recording: {:?}", e));
+    }
+
+    if subtitle_config.enabled {
+        let appsrc = bin
+            .by_name("rec_subtitles")
+            .and_then(|e| e.downcast::<gst_app::AppSrc>().ok());
+        subtitles::begin(subtitle_config, &filename, appsrc);
+    }
 
-    pipeline.set_state(gst::State::Playing).unwrap();
-    *RECORDING_PIPELINE.lock().unwrap() = Some(pipeline);
+    *RECORDING.lock().unwrap() = Some(recording);
+    if let Err(e) = bin.sync_state_with_parent() {
+        stop_recording();
+        return Err(anyhow!("Failed to start recording: {:?}", e));
+    }
 
-    println!("Recording pipeline started to file: {}.{}", filename, format);
+    println!("Recording started to file: {}.{}", filename, format);
+    Ok(())
 }
 
+// Stamp of the recording branch: rec_convert ! overlays... ! rec_stamp_out
+fn add_recording_stamp(bin: &gst::Bin, stamp: &RecordingStamp, recording_id: &str) -> Result<OverlayManager> {
+    let upstream = bin.by_name("rec_convert").ok_or_else(|| anyhow!("Recording converter not found"))?;
+    let downstream = bin.by_name("rec_stamp_out").ok_or_else(|| anyhow!("Recording stamp output not found"))?;
+    upstream.unlink(&downstream);
+    stamp.attach(bin, &upstream, &downstream, recording_id).map_err(|e| {
+        // Put the plain path back so the recording still works
+        let _ = upstream.link(&downstream);
+        e
+    })
+}
+
+/// Finish the file: the branch is cut from the program tee and drained with
+/// EOS, then removed once the muxer is done.
 pub fn stop_recording() {
-    let mut guard = RECORDING_PIPELINE.lock().unwrap();
-    if let Some(pipe) = guard.take() {
-        pipe.set_state(gst::State::Null).unwrap();
-        println!("Recording pipeline stopped.");
+    // Taken out first: overlay draw callbacks ask for the recording time
+    let recording = RECORDING.lock().unwrap().take();
+    let recording = match recording {
+        Some(r) => r,
+        None => return,
+    };
+    if let Err(e) = subtitles::finish(recording.elapsed()) {
+        eprintln!("Failed to write subtitles: {:?}", e);
     }
+    RECORDING_OVERLAY_MANAGER.with(|m| *m.borrow_mut() = None);
+    // EOS must reach the muxer, paused or not
+    recording.dropping.store(false, Ordering::Relaxed);
+
+    let Recording { bin, tee_pads, .. } = recording;
+    let pending = Arc::new(Mutex::new(Some((bin.clone(), tee_pads.clone()))));
+
+    // The muxer passes EOS on once the file is complete
+    if let Some(sink_pad) = bin.by_name("rec_sink").and_then(|s| s.static_pad("sink")) {
+        let pending = pending.clone();
+        sink_pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+            match info.data {
+                Some(gst::PadProbeData::Event(ref ev)) if ev.type_() == gst::EventType::Eos => {
+                    let pending = pending.clone();
+                    glib::MainContext::default().invoke(move || remove_recording_branch(&pending));
+                    gst::PadProbeReturn::Remove
+                }
+                _ => gst::PadProbeReturn::Ok,
+            }
+        });
+    }
+    let pending_timeout = pending.clone();
+    glib::timeout_add_local_once(RECORDING_DRAIN_TIMEOUT, move || remove_recording_branch(&pending_timeout));
+
+    // Cut video and audio off between two buffers, then end each input
+    for tee_pad in &tee_pads {
+        tee_pad.add_probe(gst::PadProbeType::BLOCK_DOWNSTREAM, move |pad, _| {
+            if let Some(peer) = pad.peer() {
+                let _ = pad.unlink(&peer);
+                peer.send_event(gst::event::Eos::new());
+            }
+            gst::PadProbeReturn::Remove
+        });
+    }
+    println!("Recording stopping...");
+}
+
+// Take the drained (or timed out) branch out of the pipeline; runs once
+fn remove_recording_branch(pending: &Arc<Mutex<Option<(gst::Bin, Vec<gst::Pad>)>>>) {
+    let (bin, tee_pads) = match pending.lock().unwrap().take() {
+        Some(branch) => branch,
+        None => return,
+    };
+    let _ = bin.set_state(gst::State::Null);
+    if let Some(pipeline) = get_global_pipeline() {
+        let _ = pipeline.remove(&bin);
+    }
+    for tee_pad in &tee_pads {
+        if let Some(tee) = tee_pad.parent_element() {
+            tee.release_request_pad(tee_pad);
+        }
+    }
+    println!("Recording stopped.");
 }
 
+/// How long the current recording has been running, pauses excluded.
+pub fn recording_elapsed() -> Option<Duration> {
+    RECORDING.lock().unwrap().as_ref().map(Recording::elapsed)
+}
+
+/// Stop writing frames until `resume_recording`; the program keeps running
+/// and the pause is left out of the file.
 pub fn pause_recording() {
-    let guard = RECORDING_PIPELINE.lock().unwrap();
-    if let Some(pipe) = guard.as_ref() {
-        pipe.set_state(gst::State::Paused).unwrap();
-        println!("Recording pipeline paused.");
+    let mut guard = RECORDING.lock().unwrap();
+    if let Some(rec) = guard.as_mut() {
+        if rec.paused_at.is_none() {
+            rec.paused_at = Some(rec.bin.current_running_time().unwrap_or(rec.started));
+            rec.dropping.store(true, Ordering::Relaxed);
+            println!("Recording paused.");
+        }
     }
 }
 
 pub fn resume_recording() {
-    let guard = RECORDING_PIPELINE.lock().unwrap();
-    if let Some(pipe) = guard.as_ref() {
-        pipe.set_state(gst::State::Playing).unwrap();
-        println!("Recording pipeline resumed.");
+    let mut guard = RECORDING.lock().unwrap();
+    if let Some(rec) = guard.as_mut() {
+        if let Some(at) = rec.paused_at.take() {
+            let now = rec.bin.current_running_time().unwrap_or(at);
+            rec.paused += now.saturating_sub(at);
+            rec.apply_offsets();
+            rec.dropping.store(false, Ordering::Relaxed);
+            println!("Recording resumed.");
+        }
     }
 }
@@ -0,0 +1,346 @@
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use anyhow::{anyhow, Result};
+use std::env;
+
+/// Environment override for the camera: "none", "test" or a V4L2 device path.
+pub const CAMERA_ENV_VAR: &str = "WAYCORD_CAMERA";
+
+/// A single capture mode advertised by a camera (one caps structure).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraMode {
+    /// Raw pixel format (e.g. "YUY2", "NV12") or "MJPG" for image/jpeg.
+    pub format: String,
+    pub width: i32,
+    pub height: i32,
+    /// Framerate as numerator / denominator
+    pub framerate: (i32, i32),
+}
+
+impl CameraMode {
+    pub fn new(format: &str, width: i32, height: i32, fps: i32) -> Self {
+        Self {
+            format: format.to_string(),
+            width,
+            height,
+            framerate: (fps, 1),
+        }
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.framerate.0 as f64 / self.framerate.1.max(1) as f64
+    }
+
+    /// Caps string used to pin the source to this mode.
+    pub fn caps_string(&self) -> String {
+        let (num, den) = self.framerate;
+        if self.format == "MJPG" {
+            format!("image/jpeg,width={},height={},framerate={}/{}", self.width, self.height, num, den)
+        } else {
+            format!(
+                "video/x-raw,format={},width={},height={},framerate={}/{}",
+                self.format, self.width, self.height, num, den
+            )
+        }
+    }
+
+    /// Human readable label for dropdowns, e.g. "1280x720 @ 30 fps (MJPG)".
+    pub fn label(&self) -> String {
+        format!("{}x{} @ {:.0} fps ({})", self.width, self.height, self.fps(), self.format)
+    }
+}
+
+/// A V4L2 camera found by the device monitor.
+#[derive(Debug, Clone)]
+pub struct CameraDevice {
+    pub name: String,
+    pub device_path: String,
+    pub modes: Vec<CameraMode>,
+}
+
+impl CameraDevice {
+    /// Pick a sensible default: the largest mode up to 1280x720, preferring higher fps.
+    pub fn preferred_mode(&self) -> Option<CameraMode> {
+        self.modes
+            .iter()
+            .filter(|m| m.width <= 1280 && m.height <= 720)
+            .max_by(|a, b| {
+                (a.width * a.height)
+                    .cmp(&(b.width * b.height))
+                    .then(a.fps().partial_cmp(&b.fps()).unwrap_or(std::cmp::Ordering::Equal))
+            })
+            .or_else(|| self.modes.first())
+            .cloned()
+    }
+}
+
+/// Where camera frames come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraSource {
+    /// A V4L2 device node such as /dev/video0 (also works with v4l2loopback)
+    V4l2(String),
+    /// A videotestsrc stand-in, handy for machines without a camera
+    TestPattern,
+}
+
+/// The user's camera selection: device plus the mode to capture in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraConfig {
+    pub source: CameraSource,
+    pub mode: CameraMode,
+}
+
+impl CameraConfig {
+    pub fn v4l2(device_path: &str, mode: CameraMode) -> Self {
+        Self {
+            source: CameraSource::V4l2(device_path.to_string()),
+            mode,
+        }
+    }
+
+    pub fn test_pattern(width: i32, height: i32, fps: i32) -> Self {
+        Self {
+            source: CameraSource::TestPattern,
+            mode: CameraMode::new("I420", width, height, fps),
+        }
+    }
+
+    /// gst-launch style description of the camera branch, ending in raw video.
+    pub fn launch_fragment(&self) -> String {
+        let src = match &self.source {
+            CameraSource::V4l2(path) => format!("v4l2src device={}", quote_launch_value(path)),
+            CameraSource::TestPattern => "videotestsrc is-live=true pattern=ball".to_string(),
+        };
+        let decode = if self.mode.format == "MJPG" { " ! jpegdec" } else { "" };
+        format!("{} ! {}{} ! videoconvert", src, self.mode.caps_string(), decode)
+    }
+
+    /// Build the camera branch as a bin with a ghost src pad.
+    pub fn build_source(&self, name: &str) -> Result<gst::Element> {
+        let bin = gst::parse_bin_from_description(&self.launch_fragment(), true)
+            .map_err(|e| anyhow!("Failed to build camera source: {}", e))?;
+        bin.set_property("name", name);
+        Ok(bin.upcast())
+    }
+}
+
+/// Modes offered for the test-pattern stand-in.
+pub fn test_pattern_modes() -> Vec<CameraMode> {
+    vec![
+        CameraMode::new("I420", 320, 240, 30),
+        CameraMode::new("I420", 640, 480, 30),
+        CameraMode::new("I420", 1280, 720, 30),
+    ]
+}
+
+/// Enumerate V4L2 cameras and their supported caps through a DeviceMonitor.
+pub fn list_cameras() -> Vec<CameraDevice> {
+    if gst::init().is_err() {
+        return Vec::new();
+    }
+
+    let monitor = gst::DeviceMonitor::new();
+    monitor.add_filter(Some("Video/Source"), None);
+    if monitor.start().is_err() {
+        eprintln!("Failed to start video device monitor.");
+        return Vec::new();
+    }
+
+    let mut cameras = Vec::new();
+    for device in monitor.devices() {
+        let props = match device.properties() {
+            Some(p) => p,
+            None => continue,
+        };
+        // Only keep V4L2 devices; PipeWire also announces cameras through its own provider
+        let api = props.get::<String>("device.api").unwrap_or_default();
+        if api != "v4l2" {
+            continue;
+        }
+        let device_path = match props.get::<String>("device.path") {
+            Ok(p) => p,
+            Err(_) => continue,
+        };
+
+        // Cameras that only advertise ranges still get one mode to start with
+        let mut modes = device.caps().map(|caps| modes_from_caps(&caps)).unwrap_or_default();
+        if modes.is_empty() {
+            modes.push(fallback_mode(device.caps().as_ref()));
+        }
+        cameras.push(CameraDevice {
+            name: device.display_name().to_string(),
+            device_path,
+            modes,
+        });
+    }
+    monitor.stop();
+
+    cameras
+}
+
+/// Resolve the default camera: honours WAYCORD_CAMERA, otherwise the first V4L2 device.
+/// Returns None when no camera is available, in which case the camera is left out.
+pub fn default_camera() -> Option<CameraConfig> {
+    match env::var(CAMERA_ENV_VAR).ok().as_deref() {
+        Some("none") => return None,
+        Some("test") => return Some(CameraConfig::test_pattern(320, 240, 30)),
+        Some(path) if !path.is_empty() => {
+            let mode = list_cameras()
+                .into_iter()
+                .find(|c| c.device_path == path)
+                .and_then(|c| c.preferred_mode())
+                .unwrap_or_else(|| CameraMode::new("YUY2", 320, 240, 30));
+            return Some(CameraConfig::v4l2(path, mode));
+        }
+        _ => {}
+    }
+
+    list_cameras().into_iter().find_map(|cam| {
+        cam.preferred_mode()
+            .map(|mode| CameraConfig::v4l2(&cam.device_path, mode))
+    })
+}
+
+// Internal: a double-quoted gst-launch value, so paths with spaces or quotes survive parsing
+fn quote_launch_value(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Internal: mode for a camera without fixed modes: 640x480 @ 30 in the
+// first format it advertises, YUY2 if it names none
+fn fallback_mode(caps: Option<&gst::Caps>) -> CameraMode {
+    let format = caps
+        .and_then(|caps| caps.iter().find_map(|s| match s.name() {
+            n if n == "image/jpeg" => Some("MJPG".to_string()),
+            n if n == "video/x-raw" => s.get::<String>("format").ok(),
+            _ => None,
+        }))
+        .unwrap_or_else(|| "YUY2".to_string());
+    CameraMode::new(&format, 640, 480, 30)
+}
+
+fn modes_from_caps(caps: &gst::Caps) -> Vec<CameraMode> {
+    let mut modes = Vec::new();
+    for s in caps.iter() {
+        let format = match s.name() {
+            n if n == "image/jpeg" => "MJPG".to_string(),
+            n if n == "video/x-raw" => match s.get::<String>("format") {
+                Ok(f) => f,
+                Err(_) => continue,
+            },
+            _ => continue,
+        };
+        // Ranges (e.g. virtual devices) are skipped; we only offer fixed sizes
+        let (width, height) = match (s.get::<i32>("width"), s.get::<i32>("height")) {
+            (Ok(w), Ok(h)) => (w, h),
+            _ => continue,
+        };
+
+        let mut rates = Vec::new();
+        if let Ok(rate) = s.get::<gst::Fraction>("framerate") {
+            rates.push((rate.numer(), rate.denom()));
+        } else if let Ok(list) = s.get::<gst::List>("framerate") {
+            for value in list.iter() {
+                if let Ok(rate) = value.get::<gst::Fraction>() {
+                    rates.push((rate.numer(), rate.denom()));
+                }
+            }
+        }
+
+        for framerate in rates {
+            let mode = CameraMode {
+                format: format.clone(),
+                width,
+                height,
+                framerate,
+            };
+            if !modes.contains(&mode) {
+                modes.push(mode);
+            }
+        }
+    }
+    modes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn caps(description: &str) -> gst::Caps {
+        gst::init().unwrap();
+        gst::Caps::from_str(description).unwrap()
+    }
+
+    #[test]
+    fn quote_launch_value_escapes_quotes_and_backslashes() {
+        assert_eq!(quote_launch_value("/dev/video0"), "\"/dev/video0\"");
+        assert_eq!(quote_launch_value("/dev/my camera"), "\"/dev/my camera\"");
+        assert_eq!(quote_launch_value("/dev/\"cam\""), "\"/dev/\\\"cam\\\"\"");
+        assert_eq!(quote_launch_value("a\\b"), "\"a\\\\b\"");
+    }
+
+    #[test]
+    fn quoted_paths_survive_launch_parsing() {
+        gst::init().unwrap();
+        for path in ["/tmp/my camera", "/tmp/\"quoted\" cam", "/tmp/back\\slash"] {
+            let description = format!("fakesrc ! filesink name=sink location={}", quote_launch_value(path));
+            let bin = gst::parse_bin_from_description(&description, false).unwrap();
+            let sink = bin.by_name("sink").unwrap();
+            assert_eq!(sink.property::<Option<String>>("location").as_deref(), Some(path));
+        }
+    }
+
+    #[test]
+    fn modes_from_caps_reads_fixed_sizes_and_rate_lists() {
+        let modes = modes_from_caps(&caps(
+            "video/x-raw, format=YUY2, width=640, height=480, framerate={ 30/1, 15/1 }; \
+             image/jpeg, width=1280, height=720, framerate=30/1",
+        ));
+        assert_eq!(
+            modes,
+            vec![
+                CameraMode::new("YUY2", 640, 480, 30),
+                CameraMode::new("YUY2", 640, 480, 15),
+                CameraMode::new("MJPG", 1280, 720, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn range_only_caps_get_the_fallback_mode() {
+        let caps = caps("video/x-raw, format=NV12, width=[ 1, 4096 ], height=[ 1, 2160 ], framerate=[ 1/1, 60/1 ]");
+        assert!(modes_from_caps(&caps).is_empty());
+        assert_eq!(fallback_mode(Some(&caps)), CameraMode::new("NV12", 640, 480, 30));
+        // A fixed size with only a framerate range offers no rate to pick either
+        let caps = self::caps("image/jpeg, width=1280, height=720, framerate=[ 1/1, 30/1 ]");
+        assert!(modes_from_caps(&caps).is_empty());
+        assert_eq!(fallback_mode(Some(&caps)), CameraMode::new("MJPG", 640, 480, 30));
+    }
+
+    #[test]
+    fn fallback_mode_defaults_to_yuy2() {
+        assert_eq!(fallback_mode(None), CameraMode::new("YUY2", 640, 480, 30));
+        assert_eq!(fallback_mode(Some(&caps("video/x-bayer, width=640, height=480"))), CameraMode::new("YUY2", 640, 480, 30));
+    }
+
+    #[test]
+    fn preferred_mode_picks_the_largest_up_to_720p_then_the_fastest() {
+        let device = |modes| CameraDevice {
+            name: "cam".to_string(),
+            device_path: "/dev/video0".to_string(),
+            modes,
+        };
+        let cam = device(vec![
+            CameraMode::new("YUY2", 640, 480, 30),
+            CameraMode::new("MJPG", 1280, 720, 15),
+            CameraMode::new("MJPG", 1280, 720, 30),
+            CameraMode::new("MJPG", 1920, 1080, 30),
+        ]);
+        assert_eq!(cam.preferred_mode(), Some(CameraMode::new("MJPG", 1280, 720, 30)));
+        // Only larger modes: the first one
+        let cam = device(vec![CameraMode::new("MJPG", 1920, 1080, 30), CameraMode::new("MJPG", 3840, 2160, 30)]);
+        assert_eq!(cam.preferred_mode(), Some(CameraMode::new("MJPG", 1920, 1080, 30)));
+        assert_eq!(device(Vec::new()).preferred_mode(), None);
+    }
+}
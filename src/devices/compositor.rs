@@ -0,0 +1,133 @@
+// Window queries against the running compositor. Wayland has no common API
+// for these, so only Hyprland (hyprctl) and Sway (swaymsg) are supported;
+// everything returns None elsewhere.
+
+use std::process::Command;
+
+/// The window with keyboard focus.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FocusedWindow {
+    pub title: String,
+    pub app_id: String,
+}
+
+/// A visible window and where it is, in compositor coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowGeometry {
+    pub title: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+enum Compositor {
+    Hyprland,
+    Sway,
+}
+
+fn detect() -> Option<Compositor> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        Some(Compositor::Hyprland)
+    } else if std::env::var_os("SWAYSOCK").is_some() {
+        Some(Compositor::Sway)
+    } else {
+        None
+    }
+}
+
+fn hyprctl(what: &str) -> Option<serde_json::Value> {
+    let out = Command::new("hyprctl").args([what, "-j"]).output().ok()?;
+    serde_json::from_slice(&out.stdout).ok()
+}
+
+fn sway_tree() -> Option<serde_json::Value> {
+    let out = Command::new("swaymsg").args(["-t", "get_tree"]).output().ok()?;
+    serde_json::from_slice(&out.stdout).ok()
+}
+
+/// The focused window, or None when it cannot be asked for.
+pub fn focused_window() -> Option<FocusedWindow> {
+    match detect()? {
+        Compositor::Hyprland => {
+            let json = hyprctl("activewindow")?;
+            Some(FocusedWindow {
+                title: json["title"].as_str().unwrap_or_default().to_string(),
+                app_id: json["class"].as_str().unwrap_or_default().to_string(),
+            })
+        }
+        Compositor::Sway => find_focused_node(&sway_tree()?),
+    }
+}
+
+/// Windows on the workspaces some output is showing, or None when they
+/// cannot be asked for.
+pub fn visible_windows() -> Option<Vec<WindowGeometry>> {
+    match detect()? {
+        Compositor::Hyprland => {
+            let shown: Vec<i64> = hyprctl("monitors")?
+                .as_array()?
+                .iter()
+                .filter_map(|m| m["activeWorkspace"]["id"].as_i64())
+                .collect();
+            let clients = hyprctl("clients")?;
+            Some(
+                clients
+                    .as_array()?
+                    .iter()
+                    .filter(|c| c["mapped"].as_bool() != Some(false) && c["hidden"].as_bool() != Some(true))
+                    .filter(|c| c["workspace"]["id"].as_i64().map(|id| shown.contains(&id)).unwrap_or(false))
+                    .map(|c| WindowGeometry {
+                        title: c["title"].as_str().unwrap_or_default().to_string(),
+                        x: c["at"][0].as_i64().unwrap_or(0) as i32,
+                        y: c["at"][1].as_i64().unwrap_or(0) as i32,
+                        width: c["size"][0].as_i64().unwrap_or(0) as i32,
+                        height: c["size"][1].as_i64().unwrap_or(0) as i32,
+                    })
+                    .collect(),
+            )
+        }
+        Compositor::Sway => {
+            let mut windows = Vec::new();
+            collect_sway_windows(&sway_tree()?, &mut windows);
+            Some(windows)
+        }
+    }
+}
+
+fn find_focused_node(node: &serde_json::Value) -> Option<FocusedWindow> {
+    if node["focused"].as_bool() == Some(true) {
+        let app_id = node["app_id"]
+            .as_str()
+            .or_else(|| node["window_properties"]["class"].as_str())
+            .unwrap_or_default();
+        return Some(FocusedWindow {
+            title: node["name"].as_str().unwrap_or_default().to_string(),
+            app_id: app_id.to_string(),
+        });
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[*key].as_array())
+        .flatten()
+        .find_map(find_focused_node)
+}
+
+fn collect_sway_windows(node: &serde_json::Value, windows: &mut Vec<WindowGeometry>) {
+    let is_window = node["pid"].is_number();
+    if is_window && node["visible"].as_bool() == Some(true) {
+        let rect = &node["rect"];
+        windows.push(WindowGeometry {
+            title: node["name"].as_str().unwrap_or_default().to_string(),
+            x: rect["x"].as_i64().unwrap_or(0) as i32,
+            y: rect["y"].as_i64().unwrap_or(0) as i32,
+            width: rect["width"].as_i64().unwrap_or(0) as i32,
+            height: rect["height"].as_i64().unwrap_or(0) as i32,
+        });
+    }
+    for key in ["nodes", "floating_nodes"] {
+        for child in node[key].as_array().into_iter().flatten() {
+            collect_sway_windows(child, windows);
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use anyhow::{anyhow, Result};
+use pipewire as pw;
+use pw::spa;
+use pw::spa::pod::serialize::PodSerializer;
+use pw::spa::pod::{ChoiceValue, Object, Pod, Property, PropertyFlags, Value};
+use pw::spa::utils::{Choice, ChoiceEnum, ChoiceFlags, Fraction, Id, Rectangle};
+use std::fs::{self, File};
+use std::io::{self, Cursor};
+use std::mem;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Largest cursor bitmap we ask the compositor for
+const MAX_CURSOR_SIZE: i32 = 256;
+
+// Linux input event codes (linux/input-event-codes.h)
+const EV_KEY: u16 = 0x01;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+
+/// How long a click stays in `CursorState::clicks`.
+pub const CLICK_LIFETIME: Duration = Duration::from_millis(600);
+
+// How often click readers check whether they should stop, in milliseconds
+const CLICK_POLL_MS: i32 = 250;
+
+// Cursor states fed by running metadata trackers, and whether anyone asked
+// for clicks; the input devices are read only while both are non-empty
+static CLICKS: Mutex<ClickCapture> = Mutex::new(ClickCapture {
+    highlight: false,
+    zoom: false,
+    targets: Vec::new(),
+    listener: None,
+});
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClickEvent {
+    pub x: f64,
+    pub y: f64,
+    pub button: MouseButton,
+    pub at: Instant,
+}
+
+/// Cursor image as premultiplied ARGB32 (cairo's native format).
+#[derive(Debug, Clone)]
+pub struct CursorBitmap {
+    pub width: i32,
+    pub height: i32,
+    pub stride: i32,
+    pub data: Vec<u8>,
+}
+
+/// Latest cursor information from the screen cast, in stream pixel coordinates.
+#[derive(Debug, Clone, Default)]
+pub struct CursorState {
+    pub x: f64,
+    pub y: f64,
+    pub visible: bool,
+    pub hotspot: (i32, i32),
+    pub bitmap: Option<CursorBitmap>,
+    /// Recent clicks, newest last
+    pub clicks: Vec<ClickEvent>,
+}
+
+pub type SharedCursorState = Arc<Mutex<CursorState>>;
+
+/// Follows the cursor metadata of a screen cast node on a background thread,
+/// until dropped.
+pub struct MetadataTracker {
+    quit: pw::channel::Sender<()>,
+    state: SharedCursorState,
+}
+
+impl Drop for MetadataTracker {
+    fn drop(&mut self) {
+        let _ = self.quit.send(());
+        let mut clicks = CLICKS.lock().unwrap();
+        clicks.targets.retain(|t| t.upgrade().map(|t| !Arc::ptr_eq(&t, &self.state)).unwrap_or(false));
+        clicks.update();
+    }
+}
+
+/// Follow the cursor metadata of a screen cast node on a background thread.
+/// Requires a session started with `CursorMode::Metadata`.
+pub fn spawn_metadata_tracker(fd: OwnedFd, node_id: u32, state: SharedCursorState) -> MetadataTracker {
+    let (quit, quit_receiver) = pw::channel::channel();
+    let thread_state = state.clone();
+    thread::spawn(move || {
+        if let Err(e) = run_metadata_tracker(fd, node_id, thread_state, quit_receiver) {
+            eprintln!("Cursor metadata tracker stopped: {:?}", e);
+        }
+    });
+    let mut clicks = CLICKS.lock().unwrap();
+    clicks.targets.push(Arc::downgrade(&state));
+    clicks.update();
+    MetadataTracker { quit, state }
+}
+
+fn run_metadata_tracker(
+    fd: OwnedFd,
+    node_id: u32,
+    state: SharedCursorState,
+    quit: pw::channel::Receiver<()>,
+) -> Result<()> {
+    pw::init();
+    let main_loop = pw::main_loop::MainLoop::new().map_err(|e| anyhow!("PipeWire main loop: {}", e))?;
+    let _quit = quit.attach(main_loop.loop_(), {
+        let main_loop = main_loop.clone();
+        move |_| main_loop.quit()
+    });
+    let context = pw::context::Context::new(&main_loop).map_err(|e| anyhow!("PipeWire context: {}", e))?;
+    let core = context.connect_fd(fd, None).map_err(|e| anyhow!("PipeWire connect: {}", e))?;
+
+    let stream = pw::stream::Stream::new(
+        &core,
+        "waycord-cursor",
+        pw::properties! {
+            *pw::keys::MEDIA_TYPE => "Video",
+            *pw::keys::MEDIA_CATEGORY => "Capture",
+            *pw::keys::MEDIA_ROLE => "Screen",
+        },
+    )
+    .map_err(|e| anyhow!("PipeWire stream: {}", e))?;
+
+    let _listener = stream
+        .add_local_listener_with_user_data(state)
+        .param_changed(|stream, _, id, param| {
+            // Once a format is fixed, ask for cursor metadata on the buffers
+            if id != spa::param::ParamType::Format.as_raw() || param.is_none() {
+                return;
+            }
+            let meta = serialize_pod(Object {
+                type_: spa::sys::SPA_TYPE_OBJECT_ParamMeta,
+                id: spa::sys::SPA_PARAM_Meta,
+                properties: vec![
+                    prop(spa::sys::SPA_PARAM_META_type, Value::Id(Id(spa::sys::SPA_META_Cursor))),
+                    prop(spa::sys::SPA_PARAM_META_size, Value::Int(cursor_meta_size(MAX_CURSOR_SIZE))),
+                ],
+            });
+            if let Some(pod) = Pod::from_bytes(&meta) {
+                let _ = stream.update_params(&mut [pod]);
+            }
+        })
+        .process(|stream, state| {
+            let buffer = unsafe { stream.dequeue_raw_buffer() };
+            if buffer.is_null() {
+                return;
+            }
+            unsafe {
+                read_cursor_meta(buffer, state);
+                stream.queue_raw_buffer(buffer);
+            }
+        })
+        .register()
+        .map_err(|e| anyhow!("PipeWire listener: {}", e))?;
+
+    let format = serialize_pod(video_format_object());
+    let format_pod = Pod::from_bytes(&format).ok_or_else(|| anyhow!("Invalid format pod"))?;
+    stream
+        .connect(
+            spa::utils::Direction::Input,
+            Some(node_id),
+            pw::stream::StreamFlags::AUTOCONNECT,
+            &mut [format_pod],
+        )
+        .map_err(|e| anyhow!("PipeWire stream connect: {}", e))?;
+
+    main_loop.run();
+    Ok(())
+}
+
+// Internal: copy the spa_meta_cursor (and its bitmap, when sent) into the shared state
+unsafe fn read_cursor_meta(buffer: *mut pw::sys::pw_buffer, state: &SharedCursorState) {
+    let spa_buffer = (*buffer).buffer;
+    if spa_buffer.is_null() {
+        return;
+    }
+    for i in 0..(*spa_buffer).n_metas as usize {
+        let meta = (*spa_buffer).metas.add(i);
+        if (*meta).type_ != spa::sys::SPA_META_Cursor || (*meta).data.is_null() {
+            continue;
+        }
+        let cursor = (*meta).data as *const spa::sys::spa_meta_cursor;
+        let mut st = state.lock().unwrap();
+        // id 0 means the cursor is not over the captured area
+        st.visible = (*cursor).id != 0;
+        if !st.visible {
+            return;
+        }
+        st.x = (*cursor).position.x as f64;
+        st.y = (*cursor).position.y as f64;
+        st.hotspot = ((*cursor).hotspot.x, (*cursor).hotspot.y);
+
+        // The bitmap is only sent when the cursor image changes
+        let offset = (*cursor).bitmap_offset as usize;
+        if offset >= mem::size_of::<spa::sys::spa_meta_cursor>() {
+            let bitmap = (cursor as *const u8).add(offset) as *const spa::sys::spa_meta_bitmap;
+            let (w, h) = ((*bitmap).size.width as i32, (*bitmap).size.height as i32);
+            let stride = (*bitmap).stride;
+            if w > 0 && h > 0 && stride > 0 {
+                let pixels = (bitmap as *const u8).add((*bitmap).offset as usize);
+                let mut data = std::slice::from_raw_parts(pixels, (stride * h) as usize).to_vec();
+                if (*bitmap).format == spa::sys::SPA_VIDEO_FORMAT_RGBA {
+                    // cairo wants BGRA in memory on little endian
+                    for px in data.chunks_exact_mut(4) {
+                        px.swap(0, 2);
+                    }
+                }
+                st.bitmap = Some(CursorBitmap {
+                    width: w,
+                    height: h,
+                    stride,
+                    data,
+                });
+            }
+        }
+        return;
+    }
+}
+
+fn cursor_meta_size(max: i32) -> i32 {
+    (mem::size_of::<spa::sys::spa_meta_cursor>()
+        + mem::size_of::<spa::sys::spa_meta_bitmap>()
+        + (max * max * 4) as usize) as i32
+}
+
+fn prop(key: u32, value: Value) -> Property {
+    Property {
+        key,
+        flags: PropertyFlags::empty(),
+        value,
+    }
+}
+
+fn video_format_object() -> Object {
+    let formats = [
+        spa::sys::SPA_VIDEO_FORMAT_BGRx,
+        spa::sys::SPA_VIDEO_FORMAT_BGRA,
+        spa::sys::SPA_VIDEO_FORMAT_RGBx,
+        spa::sys::SPA_VIDEO_FORMAT_RGBA,
+    ];
+    Object {
+        type_: spa::sys::SPA_TYPE_OBJECT_Format,
+        id: spa::sys::SPA_PARAM_EnumFormat,
+        properties: vec![
+            prop(spa::sys::SPA_FORMAT_mediaType, Value::Id(Id(spa::sys::SPA_MEDIA_TYPE_video))),
+            prop(spa::sys::SPA_FORMAT_mediaSubtype, Value::Id(Id(spa::sys::SPA_MEDIA_SUBTYPE_raw))),
+            prop(
+                spa::sys::SPA_FORMAT_VIDEO_format,
+                Value::Choice(ChoiceValue::Id(Choice(
+                    ChoiceFlags::empty(),
+                    ChoiceEnum::Enum {
+                        default: Id(formats[0]),
+                        alternatives: formats.iter().map(|f| Id(*f)).collect(),
+                    },
+                ))),
+            ),
+            prop(
+                spa::sys::SPA_FORMAT_VIDEO_size,
+                Value::Choice(ChoiceValue::Rectangle(Choice(
+                    ChoiceFlags::empty(),
+                    ChoiceEnum::Range {
+                        default: Rectangle { width: 1920, height: 1080 },
+                        min: Rectangle { width: 1, height: 1 },
+                        max: Rectangle { width: 8192, height: 8192 },
+                    },
+                ))),
+            ),
+            prop(
+                spa::sys::SPA_FORMAT_VIDEO_framerate,
+                Value::Choice(ChoiceValue::Fraction(Choice(
+                    ChoiceFlags::empty(),
+                    ChoiceEnum::Range {
+                        default: Fraction { num: 30, denom: 1 },
+                        min: Fraction { num: 0, denom: 1 },
+                        max: Fraction { num: 1000, denom: 1 },
+                    },
+                ))),
+            ),
+        ],
+    }
+}
+
+fn serialize_pod(object: Object) -> Vec<u8> {
+    PodSerializer::serialize(Cursor::new(Vec::new()), &Value::Object(object))
+        .map(|(cursor, _)| cursor.into_inner())
+        .unwrap_or_default()
+}
+
+/// Who wants mouse clicks. Reading input devices is opt-in: clicks are only
+/// collected while one of these is enabled and a metadata tracker runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClickUser {
+    /// Click rings drawn by the cursor overlay
+    Highlight,
+    /// Zoom follow's zoom-on-click
+    Zoom,
+}
+
+/// Opt in to (or out of) reading mouse buttons from /dev/input. Needs read
+/// access to the pointer devices (usually membership in the `input` group).
+pub fn set_click_capture(user: ClickUser, enabled: bool) {
+    let mut clicks = CLICKS.lock().unwrap();
+    match user {
+        ClickUser::Highlight => clicks.highlight = enabled,
+        ClickUser::Zoom => clicks.zoom = enabled,
+    }
+    clicks.update();
+}
+
+struct ClickCapture {
+    highlight: bool,
+    zoom: bool,
+    targets: Vec<Weak<Mutex<CursorState>>>,
+    listener: Option<ClickListener>,
+}
+
+impl ClickCapture {
+    // Start or stop the device readers to match the opt-ins and trackers
+    fn update(&mut self) {
+        self.targets.retain(|t| t.strong_count() > 0);
+        let wanted = (self.highlight || self.zoom) && !self.targets.is_empty();
+        if wanted && self.listener.is_none() {
+            self.listener = Some(ClickListener::start());
+        } else if !wanted {
+            self.listener = None;
+        }
+    }
+}
+
+// Reader threads, one per pointer device; they stop within CLICK_POLL_MS of a drop
+struct ClickListener {
+    stop: Arc<AtomicBool>,
+}
+
+impl ClickListener {
+    fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut watched = 0;
+        for path in pointer_devices() {
+            let file = match File::open(&path) {
+                Ok(f) => f,
+                Err(_) => continue,
+            };
+            let stop = stop.clone();
+            thread::spawn(move || read_click_events(file, stop));
+            watched += 1;
+        }
+        if watched == 0 {
+            eprintln!("No readable pointer devices; click highlighting disabled.");
+        }
+        Self { stop }
+    }
+}
+
+impl Drop for ClickListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Internal: event devices that report EV_KEY with BTN_LEFT (mice, touchpads).
+// Keyboards never get opened.
+fn pointer_devices() -> Vec<PathBuf> {
+    let entries = match fs::read_dir("/sys/class/input") {
+        Ok(e) => e,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .filter(|entry| {
+            let caps = entry.path().join("device/capabilities");
+            let read = |name: &str| fs::read_to_string(caps.join(name)).unwrap_or_default();
+            has_capability(&read("ev"), EV_KEY) && has_capability(&read("key"), BTN_LEFT)
+        })
+        .map(|entry| PathBuf::from("/dev/input").join(entry.file_name()))
+        .collect()
+}
+
+// Internal: test a bit of a sysfs capability bitmap, printed as hex longs with
+// the most significant word first
+fn has_capability(bitmap: &str, bit: u16) -> bool {
+    let word_bits = mem::size_of::<libc::c_ulong>() * 8;
+    let (index, shift) = (bit as usize / word_bits, bit as usize % word_bits);
+    bitmap
+        .split_whitespace()
+        .rev()
+        .nth(index)
+        .and_then(|word| u64::from_str_radix(word, 16).ok())
+        .map(|word| (word >> shift) & 1 == 1)
+        .unwrap_or(false)
+}
+
+fn read_click_events(file: File, stop: Arc<AtomicBool>) {
+    let size = mem::size_of::<libc::input_event>();
+    while !stop.load(Ordering::Relaxed) {
+        let mut pollfd = libc::pollfd {
+            fd: file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ready = unsafe { libc::poll(&mut pollfd, 1, CLICK_POLL_MS) };
+        if ready < 0 && io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+            break;
+        }
+        if ready <= 0 {
+            continue;
+        }
+        let mut event: libc::input_event = unsafe { mem::zeroed() };
+        let read = unsafe { libc::read(file.as_raw_fd(), &mut event as *mut _ as *mut libc::c_void, size) };
+        // Unplugged, or an error: this device is done
+        if read != size as isize {
+            break;
+        }
+        if event.type_ != EV_KEY || event.value != 1 {
+            continue;
+        }
+        let button = match event.code {
+            BTN_LEFT => MouseButton::Left,
+            BTN_RIGHT => MouseButton::Right,
+            BTN_MIDDLE => MouseButton::Middle,
+            _ => continue,
+        };
+
+        let targets: Vec<SharedCursorState> = CLICKS.lock().unwrap().targets.iter().filter_map(Weak::upgrade).collect();
+        let now = Instant::now();
+        for state in targets {
+            let mut st = state.lock().unwrap();
+            st.clicks.retain(|c| now.duration_since(c.at) < CLICK_LIFETIME);
+            let (x, y) = (st.x, st.y);
+            st.clicks.push(ClickEvent { x, y, button, at: now });
+        }
+    }
+}
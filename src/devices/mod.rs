@@ -1,2 +1,5 @@
 pub mod screen;
 pub mod audio;
+pub mod camera;
+pub mod cursor;
+pub mod compositor;
@@ -1,4 +1,42 @@
-use std::process::Command;
+use anyhow::{anyhow, Result};
+use gio::prelude::*;
+use glib::{ToVariant, Variant, VariantDict};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::cell::RefCell;
+use std::future::Future;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::pin::Pin;
+use std::process::{self, Command};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::devices::cursor::{spawn_metadata_tracker, MetadataTracker, SharedCursorState};
+
+const PORTAL_BUS: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const SCREENCAST_IFACE: &str = "org.freedesktop.portal.ScreenCast";
+
+// Object data key holding the PipeWire remote fd of a pipewiresrc
+const PIPEWIRE_FD_KEY: &str = "waycord-pipewire-fd";
+
+// How long the user gets to answer the portal's share dialog
+const PORTAL_TIMEOUT: Duration = Duration::from_secs(120);
+
+static TOKEN_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+type SessionCallback = Box<dyn FnOnce(Result<&ScreenCastSession>)>;
+
+thread_local! {
+    // Portal screen cast sessions by owner key, e.g. the ID of the screen source using it
+    static SCREEN_SESSIONS: RefCell<Vec<(String, Rc<ScreenCastSession>)>> = RefCell::new(Vec::new());
+    // Sessions the portal is still being asked for, with everyone waiting on them
+    static PENDING_SESSIONS: RefCell<Vec<(String, Vec<SessionCallback>)>> = RefCell::new(Vec::new());
+    // Cursor of each session key, handed out before the session exists
+    static SESSION_CURSORS: RefCell<Vec<(String, SharedCursorState)>> = RefCell::new(Vec::new());
+}
 
 /// Lists available Wayland outputs using the `wayland-info` tool.
 pub fn list_wayland_outputs() -> Vec<String> {
@@ -14,3 +52,508 @@ pub fn list_wayland_outputs() -> Vec<String> {
         .map(|line| line.to_string())
         .collect()
 }
+
+/// How the mouse cursor ends up in the captured stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorMode {
+    /// No cursor at all
+    Hidden,
+    /// Cursor drawn into the frames by the compositor
+    Embedded,
+    /// Cursor sent as PipeWire metadata; we draw it ourselves
+    Metadata,
+}
+
+impl CursorMode {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "hidden" => Some(Self::Hidden),
+            "embedded" => Some(Self::Embedded),
+            "metadata" => Some(Self::Metadata),
+            _ => None,
+        }
+    }
+
+    /// Bit used by the ScreenCast portal's cursor_mode option.
+    fn portal_bit(self) -> u32 {
+        match self {
+            Self::Hidden => 1,
+            Self::Embedded => 2,
+            Self::Metadata => 4,
+        }
+    }
+}
+
+/// What the user may pick in the portal's share dialog.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureTarget {
+    /// A monitor or a window
+    Any,
+    Monitor,
+    Window,
+}
+
+impl CaptureTarget {
+    /// Source types bitmask for SelectSources.
+    fn portal_types(self) -> u32 {
+        match self {
+            Self::Any => 1 | 2,
+            Self::Monitor => 1,
+            Self::Window => 2,
+        }
+    }
+}
+
+/// A ScreenCast portal session: the user picked a screen/window and we got a
+/// PipeWire node for it. Every consumer opens its own remote to the node.
+pub struct ScreenCastSession {
+    connection: gio::DBusConnection,
+    session_handle: String,
+    pub node_id: u32,
+    pub cursor_mode: CursorMode,
+    /// Where the captured output sits in compositor coordinates, and its
+    /// logical size, when the portal says (monitors only)
+    pub position: Option<(i32, i32)>,
+    pub size: Option<(i32, i32)>,
+    /// Cursor position and clicks, fed from the metadata in metadata mode
+    pub cursor: SharedCursorState,
+    // Cursor metadata follower, stopped with the session
+    cursor_tracker: Option<MetadataTracker>,
+}
+
+impl ScreenCastSession {
+    /// Ask the portal for a screen cast with the given cursor mode. Falls back to
+    /// embedded (or hidden) when the compositor does not offer the requested mode.
+    pub async fn start(cursor_mode: CursorMode) -> Result<Self> {
+        Self::start_for(cursor_mode, CaptureTarget::Any).await
+    }
+
+    /// Like `start`, limiting what the user can pick.
+    pub async fn start_for(cursor_mode: CursorMode, target: CaptureTarget) -> Result<Self> {
+        let connection = gio::bus_get_future(gio::BusType::Session)
+            .await
+            .map_err(|e| anyhow!("No session bus: {}", e))?;
+
+        let available = available_cursor_modes(&connection).await.unwrap_or(CursorMode::Embedded.portal_bit());
+        let cursor_mode = if available & cursor_mode.portal_bit() != 0 {
+            cursor_mode
+        } else {
+            let fallback = if available & CursorMode::Embedded.portal_bit() != 0 {
+                CursorMode::Embedded
+            } else {
+                CursorMode::Hidden
+            };
+            eprintln!("Cursor mode {:?} not supported by the portal, using {:?}.", cursor_mode, fallback);
+            fallback
+        };
+
+        let session_token = new_token();
+        let results = portal_request(&connection, "CreateSession", |token| {
+            let opts = VariantDict::new(None);
+            opts.insert("handle_token", &token);
+            opts.insert("session_handle_token", &session_token.as_str());
+            (opts.end(),).to_variant()
+        })
+        .await?;
+        let session_handle = results
+            .lookup_value("session_handle", None)
+            .and_then(|v| v.str().map(|s| s.to_string()))
+            .ok_or_else(|| anyhow!("Portal did not return a session handle"))?;
+
+        // The portal keeps the session until it is closed, also when picking fails
+        let (node_id, position, size) = match select_and_start(&connection, &session_handle, cursor_mode, target).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                close_portal_session(&connection, &session_handle);
+                return Err(e);
+            }
+        };
+
+        println!("Screen cast started on PipeWire node {} ({:?} cursor).", node_id, cursor_mode);
+        Ok(Self {
+            connection,
+            session_handle,
+            node_id,
+            cursor_mode,
+            position,
+            size,
+            cursor: SharedCursorState::default(),
+            cursor_tracker: None,
+        })
+    }
+
+    /// Open a new PipeWire remote for this session.
+    pub fn open_remote(&self) -> Result<OwnedFd> {
+        let params = Variant::tuple_from_iter([
+            object_path(&self.session_handle),
+            VariantDict::new(None).end(),
+        ]);
+        let (reply, fd_list) = self
+            .connection
+            .call_with_unix_fd_list_sync(
+                Some(PORTAL_BUS),
+                PORTAL_PATH,
+                SCREENCAST_IFACE,
+                "OpenPipeWireRemote",
+                Some(&params),
+                None,
+                gio::DBusCallFlags::NONE,
+                -1,
+                gio::UnixFDList::NONE,
+                gio::Cancellable::NONE,
+            )
+            .map_err(|e| anyhow!("OpenPipeWireRemote failed: {}", e))?;
+
+        let index = reply
+            .child_value(0)
+            .get::<glib::variant::Handle>()
+            .ok_or_else(|| anyhow!("OpenPipeWireRemote returned no fd"))?
+            .0;
+        let fd_list = fd_list.ok_or_else(|| anyhow!("OpenPipeWireRemote returned no fd list"))?;
+        let raw = fd_list.get(index).map_err(|e| anyhow!("Failed to get PipeWire fd: {}", e))?;
+        Ok(unsafe { OwnedFd::from_raw_fd(raw) })
+    }
+
+    /// Point a pipewiresrc at this session. pipewiresrc duplicates the fd, so
+    /// ours is kept on the element and closed when the element goes away.
+    pub fn configure_source(&self, src: &gst::Element) -> Result<()> {
+        let fd = self.open_remote()?;
+        src.set_property("fd", fd.as_raw_fd());
+        src.set_property("path", self.node_id.to_string());
+        unsafe { src.set_data(PIPEWIRE_FD_KEY, fd) };
+        Ok(())
+    }
+
+    /// pipewiresrc properties for gst-launch style descriptions, e.g. "fd=12 path=47".
+    /// The fd must outlive the pipeline, see `keep_remote`.
+    pub fn launch_props(&self) -> Result<(String, OwnedFd)> {
+        let fd = self.open_remote()?;
+        Ok((format!("fd={} path={}", fd.as_raw_fd(), self.node_id), fd))
+    }
+}
+
+// Internal: let the user pick a source for a created session and start it.
+// Returns the stream's PipeWire node, position and size.
+async fn select_and_start(
+    connection: &gio::DBusConnection,
+    session_handle: &str,
+    cursor_mode: CursorMode,
+    target: CaptureTarget,
+) -> Result<(u32, Option<(i32, i32)>, Option<(i32, i32)>)> {
+    portal_request(connection, "SelectSources", |token| {
+        let opts = VariantDict::new(None);
+        opts.insert("handle_token", &token);
+        opts.insert("types", &target.portal_types());
+        opts.insert("multiple", &false);
+        opts.insert("cursor_mode", &cursor_mode.portal_bit());
+        Variant::tuple_from_iter([object_path(session_handle), opts.end()])
+    })
+    .await?;
+
+    let results = portal_request(connection, "Start", |token| {
+        let opts = VariantDict::new(None);
+        opts.insert("handle_token", &token);
+        Variant::tuple_from_iter([object_path(session_handle), "".to_variant(), opts.end()])
+    })
+    .await?;
+    // streams: a(ua{sv}), we asked for a single one
+    let stream = results
+        .lookup_value("streams", None)
+        .filter(|streams| streams.n_children() > 0)
+        .map(|streams| streams.child_value(0))
+        .ok_or_else(|| anyhow!("Portal did not return a stream"))?;
+    let node_id = stream
+        .child_value(0)
+        .get::<u32>()
+        .ok_or_else(|| anyhow!("Portal did not return a stream"))?;
+    let props = VariantDict::new(Some(&stream.child_value(1)));
+    let position = props.lookup::<(i32, i32)>("position").ok().flatten();
+    let size = props.lookup::<(i32, i32)>("size").ok().flatten();
+    Ok((node_id, position, size))
+}
+
+// Internal: end a portal session, without waiting for the answer
+fn close_portal_session(connection: &gio::DBusConnection, session_handle: &str) {
+    connection.call(
+        Some(PORTAL_BUS),
+        session_handle,
+        "org.freedesktop.portal.Session",
+        "Close",
+        None,
+        None,
+        gio::DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+        |_| {},
+    );
+}
+
+impl Drop for ScreenCastSession {
+    fn drop(&mut self) {
+        close_portal_session(&self.connection, &self.session_handle);
+        println!("Screen cast on PipeWire node {} closed.", self.node_id);
+    }
+}
+
+/// Keep a remote fd from `launch_props` open for as long as `pipeline` lives.
+pub fn keep_remote(pipeline: &gst::Pipeline, fd: OwnedFd) {
+    unsafe { pipeline.set_data(PIPEWIRE_FD_KEY, fd) };
+}
+
+/// Run `f` with the shared screen cast session for a cursor mode, see
+/// `request_capture_session`.
+pub fn request_screen_session(mode: CursorMode, f: impl FnOnce(Result<&ScreenCastSession>) + 'static) {
+    request_capture_session(&format!("{:?} screen", mode), mode, CaptureTarget::Any, f)
+}
+
+/// Run `f` with the screen cast session owned by `key` (a screen source gets
+/// its own, so two sources can show different outputs). An existing session
+/// is handed over right away. Otherwise the portal is asked (starting the
+/// cursor tracker, for metadata mode) without blocking the main loop, and `f`
+/// runs once the user has picked, or with the error.
+pub fn request_capture_session(
+    key: &str,
+    mode: CursorMode,
+    target: CaptureTarget,
+    f: impl FnOnce(Result<&ScreenCastSession>) + 'static,
+) {
+    let existing = SCREEN_SESSIONS.with(|sessions| {
+        sessions
+            .borrow()
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, session)| session.clone())
+    });
+    if let Some(session) = existing {
+        f(Ok(&session));
+        return;
+    }
+
+    let already_asked = PENDING_SESSIONS.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        match pending.iter_mut().find(|(k, _)| k == key) {
+            Some((_, waiting)) => {
+                waiting.push(Box::new(f));
+                true
+            }
+            None => {
+                pending.push((key.to_string(), vec![Box::new(f) as SessionCallback]));
+                false
+            }
+        }
+    });
+    if already_asked {
+        return;
+    }
+
+    let key = key.to_string();
+    glib::MainContext::default().spawn_local(async move {
+        let result = ScreenCastSession::start_for(mode, target).await.and_then(|mut session| {
+            session.cursor = session_cursor(&key);
+            if session.cursor_mode == CursorMode::Metadata {
+                start_cursor_tracking(&mut session)?;
+            }
+            Ok(Rc::new(session))
+        });
+        if let Ok(session) = &result {
+            SCREEN_SESSIONS.with(|sessions| sessions.borrow_mut().push((key.clone(), session.clone())));
+        }
+        let waiting = PENDING_SESSIONS.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            pending
+                .iter()
+                .position(|(k, _)| *k == key)
+                .map(|i| pending.remove(i).1)
+                .unwrap_or_default()
+        });
+        for f in waiting {
+            match &result {
+                Ok(session) => f(Ok(session)),
+                Err(e) => f(Err(anyhow!("{}", e))),
+            }
+        }
+    });
+}
+
+/// End the screen cast session owned by `key`, if there is one.
+pub fn close_capture_session(key: &str) {
+    let closed = SCREEN_SESSIONS.with(|sessions| {
+        let mut sessions = sessions.borrow_mut();
+        let pos = sessions.iter().position(|(k, _)| k == key)?;
+        Some(sessions.remove(pos).1)
+    });
+    // Dropped outside the borrow; the portal session closes with the last user
+    drop(closed);
+    SESSION_CURSORS.with(|cursors| cursors.borrow_mut().retain(|(k, _)| k != key));
+}
+
+/// The area the session owned by `key` captures, as (x, y, width, height) in
+/// compositor coordinates. None until the session exists, or when the portal
+/// did not say (window captures).
+pub fn capture_area(key: &str) -> Option<(i32, i32, i32, i32)> {
+    SCREEN_SESSIONS.with(|sessions| {
+        let sessions = sessions.borrow();
+        let (_, session) = sessions.iter().find(|(k, _)| k == key)?;
+        let (x, y) = session.position.unwrap_or((0, 0));
+        let (width, height) = session.size?;
+        Some((x, y, width, height))
+    })
+}
+
+/// Cursor state of the session owned by `key`. Available right away, so
+/// overlays and zoom can be set up while the portal is still asking.
+pub fn session_cursor(key: &str) -> SharedCursorState {
+    SESSION_CURSORS.with(|cursors| {
+        let mut cursors = cursors.borrow_mut();
+        if let Some((_, cursor)) = cursors.iter().find(|(k, _)| k == key) {
+            return cursor.clone();
+        }
+        let cursor = SharedCursorState::default();
+        cursors.push((key.to_string(), cursor.clone()));
+        cursor
+    })
+}
+
+// Clicks are only read from input devices once the user opts in, see `set_click_capture`
+fn start_cursor_tracking(session: &mut ScreenCastSession) -> Result<()> {
+    let tracker = spawn_metadata_tracker(session.open_remote()?, session.node_id, session.cursor.clone());
+    session.cursor_tracker = Some(tracker);
+    Ok(())
+}
+
+fn new_token() -> String {
+    format!("waycord_{}_{}", process::id(), TOKEN_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+fn object_path(path: &str) -> Variant {
+    glib::variant::ObjectPath::try_from(path.to_string())
+        .map(|p| p.to_variant())
+        .unwrap_or_else(|_| path.to_variant())
+}
+
+async fn available_cursor_modes(connection: &gio::DBusConnection) -> Result<u32> {
+    let reply = connection
+        .call_future(
+            Some(PORTAL_BUS),
+            PORTAL_PATH,
+            "org.freedesktop.DBus.Properties",
+            "Get",
+            Some(&(SCREENCAST_IFACE, "AvailableCursorModes").to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await?;
+    // Reply is (v)
+    reply
+        .child_value(0)
+        .as_variant()
+        .and_then(|v| v.get::<u32>())
+        .ok_or_else(|| anyhow!("Unexpected AvailableCursorModes reply"))
+}
+
+/// Call a portal method that answers through an org.freedesktop.portal.Request
+/// object, and wait for its Response signal (at most `PORTAL_TIMEOUT`).
+/// Returns the result vardict.
+async fn portal_request<F>(connection: &gio::DBusConnection, method: &str, build_params: F) -> Result<VariantDict>
+where
+    F: FnOnce(&str) -> Variant,
+{
+    let token = new_token();
+    let sender = connection
+        .unique_name()
+        .ok_or_else(|| anyhow!("D-Bus connection has no unique name"))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let request_path = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+
+    // Subscribe before calling, so a fast response is not missed
+    let response: Rc<RefCell<PortalResponse>> = Rc::new(RefCell::new(PortalResponse::default()));
+    let response_ref = response.clone();
+    let subscription = connection.signal_subscribe(
+        Some(PORTAL_BUS),
+        Some("org.freedesktop.portal.Request"),
+        Some("Response"),
+        Some(&request_path),
+        None,
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, params| {
+            let code = params.child_value(0).get::<u32>().unwrap_or(2);
+            let mut response = response_ref.borrow_mut();
+            response.value = Some((code, params.child_value(1)));
+            if let Some(waker) = response.waker.take() {
+                waker.wake();
+            }
+        },
+    );
+
+    if let Err(e) = connection
+        .call_future(
+            Some(PORTAL_BUS),
+            PORTAL_PATH,
+            SCREENCAST_IFACE,
+            method,
+            Some(&build_params(&token)),
+            None,
+            gio::DBusCallFlags::NONE,
+            -1,
+        )
+        .await
+    {
+        connection.signal_unsubscribe(subscription);
+        return Err(anyhow!("ScreenCast.{} failed: {}", method, e));
+    }
+
+    let answer = glib::future_with_timeout(PORTAL_TIMEOUT, ResponseFuture(response)).await;
+    connection.signal_unsubscribe(subscription);
+
+    let (code, results) = match answer {
+        Ok(answer) => answer,
+        Err(_) => {
+            // Take the dialog down too, the answer would go nowhere
+            let _ = connection
+                .call_future(
+                    Some(PORTAL_BUS),
+                    &request_path,
+                    "org.freedesktop.portal.Request",
+                    "Close",
+                    None,
+                    None,
+                    gio::DBusCallFlags::NONE,
+                    -1,
+                )
+                .await;
+            return Err(anyhow!("ScreenCast.{} got no answer within {:?}", method, PORTAL_TIMEOUT));
+        }
+    };
+    match code {
+        0 => Ok(VariantDict::new(Some(&results))),
+        1 => Err(anyhow!("Screen cast request was cancelled by the user")),
+        _ => Err(anyhow!("ScreenCast.{} failed (response {})", method, code)),
+    }
+}
+
+// Response signal of a portal request, filled in by the signal handler
+#[derive(Default)]
+struct PortalResponse {
+    value: Option<(u32, Variant)>,
+    waker: Option<Waker>,
+}
+
+// Resolves with (response code, results) once the Response signal arrives
+struct ResponseFuture(Rc<RefCell<PortalResponse>>);
+
+impl Future for ResponseFuture {
+    type Output = (u32, Variant);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut response = self.0.borrow_mut();
+        match response.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                response.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+// src/extensions/animated_image.rs
+
+use gtk4::cairo;
+use anyhow::{anyhow, Result};
+use image::codecs::gif::GifDecoder;
+use image::codecs::png::PngDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{AnimationDecoder, RgbaImage};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+// Browsers treat GIF delays this short as "unset"
+const MIN_DELAY: Duration = Duration::from_millis(20);
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// One decoded frame, as a premultiplied cairo ARGB32 surface.
+pub struct AnimationFrame {
+    surface: FrameSurface,
+    delay: Duration,
+}
+
+// A frame's surface, made once at load time and only painted from afterwards
+struct FrameSurface(cairo::ImageSurface);
+
+// SAFETY: cairo counts references atomically, and nothing draws to the
+// surface once it is built, so it may be loaded on one thread and painted
+// on another
+unsafe impl Send for FrameSurface {}
+unsafe impl Sync for FrameSurface {}
+
+/// The frames of an animated image, decoded up front.
+pub struct Animation {
+    frames: Vec<AnimationFrame>,
+    total: Duration,
+}
+
+impl Animation {
+    /// Load a GIF, APNG or animated WebP with its own frame timing, or a
+    /// numbered PNG sequence ("badge_%04d.png", starting at 0 or 1) at `fps`.
+    /// Other images load as a single frame.
+    pub fn load(path: &str, fps: f64) -> Result<Self> {
+        let frames = if path.contains('%') {
+            load_sequence(path, fps)?
+        } else {
+            let ext = Path::new(path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let reader = || -> Result<BufReader<File>> { Ok(BufReader::new(File::open(path)?)) };
+            match ext.as_str() {
+                "gif" => from_decoder(GifDecoder::new(reader()?)?)?,
+                "webp" => from_decoder(WebPDecoder::new(reader()?)?)?,
+                "png" | "apng" => {
+                    let decoder = PngDecoder::new(reader()?)?;
+                    if decoder.is_apng() {
+                        from_decoder(decoder.apng())?
+                    } else {
+                        vec![to_frame(&image::open(path)?.to_rgba8(), DEFAULT_DELAY)?]
+                    }
+                }
+                _ => vec![to_frame(&image::open(path)?.to_rgba8(), DEFAULT_DELAY)?],
+            }
+        };
+        if frames.is_empty() {
+            return Err(anyhow!("{} has no frames", path));
+        }
+        let total = frames.iter().map(|f| f.delay).sum();
+        Ok(Self { frames, total })
+    }
+
+    /// The frame to show `elapsed` after the start. Without `looping` the
+    /// last frame stays up.
+    pub fn frame_at(&self, elapsed: Duration, looping: bool) -> &AnimationFrame {
+        let mut t = if looping && !self.total.is_zero() {
+            Duration::from_nanos((elapsed.as_nanos() % self.total.as_nanos()) as u64)
+        } else {
+            elapsed
+        };
+        for frame in &self.frames {
+            if t < frame.delay {
+                return frame;
+            }
+            t -= frame.delay;
+        }
+        &self.frames[self.frames.len() - 1]
+    }
+}
+
+/// Paint a frame with its top-left corner at `x`, `y`.
+pub fn draw_frame(cr: &cairo::Context, frame: &AnimationFrame, x: f64, y: f64, scale: f64, opacity: f64) {
+    cr.save().ok();
+    cr.translate(x, y);
+    cr.scale(scale, scale);
+    let _ = cr.set_source_surface(&frame.surface.0, 0.0, 0.0);
+    let _ = cr.paint_with_alpha(opacity.clamp(0.0, 1.0));
+    cr.restore().ok();
+}
+
+fn from_decoder<'a>(decoder: impl AnimationDecoder<'a>) -> Result<Vec<AnimationFrame>> {
+    decoder
+        .into_frames()
+        .collect_frames()?
+        .iter()
+        .map(|frame| {
+            let (num, denom) = frame.delay().numer_denom_ms();
+            let delay = Duration::from_micros(num as u64 * 1000 / denom.max(1) as u64);
+            let delay = if delay < MIN_DELAY { DEFAULT_DELAY } else { delay };
+            to_frame(frame.buffer(), delay)
+        })
+        .collect()
+}
+
+fn load_sequence(pattern: &str, fps: f64) -> Result<Vec<AnimationFrame>> {
+    let delay = Duration::from_secs_f64(1.0 / fps.max(1.0));
+    let first = if Path::new(&sequence_path(pattern, 0)?).exists() { 0 } else { 1 };
+    let mut frames = Vec::new();
+    for index in first.. {
+        let path = sequence_path(pattern, index)?;
+        if !Path::new(&path).exists() {
+            break;
+        }
+        frames.push(to_frame(&image::open(&path)?.to_rgba8(), delay)?);
+    }
+    Ok(frames)
+}
+
+// Internal: fill the "%d" or "%0Nd" of a sequence pattern
+fn sequence_path(pattern: &str, index: u32) -> Result<String> {
+    let start = pattern.find('%').ok_or_else(|| anyhow!("No %d in {}", pattern))?;
+    let rest = &pattern[start + 1..];
+    let end = rest.find('d').ok_or_else(|| anyhow!("Only %d and %0Nd are supported in {}", pattern))?;
+    let width: usize = rest[..end].trim_start_matches('0').parse().unwrap_or(0);
+    Ok(format!("{}{:0width$}{}", &pattern[..start], index, &rest[end + 1..], width = width))
+}
+
+// Internal: RGBA to premultiplied BGRA, the byte order of cairo's ARGB32 here
+fn to_frame(image: &RgbaImage, delay: Duration) -> Result<AnimationFrame> {
+    let mut data = Vec::with_capacity(image.as_raw().len());
+    for px in image.pixels() {
+        let [r, g, b, a] = px.0;
+        let pre = |c: u8| ((c as u16 * a as u16 + 127) / 255) as u8;
+        data.extend_from_slice(&[pre(b), pre(g), pre(r), a]);
+    }
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    let surface = cairo::ImageSurface::create_for_data(data, cairo::Format::ARgb32, width, height, width * 4)
+        .map_err(|e| anyhow!("Failed to create a {}x{} frame: {}", width, height, e))?;
+    Ok(AnimationFrame {
+        surface: FrameSurface(surface),
+        delay,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_path_pads_to_the_given_width() {
+        assert_eq!(sequence_path("badge_%04d.png", 7).unwrap(), "badge_0007.png");
+        assert_eq!(sequence_path("/tmp/f%02d.png", 123).unwrap(), "/tmp/f123.png");
+    }
+
+    #[test]
+    fn sequence_path_without_width_uses_the_plain_number() {
+        assert_eq!(sequence_path("frame%d.png", 0).unwrap(), "frame0.png");
+        assert_eq!(sequence_path("frame%d.png", 42).unwrap(), "frame42.png");
+    }
+
+    #[test]
+    fn sequence_path_rejects_patterns_without_a_number() {
+        assert!(sequence_path("badge.png", 1).is_err());
+        assert!(sequence_path("badge_%s.png", 1).is_err());
+    }
+}
@@ -0,0 +1,116 @@
+// src/extensions/audio_visualizer.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Which GStreamer visualizer draws the audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VisualizerStyle {
+    /// Connected waveform (wavescope, lines)
+    Waveform,
+    /// Frequency bars (spectrascope)
+    #[default]
+    Spectrum,
+    /// Sample dots, like an oscilloscope trace (wavescope, dots)
+    Oscilloscope,
+    /// Stereo image and frequencies as a glowing cloud (synaesthesia)
+    Synaesthesia,
+}
+
+impl VisualizerStyle {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "waveform" => Some(Self::Waveform),
+            "spectrum" => Some(Self::Spectrum),
+            "oscilloscope" => Some(Self::Oscilloscope),
+            "synaesthesia" => Some(Self::Synaesthesia),
+            _ => None,
+        }
+    }
+
+    // Internal: the visualizer element as gst-launch syntax
+    fn element(self) -> &'static str {
+        match self {
+            Self::Waveform => "wavescope style=lines",
+            Self::Spectrum => "spectrascope",
+            Self::Oscilloscope => "wavescope style=dots",
+            Self::Synaesthesia => "synaesthesia",
+        }
+    }
+}
+
+/// Settings of an audio visualizer source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisualizerOptions {
+    /// PipeWire node name from `devices::audio::get_audio_sources`; the default input if None
+    pub device: Option<String>,
+    pub style: VisualizerStyle,
+    pub width: i32,
+    pub height: i32,
+    pub fps: i32,
+    /// 0xAARRGGBB of the brightest parts
+    pub color: u32,
+    /// 0xAARRGGBB behind the drawing; 0 for transparent
+    pub background: u32,
+    /// Let earlier frames fade out instead of clearing each frame
+    pub trails: bool,
+}
+
+/// A video source drawing the sound of a PipeWire audio input:
+///   pwaudiosrc ! audioconvert ! <visualizer> ! videoconvert ! BGRA, recolored
+/// The visualizers' own palettes are replaced with `color` over `background`,
+/// by brightness, so a transparent background can sit over other sources.
+pub fn visualizer_source(name: &str, options: &VisualizerOptions) -> Result<gst::Element> {
+    let target = match &options.device {
+        Some(device) if !device.is_empty() => format!(" target-object=\"{}\"", device.replace('"', "")),
+        _ => String::new(),
+    };
+    let shader = if options.trails { "fade" } else { "none" };
+    let description = format!(
+        "pwaudiosrc{} ! audioconvert ! audioresample ! queue max-size-time=100000000 leaky=downstream ! \
+         {} name=visualizer shader={} ! \
+         video/x-raw,width={},height={},framerate={}/1 ! videoconvert ! video/x-raw,format=BGRA ! \
+         identity name=tint",
+        target,
+        options.style.element(),
+        shader,
+        options.width,
+        options.height,
+        options.fps.max(1),
+    );
+    let bin = gst::parse_bin_from_description(&description, true)
+        .map_err(|e| anyhow!("Failed to build audio visualizer (is gst-plugins-bad installed?): {}", e))?;
+    bin.set_property("name", name);
+
+    let tint = bin.by_name("tint").ok_or_else(|| anyhow!("Audio visualizer has no tint stage"))?;
+    let pad = tint.static_pad("src").ok_or_else(|| anyhow!("Audio visualizer tint stage has no src pad"))?;
+    let (color, background) = (options.color, options.background);
+    pad.add_probe(gst::PadProbeType::BUFFER, move |_, info| {
+        if let Some(gst::PadProbeData::Buffer(ref mut buffer)) = info.data {
+            let buffer = buffer.make_mut();
+            if let Ok(mut map) = buffer.map_writable() {
+                recolor(map.as_mut_slice(), color, background);
+            }
+        }
+        gst::PadProbeReturn::Ok
+    });
+    Ok(bin.upcast())
+}
+
+// Internal: map each BGRA pixel's brightness onto background..color
+fn recolor(data: &mut [u8], color: u32, background: u32) {
+    let channels = |argb: u32| {
+        let c = |shift: u32| ((argb >> shift) & 0xff) as f32;
+        [c(0), c(8), c(16), c(24)]
+    };
+    let (fg, bg) = (channels(color), channels(background));
+    for px in data.chunks_exact_mut(4) {
+        let t = px[0].max(px[1]).max(px[2]) as f32 / 255.0;
+        for (c, (f, b)) in px.iter_mut().zip(fg.iter().zip(bg.iter())) {
+            *c = (b + (f - b) * t).round() as u8;
+        }
+    }
+}
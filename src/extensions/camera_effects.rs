@@ -0,0 +1,373 @@
+// src/extensions/camera_effects.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gtk4::cairo;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::env;
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex};
+
+/// Name of an installed segmentation element that outputs the person with a
+/// transparent background (RGBA). Background blur/replacement is only offered when set.
+pub const SEGMENTATION_ENV_VAR: &str = "WAYCORD_SEGMENTATION_ELEMENT";
+
+// Segmentation element in use; starts from the environment, changed in the settings
+static SEGMENTATION_ELEMENT: Lazy<Mutex<Option<String>>> =
+    Lazy::new(|| Mutex::new(env::var(SEGMENTATION_ENV_VAR).ok().filter(|name| !name.is_empty())));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChromaKeyColor {
+    Green,
+    Blue,
+    Custom(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromaKey {
+    pub color: ChromaKeyColor,
+    /// Size of the colorcube to key out, in degrees
+    pub angle: f32,
+    pub noise_level: f32,
+}
+
+impl ChromaKey {
+    pub fn new(color: ChromaKeyColor) -> Self {
+        Self {
+            color,
+            angle: 20.0,
+            noise_level: 2.0,
+        }
+    }
+}
+
+/// Shape the camera is cut into; everything outside becomes transparent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraMask {
+    Circle,
+    RoundedRect { radius: f64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorBalance {
+    /// -1.0 .. 1.0
+    pub brightness: f64,
+    /// 0.0 .. 2.0
+    pub contrast: f64,
+    /// 0.0 .. 2.0
+    pub saturation: f64,
+}
+
+impl Default for ColorBalance {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackgroundEffect {
+    Blur { sigma: f64 },
+    Replace { image_path: String },
+}
+
+/// Effects applied to a camera source. Stored per scene source, so each
+/// scene can show the same camera differently.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CameraEffects {
+    pub chroma_key: Option<ChromaKey>,
+    pub mask: Option<CameraMask>,
+    pub mirror: bool,
+    pub balance: ColorBalance,
+    pub background: Option<BackgroundEffect>,
+}
+
+/// Background stage: raw, blurred, replacement and segmented foreground
+/// layers inside a private compositor; their alphas select the effect.
+struct BackgroundStage {
+    raw_pad: gst::Pad,
+    blur_pad: gst::Pad,
+    replace_pad: gst::Pad,
+    fg_pad: gst::Pad,
+    blur: gst::Element,
+    replacement: gst::Element,
+}
+
+/// The effects bin sitting between a camera source and the compositor:
+///   [background stage] ! videoflip ! videobalance ! alpha ! cairooverlay (mask)
+/// Every element stays in place; `apply` only changes properties, so switching
+/// effects never relinks a playing pipeline.
+pub struct CameraEffectChain {
+    bin: gst::Bin,
+    flip: gst::Element,
+    balance: gst::Element,
+    alpha: gst::Element,
+    mask: Arc<Mutex<Option<CameraMask>>>,
+    background: Option<BackgroundStage>,
+}
+
+impl CameraEffectChain {
+    /// Build the chain for a camera of the given size. The background stage is
+    /// only built if a segmentation element is installed.
+    pub fn new(name: &str, width: i32, height: i32) -> Result<Self> {
+        let bin = gst::Bin::new(Some(name));
+
+        let convert_in = gst::ElementFactory::make("videoconvert", None)?;
+        let flip = gst::ElementFactory::make("videoflip", None)?;
+        let balance = gst::ElementFactory::make("videobalance", None)?;
+        let alpha = gst::ElementFactory::make("alpha", None)
+            .map_err(|_| anyhow!("Failed to create alpha. Is gst-plugins-good installed?"))?;
+        let convert_out = gst::ElementFactory::make("videoconvert", None)?;
+        let caps = gst::ElementFactory::make("capsfilter", None)?;
+        caps.set_property("caps", gst::Caps::builder("video/x-raw").field("format", "BGRA").build());
+        let overlay = gst::ElementFactory::make("cairooverlay", None)
+            .map_err(|_| anyhow!("Failed to create cairooverlay. Is plugin installed?"))?;
+
+        bin.add_many(&[&convert_in, &flip, &balance, &alpha, &convert_out, &caps, &overlay])?;
+
+        let background = match segmentation_factory() {
+            Some(factory) => Some(build_background_stage(&bin, &convert_in, &flip, &factory, width, height)?),
+            None => {
+                convert_in.link(&flip)?;
+                None
+            }
+        };
+        gst::Element::link_many(&[&flip, &balance, &alpha, &convert_out, &caps, &overlay])?;
+
+        let sink = convert_in.static_pad("sink").ok_or_else(|| anyhow!("videoconvert has no sink pad"))?;
+        let src = overlay.static_pad("src").ok_or_else(|| anyhow!("cairooverlay has no src pad"))?;
+        bin.add_pad(&gst::GhostPad::with_target(Some("sink"), &sink)?)?;
+        bin.add_pad(&gst::GhostPad::with_target(Some("src"), &src)?)?;
+
+        // The mask is drawn by clearing everything outside the shape
+        let mask: Arc<Mutex<Option<CameraMask>>> = Arc::new(Mutex::new(None));
+        let frame_size = Arc::new(Mutex::new((width as f64, height as f64)));
+
+        let size_ref = frame_size.clone();
+        overlay.connect("caps-changed", false, move |args| {
+            if let Ok(caps) = args[1].get::<gst::Caps>() {
+                if let Some(s) = caps.structure(0) {
+                    if let (Ok(w), Ok(h)) = (s.get::<i32>("width"), s.get::<i32>("height")) {
+                        *size_ref.lock().unwrap() = (w as f64, h as f64);
+                    }
+                }
+            }
+            None
+        });
+
+        let mask_ref = mask.clone();
+        overlay.connect("draw", false, move |args| {
+            let shape = *mask_ref.lock().unwrap();
+            if let (Some(shape), Ok(cr)) = (shape, args[1].get::<cairo::Context>()) {
+                let (w, h) = *frame_size.lock().unwrap();
+                draw_mask(&cr, shape, w, h);
+            }
+            None
+        });
+
+        let chain = Self {
+            bin,
+            flip,
+            balance,
+            alpha,
+            mask,
+            background,
+        };
+        chain.apply(&CameraEffects::default());
+        Ok(chain)
+    }
+
+    /// The bin to link between the camera source and the compositor.
+    pub fn element(&self) -> gst::Element {
+        self.bin.clone().upcast()
+    }
+
+    pub fn has_background_support(&self) -> bool {
+        self.background.is_some()
+    }
+
+    /// Apply a set of effects to the live chain.
+    pub fn apply(&self, effects: &CameraEffects) {
+        self.flip
+            .set_property_from_str("method", if effects.mirror { "horizontal-flip" } else { "none" });
+
+        self.balance.set_property("brightness", effects.balance.brightness);
+        self.balance.set_property("contrast", effects.balance.contrast);
+        self.balance.set_property("saturation", effects.balance.saturation);
+
+        match effects.chroma_key {
+            Some(key) => {
+                match key.color {
+                    ChromaKeyColor::Green => self.alpha.set_property_from_str("method", "green"),
+                    ChromaKeyColor::Blue => self.alpha.set_property_from_str("method", "blue"),
+                    ChromaKeyColor::Custom(r, g, b) => {
+                        self.alpha.set_property_from_str("method", "custom");
+                        self.alpha.set_property("target-r", r as u32);
+                        self.alpha.set_property("target-g", g as u32);
+                        self.alpha.set_property("target-b", b as u32);
+                    }
+                }
+                self.alpha.set_property("angle", key.angle);
+                self.alpha.set_property("noise-level", key.noise_level);
+            }
+            None => {
+                // method=set with alpha=1.0 passes frames through untouched
+                self.alpha.set_property_from_str("method", "set");
+                self.alpha.set_property("alpha", 1.0f64);
+            }
+        }
+
+        *self.mask.lock().unwrap() = effects.mask;
+
+        match (&self.background, &effects.background) {
+            (Some(stage), Some(BackgroundEffect::Blur { sigma })) => {
+                stage.blur.set_property("sigma", *sigma);
+                stage.raw_pad.set_property("alpha", 0.0f64);
+                stage.blur_pad.set_property("alpha", 1.0f64);
+                stage.replace_pad.set_property("alpha", 0.0f64);
+                stage.fg_pad.set_property("alpha", 1.0f64);
+            }
+            (Some(stage), Some(BackgroundEffect::Replace { image_path })) => {
+                stage.replacement.set_property("location", image_path);
+                stage.raw_pad.set_property("alpha", 0.0f64);
+                stage.blur_pad.set_property("alpha", 0.0f64);
+                stage.replace_pad.set_property("alpha", 1.0f64);
+                stage.fg_pad.set_property("alpha", 1.0f64);
+            }
+            (Some(stage), None) => {
+                stage.raw_pad.set_property("alpha", 1.0f64);
+                stage.blur_pad.set_property("alpha", 0.0f64);
+                stage.replace_pad.set_property("alpha", 0.0f64);
+                stage.fg_pad.set_property("alpha", 0.0f64);
+            }
+            (None, Some(_)) => {
+                eprintln!(
+                    "Background effects need a segmentation element (enable it in the settings or set {}).",
+                    SEGMENTATION_ENV_VAR
+                );
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+/// Segmentation element new camera chains are built with (None turns background
+/// effects off). Defaults to WAYCORD_SEGMENTATION_ELEMENT.
+pub fn segmentation_element() -> Option<String> {
+    SEGMENTATION_ELEMENT.lock().unwrap().clone()
+}
+
+/// Pick the segmentation element, or None to build cameras without one.
+/// Only chains built afterwards are affected.
+pub fn set_segmentation_element(name: Option<&str>) {
+    *SEGMENTATION_ELEMENT.lock().unwrap() = name.map(|n| n.to_string()).filter(|n| !n.is_empty());
+}
+
+/// The installed segmentation element picked by `set_segmentation_element`, if any.
+pub fn segmentation_factory() -> Option<gst::ElementFactory> {
+    let name = segmentation_element()?;
+    let factory = gst::ElementFactory::find(&name);
+    if factory.is_none() {
+        eprintln!("Segmentation element '{}' is not installed.", name);
+    }
+    factory
+}
+
+// Internal: tee the camera into raw / blurred / segmented layers plus a
+// replacement image layer, all mixed by a private compositor.
+fn build_background_stage(
+    bin: &gst::Bin,
+    upstream: &gst::Element,
+    downstream: &gst::Element,
+    segmentation: &gst::ElementFactory,
+    width: i32,
+    height: i32,
+) -> Result<BackgroundStage> {
+    let tee = gst::ElementFactory::make("tee", None)?;
+    let raw_queue = gst::ElementFactory::make("queue", None)?;
+    let blur_queue = gst::ElementFactory::make("queue", None)?;
+    let blur = gst::ElementFactory::make("gaussianblur", None)
+        .map_err(|_| anyhow!("Failed to create gaussianblur. Is gst-plugins-bad installed?"))?;
+    let blur_convert = gst::ElementFactory::make("videoconvert", None)?;
+    let fg_queue = gst::ElementFactory::make("queue", None)?;
+    let segment = segmentation.create().build()?;
+    let fg_convert = gst::ElementFactory::make("videoconvert", None)?;
+
+    let bg_src = gst::ElementFactory::make("videotestsrc", None)?;
+    bg_src.set_property("is-live", true);
+    bg_src.set_property_from_str("pattern", "black");
+    let bg_caps = gst::ElementFactory::make("capsfilter", None)?;
+    bg_caps.set_property(
+        "caps",
+        gst::Caps::builder("video/x-raw").field("width", width).field("height", height).build(),
+    );
+    let replacement = gst::ElementFactory::make("gdkpixbufoverlay", None)
+        .map_err(|_| anyhow!("Failed to create gdkpixbufoverlay. Is plugin installed?"))?;
+    replacement.set_property("overlay-width", width);
+    replacement.set_property("overlay-height", height);
+
+    let mixer = gst::ElementFactory::make("compositor", None)?;
+
+    bin.add_many(&[
+        &tee, &raw_queue, &blur_queue, &blur, &blur_convert, &fg_queue, &segment, &fg_convert,
+        &bg_src, &bg_caps, &replacement, &mixer,
+    ])?;
+
+    upstream.link(&tee)?;
+    let layer = |chain: &[&gst::Element], zorder: u32| -> Result<gst::Pad> {
+        let pad = mixer
+            .request_pad_simple("sink_%u")
+            .ok_or_else(|| anyhow!("No compositor pad for background stage"))?;
+        pad.set_property("zorder", zorder);
+        pad.set_property("width", width);
+        pad.set_property("height", height);
+        gst::Element::link_many(chain)?;
+        let last = chain
+            .last()
+            .and_then(|element| element.static_pad("src"))
+            .ok_or_else(|| anyhow!("Background layer has no src pad"))?;
+        last.link(&pad)?;
+        Ok(pad)
+    };
+
+    let raw_pad = layer(&[&tee, &raw_queue], 0)?;
+    let blur_pad = layer(&[&tee, &blur_queue, &blur, &blur_convert], 1)?;
+    let replace_pad = layer(&[&bg_src, &bg_caps, &replacement], 2)?;
+    let fg_pad = layer(&[&tee, &fg_queue, &segment, &fg_convert], 3)?;
+
+    mixer.link(downstream)?;
+
+    Ok(BackgroundStage {
+        raw_pad,
+        blur_pad,
+        replace_pad,
+        fg_pad,
+        blur,
+        replacement,
+    })
+}
+
+fn draw_mask(cr: &cairo::Context, mask: CameraMask, w: f64, h: f64) {
+    cr.set_operator(cairo::Operator::Clear);
+    cr.set_fill_rule(cairo::FillRule::EvenOdd);
+    cr.rectangle(0.0, 0.0, w, h);
+    match mask {
+        CameraMask::Circle => {
+            cr.arc(w / 2.0, h / 2.0, w.min(h) / 2.0, 0.0, 2.0 * PI);
+        }
+        CameraMask::RoundedRect { radius } => {
+            let r = radius.min(w / 2.0).min(h / 2.0);
+            cr.new_sub_path();
+            cr.arc(w - r, r, r, -PI / 2.0, 0.0);
+            cr.arc(w - r, h - r, r, 0.0, PI / 2.0);
+            cr.arc(r, h - r, r, PI / 2.0, PI);
+            cr.arc(r, r, r, PI, 3.0 * PI / 2.0);
+            cr.close_path();
+        }
+    }
+    let _ = cr.fill();
+}
@@ -0,0 +1,115 @@
+// src/extensions/camera_position.rs
+
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+
+use crate::extensions::scene_switcher::SceneSwitcher;
+
+/// How long a camera move takes.
+pub const CAMERA_MOVE_DURATION: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl CameraCorner {
+    /// Parse the action target used by the "Camera Position" menu.
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "top_left" => Some(Self::TopLeft),
+            "top_right" => Some(Self::TopRight),
+            "bottom_left" => Some(Self::BottomLeft),
+            "bottom_right" => Some(Self::BottomRight),
+            _ => None,
+        }
+    }
+}
+
+/// Size presets as a fraction of the canvas width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraSize {
+    Small,
+    Medium,
+    Large,
+}
+
+impl CameraSize {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "small" => Some(Self::Small),
+            "medium" => Some(Self::Medium),
+            "large" => Some(Self::Large),
+            _ => None,
+        }
+    }
+
+    fn width_fraction(self) -> f64 {
+        match self {
+            Self::Small => 0.15,
+            Self::Medium => 0.25,
+            Self::Large => 0.35,
+        }
+    }
+}
+
+/// Where the webcam sits on the canvas: a corner, a margin from that corner and a size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraPlacement {
+    pub corner: CameraCorner,
+    /// Margin from the chosen corner, in pixels
+    pub offset_x: i32,
+    pub offset_y: i32,
+    pub size: CameraSize,
+    pub hidden: bool,
+}
+
+impl Default for CameraPlacement {
+    fn default() -> Self {
+        Self {
+            corner: CameraCorner::BottomRight,
+            offset_x: 20,
+            offset_y: 20,
+            size: CameraSize::Medium,
+            hidden: false,
+        }
+    }
+}
+
+impl CameraPlacement {
+    /// Compute (x, y, width, height) on a canvas, keeping the camera's aspect ratio.
+    pub fn geometry(&self, canvas: (i32, i32), aspect: f64) -> (i32, i32, i32, i32) {
+        let (canvas_w, canvas_h) = canvas;
+        let width = (canvas_w as f64 * self.size.width_fraction()).round() as i32;
+        let height = (width as f64 / aspect.max(0.1)).round() as i32;
+
+        let x = match self.corner {
+            CameraCorner::TopLeft | CameraCorner::BottomLeft => self.offset_x,
+            CameraCorner::TopRight | CameraCorner::BottomRight => canvas_w - width - self.offset_x,
+        };
+        let y = match self.corner {
+            CameraCorner::TopLeft | CameraCorner::TopRight => self.offset_y,
+            CameraCorner::BottomLeft | CameraCorner::BottomRight => canvas_h - height - self.offset_y,
+        };
+        (x, y, width, height)
+    }
+
+    /// Move the webcam in the active scene, animating to the new spot.
+    /// The result is stored in the scene, so switching away and back keeps it.
+    pub fn apply(&self, switcher: &mut SceneSwitcher, aspect: f64) -> Result<()> {
+        let scene = switcher.current_scene_index();
+        let pad_index = switcher
+            .camera_pad_index(scene)
+            .ok_or_else(|| anyhow!("The active scene has no camera"))?;
+        if self.hidden {
+            return switcher.set_source_alpha(scene, pad_index, 0.0);
+        }
+
+        let (x, y, w, h) = self.geometry(switcher.canvas_size(), aspect);
+        switcher.set_source_alpha(scene, pad_index, 1.0)?;
+        switcher.animate_source_geometry(scene, pad_index, x, y, w, h, CAMERA_MOVE_DURATION)
+    }
+}
@@ -0,0 +1,189 @@
+// src/extensions/cursor_overlay.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gtk4::cairo;
+use anyhow::{anyhow, Result};
+use once_cell::sync::Lazy;
+use std::f64::consts::PI;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::devices::cursor::{MouseButton, SharedCursorState, CLICK_LIFETIME};
+use crate::devices::screen::CursorMode;
+
+/// User-facing cursor options for a capture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CursorOptions {
+    pub mode: CursorMode,
+    /// Draw a fading ring on each click (metadata mode only)
+    pub highlight_clicks: bool,
+    /// Scale factor for the drawn cursor, 1.0 = native size (metadata mode only)
+    pub magnification: f64,
+    /// 0.0 = follow raw positions, towards 1.0 = heavier smoothing (metadata mode only)
+    pub smoothing: f64,
+}
+
+impl Default for CursorOptions {
+    fn default() -> Self {
+        Self {
+            mode: CursorMode::Embedded,
+            highlight_clicks: false,
+            magnification: 1.0,
+            smoothing: 0.0,
+        }
+    }
+}
+
+/// Cursor options of the program's screen sources, set from the controls.
+pub static PROGRAM_CURSOR_OPTIONS: Lazy<Arc<Mutex<CursorOptions>>> =
+    Lazy::new(|| Arc::new(Mutex::new(CursorOptions::default())));
+
+/// Change how every screen source of the program draws the cursor.
+pub fn set_program_cursor_options(options: CursorOptions) {
+    *PROGRAM_CURSOR_OPTIONS.lock().unwrap() = options;
+}
+
+/// Draws the cursor on a `cairooverlay` from PipeWire cursor metadata.
+/// The overlay must sit right after the screen source, so frame pixels
+/// and cursor coordinates share the same space.
+pub struct CursorOverlay {
+    element: gst::Element,
+    options: Arc<Mutex<CursorOptions>>,
+}
+
+impl CursorOverlay {
+    /// Create a new `cairooverlay` for the cursor.
+    pub fn new(name: &str, state: SharedCursorState, options: CursorOptions) -> Result<Self> {
+        let element = gst::ElementFactory::make("cairooverlay", Some(name))
+            .map_err(|_| anyhow!("Failed to create cairooverlay. Is plugin installed?"))?;
+        Ok(Self::attach(&element, state, options))
+    }
+
+    /// Take over the drawing of an existing `cairooverlay` (e.g. one from a launch string).
+    pub fn attach(element: &gst::Element, state: SharedCursorState, options: CursorOptions) -> Self {
+        Self::attach_shared(element, state, Arc::new(Mutex::new(options)))
+    }
+
+    /// Like `attach`, drawing with options shared with other overlays
+    /// (see `PROGRAM_CURSOR_OPTIONS`). Hidden draws nothing and embedded
+    /// draws the plain cursor.
+    pub fn attach_shared(element: &gst::Element, state: SharedCursorState, options: Arc<Mutex<CursorOptions>>) -> Self {
+        // Smoothed position carried from frame to frame
+        let smoothed: Arc<Mutex<Option<(f64, f64)>>> = Arc::new(Mutex::new(None));
+
+        let opts_ref = options.clone();
+        element.connect("draw", false, move |args| {
+            let cr = match args[1].get::<cairo::Context>() {
+                Ok(cr) => cr,
+                Err(_) => return None,
+            };
+            let opts = *opts_ref.lock().unwrap();
+            let opts = match opts.mode {
+                CursorMode::Hidden => return None,
+                CursorMode::Embedded => CursorOptions {
+                    highlight_clicks: false,
+                    magnification: 1.0,
+                    smoothing: 0.0,
+                    ..opts
+                },
+                CursorMode::Metadata => opts,
+            };
+            let st = state.lock().unwrap();
+
+            if opts.highlight_clicks {
+                let now = Instant::now();
+                for click in &st.clicks {
+                    let age = now.duration_since(click.at);
+                    if age < CLICK_LIFETIME {
+                        draw_click(&cr, click.x, click.y, click.button, age.as_secs_f64() / CLICK_LIFETIME.as_secs_f64());
+                    }
+                }
+            }
+
+            if !st.visible {
+                return None;
+            }
+            let (x, y) = {
+                let mut pos = smoothed.lock().unwrap();
+                let k = opts.smoothing.clamp(0.0, 0.95);
+                let next = match *pos {
+                    Some((px, py)) => (px + (st.x - px) * (1.0 - k), py + (st.y - py) * (1.0 - k)),
+                    None => (st.x, st.y),
+                };
+                *pos = Some(next);
+                next
+            };
+
+            let scale = opts.magnification.max(0.25);
+            let (hx, hy) = (st.hotspot.0 as f64, st.hotspot.1 as f64);
+            let _ = cr.save();
+            cr.translate(x, y);
+            cr.scale(scale, scale);
+            cr.translate(-hx, -hy);
+            match &st.bitmap {
+                Some(bitmap) => draw_bitmap(&cr, bitmap),
+                None => draw_arrow(&cr),
+            }
+            let _ = cr.restore();
+            None
+        });
+
+        Self {
+            element: element.clone(),
+            options,
+        }
+    }
+
+    pub fn element(&self) -> &gst::Element {
+        &self.element
+    }
+
+    pub fn set_options(&self, options: CursorOptions) {
+        *self.options.lock().unwrap() = options;
+    }
+}
+
+fn draw_bitmap(cr: &cairo::Context, bitmap: &crate::devices::cursor::CursorBitmap) {
+    let surface = cairo::ImageSurface::create_for_data(
+        bitmap.data.clone(),
+        cairo::Format::ARgb32,
+        bitmap.width,
+        bitmap.height,
+        bitmap.stride,
+    );
+    if let Ok(surface) = surface {
+        let _ = cr.set_source_surface(&surface, 0.0, 0.0);
+        let _ = cr.paint();
+    }
+}
+
+// Fallback when the compositor has not sent a cursor image yet
+fn draw_arrow(cr: &cairo::Context) {
+    cr.move_to(0.0, 0.0);
+    cr.line_to(0.0, 17.0);
+    cr.line_to(4.5, 13.0);
+    cr.line_to(8.0, 20.0);
+    cr.line_to(10.5, 19.0);
+    cr.line_to(7.0, 12.0);
+    cr.line_to(12.5, 12.0);
+    cr.close_path();
+    cr.set_source_rgb(0.0, 0.0, 0.0);
+    let _ = cr.fill_preserve();
+    cr.set_source_rgb(1.0, 1.0, 1.0);
+    cr.set_line_width(1.0);
+    let _ = cr.stroke();
+}
+
+fn draw_click(cr: &cairo::Context, x: f64, y: f64, button: MouseButton, progress: f64) {
+    let (r, g, b) = match button {
+        MouseButton::Left => (1.0, 0.85, 0.1),
+        MouseButton::Right => (0.2, 0.6, 1.0),
+        MouseButton::Middle => (0.4, 0.9, 0.4),
+    };
+    let radius = 10.0 + 30.0 * progress;
+    cr.set_source_rgba(r, g, b, 0.8 * (1.0 - progress));
+    cr.set_line_width(4.0);
+    cr.arc(x, y, radius, 0.0, 2.0 * PI);
+    let _ = cr.stroke();
+}
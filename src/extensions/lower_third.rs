@@ -0,0 +1,260 @@
+// src/extensions/lower_third.rs
+
+use gtk4::cairo;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::extensions::text_style::{draw_text, rounded_rect, set_argb, text_size, Anchor, TextStyle};
+
+/// Outline of the lower third's background.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LowerThirdShape {
+    Rectangle,
+    #[default]
+    Rounded,
+    /// Right edge leaning forward
+    Slanted,
+}
+
+/// How the lower third comes in and goes out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LowerThirdAnimation {
+    /// In from the left edge of the frame
+    #[default]
+    SlideLeft,
+    /// Up from below its position
+    SlideUp,
+    Fade,
+}
+
+/// Look and layout of a lower third, stored as a TOML file. Title and
+/// subtitle are filled in when it is shown.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LowerThirdTemplate {
+    pub width: i32,
+    pub height: i32,
+    pub shape: LowerThirdShape,
+    pub corner_radius: f64,
+    /// 0xAARRGGBB
+    pub background: u32,
+    /// Bar along the left edge; 0 width for none
+    pub accent: u32,
+    pub accent_width: f64,
+    pub padding: f64,
+    /// Space between title and subtitle
+    pub spacing: f64,
+    pub title: TextStyle,
+    pub subtitle: TextStyle,
+    pub animation: LowerThirdAnimation,
+    pub duration_ms: u64,
+}
+
+impl Default for LowerThirdTemplate {
+    fn default() -> Self {
+        Self {
+            width: 640,
+            height: 110,
+            shape: LowerThirdShape::Rounded,
+            corner_radius: 10.0,
+            background: 0xdd1e1e2e,
+            accent: 0xff89b4fa,
+            accent_width: 8.0,
+            padding: 16.0,
+            spacing: 4.0,
+            title: TextStyle {
+                font: "Sans Bold 30".to_string(),
+                ..TextStyle::default()
+            },
+            subtitle: TextStyle {
+                font: "Sans 20".to_string(),
+                color: 0xffcdd6f4,
+                ..TextStyle::default()
+            },
+            animation: LowerThirdAnimation::SlideLeft,
+            duration_ms: 400,
+        }
+    }
+}
+
+impl LowerThirdTemplate {
+    pub fn templates_dir() -> PathBuf {
+        glib::user_config_dir().join("waycord").join("lower_thirds")
+    }
+
+    /// Names of the saved templates, sorted.
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(Self::templates_dir())
+            .map(|dir| {
+                dir.flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().map(|e| e == "toml").unwrap_or(false))
+                    .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// A template by name. "default" is built in unless a file overrides it.
+    pub fn load(name: &str) -> Result<Self> {
+        let path = Self::path_for(name)?;
+        if name == "default" && !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::load_from_file(&path)
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    pub fn save(&self, name: &str) -> Result<()> {
+        let path = Self::path_for(name)?;
+        std::fs::create_dir_all(Self::templates_dir())?;
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Internal: the file of a template; names cannot leave the templates directory
+    fn path_for(name: &str) -> Result<PathBuf> {
+        if name.is_empty() || name.contains('/') || name.contains('\\') || name.contains("..") {
+            return Err(anyhow!("Invalid lower third template name '{}'", name));
+        }
+        Ok(Self::templates_dir().join(format!("{}.toml", name)))
+    }
+
+    fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+}
+
+/// Where a lower third is between hidden (0) and shown (1).
+#[derive(Debug, Clone, Copy)]
+pub struct Reveal {
+    shown: bool,
+    since: Instant,
+}
+
+impl Reveal {
+    pub fn new(shown: bool) -> Self {
+        Self {
+            shown,
+            // Already settled, no animation on load
+            since: Instant::now() - Duration::from_secs(3600),
+        }
+    }
+
+    pub fn shown(&self) -> bool {
+        self.shown
+    }
+
+    /// Animate towards shown or hidden. Reversing mid-way continues from the
+    /// current position.
+    pub fn set(&mut self, shown: bool, template: &LowerThirdTemplate) {
+        if shown == self.shown {
+            return;
+        }
+        let d = template.duration();
+        let left = d.saturating_sub(self.since.elapsed());
+        self.shown = shown;
+        self.since = Instant::now() - left;
+    }
+
+    pub fn progress(&self, template: &LowerThirdTemplate) -> f64 {
+        let d = template.duration().as_secs_f64();
+        let t = if d > 0.0 { (self.since.elapsed().as_secs_f64() / d).min(1.0) } else { 1.0 };
+        if self.shown {
+            t
+        } else {
+            1.0 - t
+        }
+    }
+}
+
+/// Draw a lower third with its top-left corner at `x`, `y`, `progress` of
+/// the way in (0 hidden, 1 fully shown).
+pub fn draw_lower_third(
+    cr: &cairo::Context,
+    template: &LowerThirdTemplate,
+    title: &str,
+    subtitle: &str,
+    x: f64,
+    y: f64,
+    progress: f64,
+) {
+    if progress <= 0.0 {
+        return;
+    }
+    // Ease out: quick start, soft landing
+    let eased = 1.0 - (1.0 - progress).powi(3);
+    let (w, h) = (template.width as f64, template.height as f64);
+
+    cr.save().ok();
+    match template.animation {
+        LowerThirdAnimation::SlideLeft => {
+            cr.rectangle(0.0, y, x + w, h);
+            cr.clip();
+            cr.translate(-(x + w) * (1.0 - eased), 0.0);
+        }
+        LowerThirdAnimation::SlideUp => {
+            cr.rectangle(x, y, w, h);
+            cr.clip();
+            cr.translate(0.0, h * (1.0 - eased));
+        }
+        LowerThirdAnimation::Fade => cr.push_group(),
+    }
+
+    shape_path(cr, template, x, y, w, h);
+    set_argb(cr, template.background);
+    let _ = cr.fill();
+    if template.accent_width > 0.0 {
+        cr.save().ok();
+        shape_path(cr, template, x, y, w, h);
+        cr.clip();
+        cr.rectangle(x, y, template.accent_width, h);
+        set_argb(cr, template.accent);
+        let _ = cr.fill();
+        cr.restore().ok();
+    }
+
+    let text_x = x + template.accent_width + template.padding;
+    let (_, title_h) = text_size(cr, title, &template.title);
+    let subtitle_h = if subtitle.is_empty() { 0.0 } else { text_size(cr, subtitle, &template.subtitle).1 };
+    let block_h = title_h + if subtitle.is_empty() { 0.0 } else { template.spacing + subtitle_h };
+    // Text block centered vertically
+    let top = y + (h - block_h) / 2.0;
+    draw_text(cr, title, &template.title, text_x, top, Anchor::TopLeft);
+    if !subtitle.is_empty() {
+        draw_text(cr, subtitle, &template.subtitle, text_x, top + title_h + template.spacing, Anchor::TopLeft);
+    }
+
+    if template.animation == LowerThirdAnimation::Fade {
+        if cr.pop_group_to_source().is_ok() {
+            let _ = cr.paint_with_alpha(eased);
+        }
+    }
+    cr.restore().ok();
+}
+
+fn shape_path(cr: &cairo::Context, template: &LowerThirdTemplate, x: f64, y: f64, w: f64, h: f64) {
+    cr.new_path();
+    match template.shape {
+        LowerThirdShape::Rectangle => cr.rectangle(x, y, w, h),
+        LowerThirdShape::Rounded => rounded_rect(cr, x, y, w, h, template.corner_radius),
+        LowerThirdShape::Slanted => {
+            let lean = h * 0.35;
+            cr.move_to(x, y);
+            cr.line_to(x + w, y);
+            cr.line_to(x + w - lean, y + h);
+            cr.line_to(x, y + h);
+            cr.close_path();
+        }
+    }
+}
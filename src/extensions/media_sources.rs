@@ -0,0 +1,306 @@
+// src/extensions/media_sources.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use gtk4::gdk_pixbuf::Pixbuf;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Frame rate while a slideshow transition runs
+const TRANSITION_FPS: u32 = 20;
+// A still frame is pushed again this often, so late joiners get a picture
+const KEEPALIVE: Duration = Duration::from_secs(1);
+
+/// One image of a slideshow.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Slide {
+    /// PNG, JPEG, SVG or anything else gdk-pixbuf can load
+    pub path: String,
+    #[serde(default = "default_slide_secs")]
+    pub duration_secs: f64,
+    /// How this slide replaces the previous one
+    #[serde(default)]
+    pub transition: SlideTransition,
+    #[serde(default = "default_transition_secs")]
+    pub transition_secs: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlideTransition {
+    Cut,
+    #[default]
+    Crossfade,
+}
+
+fn default_slide_secs() -> f64 {
+    5.0
+}
+fn default_transition_secs() -> f64 {
+    1.0
+}
+
+/// A video file through `uridecodebin`. The bin has a "src" pad for the video
+/// and, once the file turns out to have audio, an "audio" pad.
+pub fn media_file_source(name: &str, uri: &str, looping: bool) -> Result<gst::Element> {
+    let uri = if uri.contains("://") {
+        uri.to_string()
+    } else {
+        glib::filename_to_uri(uri, None)
+            .map_err(|e| anyhow!("Invalid media path {}: {}", uri, e))?
+            .to_string()
+    };
+    let bin = gst::Bin::new(Some(name));
+    let dec = gst::ElementFactory::make("uridecodebin", Some("dec"))?;
+    dec.set_property("uri", &uri);
+    let convert = gst::ElementFactory::make("videoconvert", None)?;
+    bin.add_many(&[&dec, &convert])?;
+    let convert_src = convert.static_pad("src").ok_or_else(|| anyhow!("videoconvert has no src pad"))?;
+    bin.add_pad(&gst::GhostPad::with_target(Some("src"), &convert_src)?)?;
+
+    let bin_weak = bin.downgrade();
+    dec.connect_pad_added(move |_, pad| {
+        let bin = match bin_weak.upgrade() {
+            Some(b) => b,
+            None => return,
+        };
+        let caps = pad.current_caps().unwrap_or_else(|| pad.query_caps(None));
+        let media = caps.structure(0).map(|s| s.name().to_string()).unwrap_or_default();
+        let result = if media.starts_with("video/") {
+            link_decoded_video(&bin, pad, looping)
+        } else if media.starts_with("audio/") {
+            link_decoded_audio(&bin, pad, looping)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            eprintln!("Media source {}: {:?}", bin.name(), e);
+        }
+    });
+    Ok(bin.upcast())
+}
+
+fn link_decoded_video(bin: &gst::Bin, pad: &gst::Pad, looping: bool) -> Result<()> {
+    let convert = bin
+        .iterate_elements()
+        .into_iter()
+        .flatten()
+        .find(|e| e.factory().map(|f| f.name() == "videoconvert").unwrap_or(false))
+        .ok_or_else(|| anyhow!("No videoconvert in media bin"))?;
+    let sink = convert.static_pad("sink").ok_or_else(|| anyhow!("videoconvert has no sink pad"))?;
+    if sink.is_linked() {
+        return Ok(()); // only the first video stream is shown
+    }
+    pad.link(&sink).map_err(|e| anyhow!("Failed to link video: {:?}", e))?;
+    if looping {
+        loop_on_eos(bin, &sink, true);
+    }
+    Ok(())
+}
+
+fn link_decoded_audio(bin: &gst::Bin, pad: &gst::Pad, looping: bool) -> Result<()> {
+    if bin.static_pad("audio").is_some() {
+        return Ok(()); // only the first audio stream is mixed
+    }
+    let convert = gst::ElementFactory::make("audioconvert", None)?;
+    let resample = gst::ElementFactory::make("audioresample", None)?;
+    bin.add_many(&[&convert, &resample])?;
+    convert.link(&resample)?;
+    let sink = convert.static_pad("sink").ok_or_else(|| anyhow!("audioconvert has no sink pad"))?;
+    pad.link(&sink).map_err(|e| anyhow!("Failed to link audio: {:?}", e))?;
+    if looping {
+        loop_on_eos(bin, &sink, false);
+    }
+    resample.sync_state_with_parent()?;
+    convert.sync_state_with_parent()?;
+    let src = resample.static_pad("src").ok_or_else(|| anyhow!("audioresample has no src pad"))?;
+    let ghost = gst::GhostPad::with_target(Some("audio"), &src)?;
+    ghost.set_active(true)?;
+    // The registry links this to the program audio mixer from `pad-added`
+    bin.add_pad(&ghost)?;
+    Ok(())
+}
+
+// Internal: swallow EOS on a stream and start the file over. The video stream
+// seeks, or the audio stream when the file has no video, so a file restarts once.
+fn loop_on_eos(bin: &gst::Bin, pad: &gst::Pad, video: bool) {
+    let bin_weak = bin.downgrade();
+    pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+        let is_eos = matches!(&info.data, Some(gst::PadProbeData::Event(ev)) if ev.type_() == gst::EventType::Eos);
+        if !is_eos {
+            return gst::PadProbeReturn::Ok;
+        }
+        let seek = video || bin_weak.upgrade().map(|bin| !has_video(&bin)).unwrap_or(false);
+        if seek {
+            let bin_weak = bin_weak.clone();
+            // Never seek from the streaming thread
+            glib::MainContext::default().invoke(move || {
+                if let Some(bin) = bin_weak.upgrade() {
+                    restart_media(bin.upcast_ref());
+                }
+            });
+        }
+        gst::PadProbeReturn::Drop
+    });
+}
+
+// Internal: whether a decoded video stream feeds the bin's "src" pad
+fn has_video(bin: &gst::Bin) -> bool {
+    bin.iterate_elements()
+        .into_iter()
+        .flatten()
+        .find(|e| e.factory().map(|f| f.name() == "videoconvert").unwrap_or(false))
+        .and_then(|convert| convert.static_pad("sink"))
+        .map(|sink| sink.is_linked())
+        .unwrap_or(false)
+}
+
+/// Play a media file source from the beginning. Its output is offset to the
+/// current running time, so the restarted stream is not dropped as late.
+pub fn restart_media(element: &gst::Element) {
+    let dec = match element.downcast_ref::<gst::Bin>().and_then(|b| b.by_name("dec")) {
+        Some(dec) => dec,
+        None => return,
+    };
+    let running = element.current_running_time().unwrap_or(gst::ClockTime::ZERO);
+    if let Err(e) = dec.seek_simple(gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT, gst::ClockTime::ZERO) {
+        eprintln!("Failed to restart media source {}: {:?}", element.name(), e);
+        return;
+    }
+    for pad in element.src_pads() {
+        pad.set_offset(running.nseconds() as i64);
+    }
+}
+
+/// A still image at its own size. SVGs are rendered by gdk-pixbuf (librsvg).
+pub fn image_source(name: &str, path: &str) -> Result<gst::Element> {
+    let pixbuf = Pixbuf::from_file(path).map_err(|e| anyhow!("Failed to load image {}: {}", path, e))?;
+    let (width, height) = (pixbuf.width(), pixbuf.height());
+    let slide = Slide {
+        path: path.to_string(),
+        duration_secs: 0.0,
+        transition: SlideTransition::Cut,
+        transition_secs: 0.0,
+    };
+    slideshow_source(name, vec![slide], false, width, height)
+}
+
+/// Images shown one after another at `width`x`height`, letterboxed on a
+/// transparent background. With `looping` the show starts over after the
+/// last slide, otherwise the last slide stays up.
+pub fn slideshow_source(name: &str, slides: Vec<Slide>, looping: bool, width: i32, height: i32) -> Result<gst::Element> {
+    if slides.is_empty() {
+        return Err(anyhow!("A slideshow needs at least one slide"));
+    }
+    let appsrc = gst_app::AppSrc::builder()
+        .name(name)
+        .caps(
+            &gst::Caps::builder("video/x-raw")
+                .field("format", "BGRA")
+                .field("width", width)
+                .field("height", height)
+                .field("framerate", gst::Fraction::new(0, 1))
+                .build(),
+        )
+        .format(gst::Format::Time)
+        .is_live(true)
+        .do_timestamp(true)
+        .build();
+
+    let weak = appsrc.downgrade();
+    thread::spawn(move || run_slideshow(weak, slides, looping, width, height));
+    Ok(appsrc.upcast())
+}
+
+// Internal: push slide frames until the source is dropped
+fn run_slideshow(appsrc: gst::glib::WeakRef<gst_app::AppSrc>, slides: Vec<Slide>, looping: bool, width: i32, height: i32) {
+    // Returns false once the source is gone
+    let push = |frame: &[u8]| -> bool {
+        match appsrc.upgrade() {
+            Some(src) => {
+                // Flushing while not playing yet; the frame is simply lost
+                let _ = src.push_buffer(gst::Buffer::from_slice(frame.to_vec()));
+                true
+            }
+            None => false,
+        }
+    };
+    let hold = |frame: &[u8], duration: Option<Duration>| -> bool {
+        let start = Instant::now();
+        loop {
+            if !push(frame) {
+                return false;
+            }
+            let left = duration.map(|d| d.saturating_sub(start.elapsed()));
+            match left {
+                Some(left) if left.is_zero() => return true,
+                Some(left) => thread::sleep(left.min(KEEPALIVE)),
+                None => thread::sleep(KEEPALIVE),
+            }
+        }
+    };
+
+    let blank = vec![0u8; (width * height * 4) as usize];
+    let mut current = blank.clone();
+    let mut index = 0;
+    loop {
+        let slide = &slides[index];
+        let next = render_slide(&slide.path, width, height).unwrap_or_else(|e| {
+            eprintln!("Slideshow: {:?}", e);
+            blank.clone()
+        });
+        if slide.transition == SlideTransition::Crossfade && slide.transition_secs > 0.0 {
+            let steps = ((slide.transition_secs * TRANSITION_FPS as f64).ceil() as u32).max(1);
+            let step_time = Duration::from_secs_f64(slide.transition_secs / steps as f64);
+            for step in 1..steps {
+                if !push(&blend(&current, &next, step as f64 / steps as f64)) {
+                    return;
+                }
+                thread::sleep(step_time);
+            }
+        }
+        current = next;
+
+        let last = index + 1 == slides.len();
+        let duration = if last && !looping { None } else { Some(Duration::from_secs_f64(slide.duration_secs.max(0.0))) };
+        if !hold(&current, duration) {
+            return;
+        }
+        index = (index + 1) % slides.len();
+    }
+}
+
+// Internal: decode an image to fit `width`x`height`, centered, as straight-alpha BGRA
+fn render_slide(path: &str, width: i32, height: i32) -> Result<Vec<u8>> {
+    let pixbuf = Pixbuf::from_file_at_scale(path, width, height, true)
+        .map_err(|e| anyhow!("Failed to load image {}: {}", path, e))?;
+    let (pw, ph) = (pixbuf.width().min(width), pixbuf.height().min(height));
+    let (ox, oy) = ((width - pw) / 2, (height - ph) / 2);
+    let channels = pixbuf.n_channels() as usize;
+    let stride = pixbuf.rowstride() as usize;
+    let pixels = pixbuf.read_pixel_bytes();
+
+    let mut frame = vec![0u8; (width * height * 4) as usize];
+    for y in 0..ph as usize {
+        for x in 0..pw as usize {
+            let src = y * stride + x * channels;
+            let dst = ((y + oy as usize) * width as usize + x + ox as usize) * 4;
+            frame[dst] = pixels[src + 2];
+            frame[dst + 1] = pixels[src + 1];
+            frame[dst + 2] = pixels[src];
+            frame[dst + 3] = if channels == 4 { pixels[src + 3] } else { 255 };
+        }
+    }
+    Ok(frame)
+}
+
+fn blend(from: &[u8], to: &[u8], t: f64) -> Vec<u8> {
+    let t = (t.clamp(0.0, 1.0) * 256.0) as u32;
+    from.iter()
+        .zip(to)
+        .map(|(a, b)| ((*a as u32 * (256 - t) + *b as u32 * t) >> 8) as u8)
+        .collect()
+}
@@ -0,0 +1,356 @@
+// src/extensions/message_rotation.rs
+
+use gtk4::cairo;
+use gtk4::gio;
+use gtk4::gio::prelude::*;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::extensions::text_style::{draw_text, text_size, Anchor, TextStyle};
+
+/// One message of a rotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RotationMessage {
+    pub text: String,
+    /// How long it stays up; the rotation's `default_secs` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secs: Option<f64>,
+}
+
+/// How one message gives way to the next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RotationTransition {
+    Cut,
+    /// The outgoing message fades out while the next fades in, over the last `secs` of its time
+    Crossfade {
+        #[serde(default = "default_fade_secs")]
+        secs: f64,
+    },
+    /// All messages on one line, scrolling right to left. Message times are not used.
+    Ticker {
+        /// Pixels per second
+        #[serde(default = "default_ticker_speed")]
+        speed: f64,
+        /// Width of the strip from the overlay's x; up to the frame's right edge if unset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        width: Option<i32>,
+        #[serde(default = "default_separator")]
+        separator: String,
+    },
+}
+
+impl Default for RotationTransition {
+    fn default() -> Self {
+        RotationTransition::Crossfade { secs: default_fade_secs() }
+    }
+}
+
+/// Messages a text overlay cycles through, saved with the overlay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RotationConfig {
+    #[serde(default)]
+    pub messages: Vec<RotationMessage>,
+    /// Read the messages from this file instead, one per line, re-read when it
+    /// changes. A line may start with its time in seconds: "[10] Message".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(default = "default_message_secs")]
+    pub default_secs: f64,
+    #[serde(default)]
+    pub transition: RotationTransition,
+}
+
+impl RotationConfig {
+    pub fn new(messages: Vec<RotationMessage>) -> Self {
+        Self {
+            messages,
+            file: None,
+            default_secs: default_message_secs(),
+            transition: RotationTransition::default(),
+        }
+    }
+}
+
+fn default_fade_secs() -> f64 {
+    0.5
+}
+fn default_ticker_speed() -> f64 {
+    120.0
+}
+fn default_separator() -> String {
+    "   •   ".to_string()
+}
+fn default_message_secs() -> f64 {
+    5.0
+}
+
+/// A running rotation: its config, the messages in use and when it started.
+pub struct Rotation {
+    pub config: RotationConfig,
+    messages: Vec<RotationMessage>,
+    started: Instant,
+}
+
+impl Rotation {
+    pub fn new(config: RotationConfig) -> Self {
+        let messages = match &config.file {
+            Some(path) => read_messages(path).unwrap_or_else(|e| {
+                eprintln!("Failed to read messages from {}: {:?}", path.display(), e);
+                config.messages.clone()
+            }),
+            None => config.messages.clone(),
+        };
+        Self {
+            config,
+            messages,
+            started: Instant::now(),
+        }
+    }
+
+    /// Draw the current frame of the rotation. Returns false if there is
+    /// nothing to rotate, so the overlay's own text can be drawn instead.
+    pub fn draw(&self, cr: &cairo::Context, style: &TextStyle, x: f64, y: f64, anchor: Anchor) -> bool {
+        if self.messages.is_empty() {
+            return false;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        match &self.config.transition {
+            RotationTransition::Ticker { speed, width, separator } => {
+                let line: Vec<&str> = self.messages.iter().map(|m| m.text.as_str()).collect();
+                draw_ticker(cr, &line.join(separator), style, x, y, anchor, elapsed * speed, *width);
+            }
+            transition => {
+                let fade = match transition {
+                    RotationTransition::Crossfade { secs } => *secs,
+                    _ => 0.0,
+                };
+                let (index, left) = self.current(elapsed);
+                let next = (index + 1) % self.messages.len();
+                if fade > 0.0 && left < fade && next != index {
+                    let p = 1.0 - left / fade;
+                    with_alpha(cr, 1.0 - p, || draw_text(cr, &self.messages[index].text, style, x, y, anchor));
+                    with_alpha(cr, p, || draw_text(cr, &self.messages[next].text, style, x, y, anchor));
+                } else {
+                    draw_text(cr, &self.messages[index].text, style, x, y, anchor);
+                }
+            }
+        }
+        true
+    }
+
+    /// The text the rotation shows now: the current message, or the whole
+    /// line of a ticker.
+    pub fn current_text(&self) -> Option<String> {
+        if self.messages.is_empty() {
+            return None;
+        }
+        match &self.config.transition {
+            RotationTransition::Ticker { separator, .. } => {
+                let line: Vec<&str> = self.messages.iter().map(|m| m.text.as_str()).collect();
+                Some(line.join(separator))
+            }
+            _ => {
+                let (index, _) = self.current(self.started.elapsed().as_secs_f64());
+                Some(self.messages[index].text.clone())
+            }
+        }
+    }
+
+    // Internal: message shown after `elapsed` seconds, and the seconds it has left
+    fn current(&self, elapsed: f64) -> (usize, f64) {
+        let secs = |m: &RotationMessage| m.secs.unwrap_or(self.config.default_secs).max(0.1);
+        let total: f64 = self.messages.iter().map(secs).sum();
+        let mut t = elapsed % total;
+        for (i, message) in self.messages.iter().enumerate() {
+            if t < secs(message) {
+                return (i, secs(message) - t);
+            }
+            t -= secs(message);
+        }
+        (self.messages.len() - 1, 0.0)
+    }
+}
+
+/// Re-read a rotation's messages file whenever it changes. The monitor must
+/// be kept alive for as long as the file should be watched.
+pub fn watch_messages(path: &Path, rotation: Arc<Mutex<Option<Rotation>>>) -> Option<gio::FileMonitor> {
+    let monitor = gio::File::for_path(path)
+        .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+        .map_err(|e| eprintln!("Cannot watch {}: {}", path.display(), e))
+        .ok()?;
+    let path = path.to_path_buf();
+    monitor.connect_changed(move |_, _, _, event| {
+        if event == gio::FileMonitorEvent::Deleted {
+            return;
+        }
+        match read_messages(&path) {
+            Ok(messages) => {
+                if let Some(rotation) = rotation.lock().unwrap().as_mut() {
+                    rotation.messages = messages;
+                }
+            }
+            Err(e) => eprintln!("Failed to re-read {}: {:?}", path.display(), e),
+        }
+    });
+    Some(monitor)
+}
+
+/// Messages from a file: one per line, blank lines and lines starting with
+/// '#' skipped, an optional "[seconds]" prefix for the message's time.
+pub fn read_messages(path: &Path) -> Result<Vec<RotationMessage>> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let timed = line
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .and_then(|(secs, text)| Some((secs.trim().parse::<f64>().ok()?, text.trim())));
+            match timed {
+                Some((secs, text)) => RotationMessage {
+                    text: text.to_string(),
+                    secs: Some(secs),
+                },
+                None => RotationMessage {
+                    text: line.to_string(),
+                    secs: None,
+                },
+            }
+        })
+        .collect())
+}
+
+fn with_alpha(cr: &cairo::Context, alpha: f64, draw: impl FnOnce()) {
+    cr.push_group();
+    draw();
+    if cr.pop_group_to_source().is_ok() {
+        let _ = cr.paint_with_alpha(alpha);
+    }
+}
+
+// Internal: one line scrolled `offset` pixels to the left inside a clipped strip,
+// repeated so the strip never runs empty
+#[allow(clippy::too_many_arguments)]
+fn draw_ticker(
+    cr: &cairo::Context,
+    line: &str,
+    style: &TextStyle,
+    x: f64,
+    y: f64,
+    anchor: Anchor,
+    offset: f64,
+    width: Option<i32>,
+) {
+    let style = TextStyle {
+        wrap_width: None,
+        ..style.clone()
+    };
+    // Only the vertical part of the anchor applies to a ticker
+    let anchor = match anchor {
+        Anchor::TopLeft | Anchor::Top | Anchor::TopRight => Anchor::TopLeft,
+        Anchor::Left | Anchor::Center | Anchor::Right => Anchor::Left,
+        _ => Anchor::BottomLeft,
+    };
+    let (text_w, text_h) = text_size(cr, line, &style);
+    let strip_w = match width {
+        Some(w) => w as f64,
+        None => cr.clip_extents().map(|(_, _, x2, _)| x2 - x).unwrap_or(0.0),
+    };
+    if strip_w <= 0.0 || text_w <= 0.0 {
+        return;
+    }
+    let gap = text_h;
+    let period = text_w + gap;
+    let top = match anchor {
+        Anchor::TopLeft => y,
+        Anchor::Left => y - text_h / 2.0,
+        _ => y - text_h,
+    };
+
+    cr.save().ok();
+    cr.rectangle(x, top, strip_w, text_h);
+    cr.clip();
+    let mut px = x + strip_w - offset % period;
+    // Copies that already scrolled past the left edge
+    while px > x {
+        px -= period;
+    }
+    while px < x + strip_w {
+        draw_text(cr, line, &style, px, y, anchor);
+        px += period;
+    }
+    cr.restore().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(text: &str, secs: Option<f64>) -> RotationMessage {
+        RotationMessage {
+            text: text.to_string(),
+            secs,
+        }
+    }
+
+    fn rotation(messages: Vec<RotationMessage>) -> Rotation {
+        Rotation::new(RotationConfig {
+            default_secs: 5.0,
+            ..RotationConfig::new(messages)
+        })
+    }
+
+    #[test]
+    fn read_messages_skips_blanks_and_comments_and_reads_times() {
+        let path = std::env::temp_dir().join(format!("waycord_messages_{}.txt", std::process::id()));
+        std::fs::write(&path, "# sponsors\n\n  Thanks to Acme  \n[10] Follow us\n[ 2.5 ]Short one\n[x] Not a time\n").unwrap();
+        let messages = read_messages(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            messages,
+            vec![
+                message("Thanks to Acme", None),
+                message("Follow us", Some(10.0)),
+                message("Short one", Some(2.5)),
+                message("[x] Not a time", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_messages_fails_on_a_missing_file() {
+        assert!(read_messages(Path::new("/nonexistent/waycord/messages.txt")).is_err());
+    }
+
+    #[test]
+    fn current_walks_through_message_times() {
+        let r = rotation(vec![message("a", Some(2.0)), message("b", None), message("c", Some(1.0))]);
+        assert_eq!(r.current(0.0), (0, 2.0));
+        assert_eq!(r.current(1.5), (0, 0.5));
+        assert_eq!(r.current(2.0), (1, 5.0));
+        assert_eq!(r.current(6.5), (1, 0.5));
+        assert_eq!(r.current(7.25), (2, 0.75));
+    }
+
+    #[test]
+    fn current_wraps_around() {
+        let r = rotation(vec![message("a", Some(2.0)), message("b", Some(3.0))]);
+        assert_eq!(r.current(5.0), (0, 2.0));
+        assert_eq!(r.current(13.0), (1, 2.0));
+    }
+
+    #[test]
+    fn current_gives_zero_length_messages_a_minimum_time() {
+        let r = rotation(vec![message("a", Some(0.0)), message("b", Some(1.0))]);
+        let (index, left) = r.current(0.05);
+        assert_eq!(index, 0);
+        assert!((left - 0.05).abs() < 1e-9);
+        assert_eq!(r.current(0.1).0, 1);
+    }
+}
@@ -3,3 +3,23 @@ pub mod overlays;
 pub mod streaming;
 pub mod hardware_accel;
 pub mod plugin_system;
+pub mod camera_position;
+pub mod camera_effects;
+pub mod cursor_overlay;
+pub mod zoom_follow;
+pub mod scene_collection;
+pub mod source_registry;
+pub mod transitions;
+pub mod studio_mode;
+pub mod source_transform;
+pub mod scene_automation;
+pub mod media_sources;
+pub mod text_style;
+pub mod message_rotation;
+pub mod text_providers;
+pub mod animated_image;
+pub mod lower_third;
+pub mod audio_visualizer;
+pub mod privacy_mask;
+pub mod recording_stamp;
+pub mod subtitles;
@@ -2,144 +2,990 @@
 
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use gtk4::gdk_pixbuf::Pixbuf;
+use gtk4::{cairo, gio};
 use anyhow::{anyhow, Result};
 use glib::MainContext;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 
+use crate::extensions::animated_image::{draw_frame, Animation};
+use crate::extensions::lower_third::{draw_lower_third, LowerThirdTemplate, Reveal};
+use crate::extensions::privacy_mask::{draw_mask, track_windows, MaskEffect, MaskRect, TrackWindow};
+use crate::extensions::subtitles;
+use crate::extensions::message_rotation::{watch_messages, Rotation, RotationConfig, RotationMessage};
+use crate::extensions::text_providers::{ProviderState, TextProvider};
+use crate::extensions::text_style::{draw_text, Anchor, TextStyle};
+
+// Longest wait for upstream to go idle before the chain is relinked
+const RELINK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Stable, user-chosen identifier of an overlay (e.g. "sponsor").
+pub type OverlayId = String;
+
+/// One overlay of the stack, as saved in the overlay profile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverlayDesc {
+    pub id: OverlayId,
+    /// Position in canvas pixels (the top-left corner unless anchored otherwise)
+    pub x: i32,
+    pub y: i32,
+    #[serde(default = "default_true")]
+    pub visible: bool,
+    pub kind: OverlayKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OverlayKind {
+    /// Text drawn with Pango; x/y is where `anchor` of the text box goes
+    Text {
+        text: String,
+        #[serde(default)]
+        anchor: Anchor,
+        #[serde(default)]
+        style: TextStyle,
+        /// Messages shown instead of `text` while set
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rotation: Option<RotationConfig>,
+        /// Live value filled into `text`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider: Option<TextProvider>,
+    },
+    /// A still image through gdkpixbufoverlay
+    Image {
+        path: String,
+        #[serde(default = "default_one")]
+        opacity: f64,
+        #[serde(default = "default_one")]
+        scale: f64,
+    },
+    /// A GIF, APNG or animated WebP with its own frame timing, or a numbered
+    /// PNG sequence ("badge_%04d.png") played at `fps`
+    Animation {
+        path: String,
+        #[serde(default = "default_fps")]
+        fps: f64,
+        #[serde(default = "default_true")]
+        looping: bool,
+        #[serde(default = "default_one")]
+        opacity: f64,
+        #[serde(default = "default_one")]
+        scale: f64,
+    },
+    /// A title/subtitle bar drawn from a template in the lower_thirds config
+    /// directory. Showing and hiding it plays the template's animation.
+    LowerThird {
+        #[serde(default = "default_template")]
+        template: String,
+        title: String,
+        #[serde(default)]
+        subtitle: String,
+        /// GTK accelerator that shows or hides it, e.g. "<Ctrl><Alt>l"
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hotkey: Option<String>,
+    },
+    /// Blurs, pixelates or blacks out `width`x`height` at x/y. With `track`,
+    /// the matching windows are masked instead, falling back to the fixed
+    /// area while the compositor cannot be asked.
+    PrivacyMask {
+        #[serde(default)]
+        effect: MaskEffect,
+        /// Blur radius or block size, in pixels
+        #[serde(default = "default_mask_strength")]
+        strength: f64,
+        #[serde(default)]
+        width: i32,
+        #[serde(default)]
+        height: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        track: Option<TrackWindow>,
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+fn default_one() -> f64 {
+    1.0
+}
+fn default_fps() -> f64 {
+    30.0
+}
+fn default_mask_strength() -> f64 {
+    16.0
+}
+fn default_template() -> String {
+    "default".to_string()
+}
+
+/// The saved overlay stack, bottom to top.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OverlayStack {
+    #[serde(default)]
+    pub overlays: Vec<OverlayDesc>,
+}
+
+impl OverlayStack {
+    pub fn default_path() -> PathBuf {
+        glib::user_config_dir().join("waycord").join("overlays.toml")
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The saved stack, or an empty one if there is none or it is unreadable.
+    pub fn load_or_default() -> Self {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load_from_file(&path).unwrap_or_else(|e| {
+            eprintln!("Ignoring saved overlays: {:?}", e);
+            Self::default()
+        })
+    }
+}
+
+// A decoded animation and what it was loaded from
+struct AnimationState {
+    source: (String, f64),
+    started: Instant,
+    // None while decoding or if decoding failed
+    animation: Option<Arc<Animation>>,
+}
+
+// A lower third's template and how far in it is
+struct LowerThirdState {
+    name: String,
+    template: LowerThirdTemplate,
+    reveal: Reveal,
+}
+
+// Windows a privacy mask follows, and where they are
+struct TrackState {
+    config: TrackWindow,
+    rects: Arc<Mutex<Option<Vec<MaskRect>>>>,
+}
+
+// What the draw callback of a cairooverlay-based overlay reads
+#[derive(Clone, Default)]
+struct DrawState {
+    desc: Arc<Mutex<Option<OverlayDesc>>>,
+    rotation: Arc<Mutex<Option<Rotation>>>,
+    provider: Arc<Mutex<Option<ProviderState>>>,
+    animation: Arc<Mutex<Option<AnimationState>>>,
+    lower_third: Arc<Mutex<Option<LowerThirdState>>>,
+    track: Arc<Mutex<Option<TrackState>>>,
+    // What the overlay's text is reported to the subtitles as
+    subtitle_key: String,
+}
+
+struct OverlayItem {
+    desc: OverlayDesc,
+    element: gst::Element,
+    draw: DrawState,
+    // Watches the rotation's messages file
+    monitor: Option<gio::FileMonitor>,
+    // Watches a file provider's file
+    provider_monitor: Option<gio::FileMonitor>,
+}
+
+impl OverlayItem {
+    // Internal: push `desc` to the element
+    fn apply(&mut self) {
+        *self.draw.desc.lock().unwrap() = Some(self.desc.clone());
+        match &self.desc.kind {
+            OverlayKind::Image { path, opacity, scale } => {
+                self.element.set_property("location", path);
+                self.element.set_property("offset-x", self.desc.x);
+                self.element.set_property("offset-y", self.desc.y);
+                self.element.set_property("alpha", if self.desc.visible { opacity.clamp(0.0, 1.0) } else { 0.0 });
+                // 0 keeps the image's own size
+                let (w, h) = match Pixbuf::file_info(path) {
+                    Some((_, w, h)) if *scale != 1.0 => ((w as f64 * scale) as i32, (h as f64 * scale) as i32),
+                    _ => (0, 0),
+                };
+                self.element.set_property("overlay-width", w);
+                self.element.set_property("overlay-height", h);
+            }
+            OverlayKind::Animation { path, fps, .. } => {
+                let source = (path.clone(), *fps);
+                self.apply_animation(source);
+            }
+            OverlayKind::Text { rotation, provider, .. } => {
+                let (config, provider) = (rotation.clone(), provider.clone());
+                self.apply_rotation(config);
+                self.apply_provider(provider);
+            }
+            OverlayKind::LowerThird { template, .. } => {
+                let template = template.clone();
+                self.apply_lower_third(&template);
+            }
+            OverlayKind::PrivacyMask { track, .. } => {
+                let mut current = self.draw.track.lock().unwrap();
+                // Dropping the old state stops its tracking thread
+                if current.as_ref().map(|t| &t.config) != track.as_ref() {
+                    *current = track.clone().map(|config| TrackState {
+                        rects: track_windows(config.clone()),
+                        config,
+                    });
+                }
+            }
+        }
+    }
+
+    // Internal: load the template if it changed, and start the in or out
+    // animation if visibility changed
+    fn apply_lower_third(&mut self, name: &str) {
+        let mut current = self.draw.lower_third.lock().unwrap();
+        match current.as_mut() {
+            Some(state) if state.name == name => state.reveal.set(self.desc.visible, &state.template),
+            _ => {
+                let template = LowerThirdTemplate::load(name).unwrap_or_else(|e| {
+                    eprintln!("Failed to load lower third template '{}': {:?}", name, e);
+                    LowerThirdTemplate::default()
+                });
+                *current = Some(LowerThirdState {
+                    name: name.to_string(),
+                    template,
+                    reveal: Reveal::new(self.desc.visible),
+                });
+            }
+        }
+    }
+
+    // Internal: (re)start the rotation if its config changed; moving or
+    // restyling the overlay leaves a running rotation alone
+    fn apply_rotation(&mut self, config: Option<RotationConfig>) {
+        let mut current = self.draw.rotation.lock().unwrap();
+        if current.as_ref().map(|r| &r.config) == config.as_ref() {
+            return;
+        }
+        self.monitor = None;
+        let file = config.as_ref().and_then(|c| c.file.clone());
+        *current = config.map(Rotation::new);
+        drop(current);
+        if let Some(file) = file {
+            self.monitor = watch_messages(&file, self.draw.rotation.clone());
+        }
+    }
+
+    // Internal: same for the text provider; an unchanged one keeps its state.
+    // Either way it learns whether the overlay is shown
+    fn apply_provider(&mut self, provider: Option<TextProvider>) {
+        let mut current = self.draw.provider.lock().unwrap();
+        if current.as_ref().map(|p| &p.provider) != provider.as_ref() {
+            *current = provider.map(ProviderState::new);
+            self.provider_monitor = current.as_ref().and_then(|p| p.watch());
+        }
+        if let Some(state) = current.as_mut() {
+            state.set_shown(self.desc.visible);
+        }
+    }
+
+    // Internal: decode the animation off the main thread when its file or rate
+    // changed; it starts playing once decoded
+    fn apply_animation(&mut self, source: (String, f64)) {
+        let mut current = self.draw.animation.lock().unwrap();
+        if current.as_ref().map(|a| &a.source) == Some(&source) {
+            return;
+        }
+        *current = Some(AnimationState {
+            source: source.clone(),
+            started: Instant::now(),
+            animation: None,
+        });
+        let slot = self.draw.animation.clone();
+        std::thread::spawn(move || {
+            let loaded = Animation::load(&source.0, source.1);
+            let mut current = slot.lock().unwrap();
+            match (loaded, current.as_mut()) {
+                // Only if the overlay was not pointed elsewhere meanwhile
+                (Ok(animation), Some(state)) if state.source == source => {
+                    state.animation = Some(Arc::new(animation));
+                    state.started = Instant::now();
+                }
+                (Err(e), _) => eprintln!("Failed to load animation {}: {:?}", source.0, e),
+                _ => {}
+            }
+        });
+    }
+}
+
+/// Overlays drawn over the program, in a chain between two fixed elements:
+///   upstream ! overlay ! overlay ! ... ! privacy mode ! downstream
+/// Properties change in place; adding, removing and reordering relink the
+/// chain while upstream is idle, so it is safe in a playing pipeline.
 pub struct OverlayManager {
-    pipeline: gst::Pipeline,
-    text_overlays: Vec<gst::Element>,
-    image_overlays: Vec<gst::Element>,
+    // The pipeline, or the bin of a branch such as a recording
+    bin: gst::Bin,
+    // Ends of the chain, set by `attach`; the end is the privacy mode stage
+    ends: Option<(gst::Element, gst::Element)>,
+    items: Vec<OverlayItem>,
     main_ctx: MainContext,
-    // Sponsor or rotating messages
-    rotating_msgs: Arc<Mutex<Vec<String>>>,
-    current_msg_index: Arc<Mutex<usize>>,
+    // Blacks out the whole frame, above every overlay
+    privacy_mode: Arc<AtomicBool>,
+    // Told about every overlay added or removed, or whose provider changed
+    stack_changed: Option<Box<dyn Fn(&[OverlayDesc])>>,
 }
 
 impl OverlayManager {
-    pub fn new(pipeline: gst::Pipeline, main_ctx: MainContext) -> Self {
+    pub fn new(bin: gst::Bin, main_ctx: MainContext) -> Self {
         Self {
-            pipeline,
-            text_overlays: Vec::new(),
-            image_overlays: Vec::new(),
+            bin,
+            ends: None,
+            items: Vec::new(),
             main_ctx,
-            rotating_msgs: Arc::new(Mutex::new(Vec::new())),
-            current_msg_index: Arc::new(Mutex::new(0)),
-        }
-    }
-
-    /// Add a text overlay at given coordinates with a given message.
-    /// This creates a `textoverlay` element, adds it to the pipeline,
-    /// and attempts to link it somewhere in the chain.
-    pub fn add_text_overlay(
-        &mut self,
-        name: &str,
-        message: &str,
-        x: i32,
-        y: i32,
-        font_desc: &str,
-        color: &str,
-        upstream: &gst::Element,
-        downstream: &gst::Element,
-    ) -> Result<()> {
-        let overlay = gst::ElementFactory::make("textoverlay", Some(name))
-            .map_err(|_| anyhow!("Failed to create textoverlay. Is plugin installed?"))?;
-        overlay.set_property("text", message);
-        overlay.set_property("font-desc", font_desc);
-        overlay.set_property("color", color);
-        overlay.set_property("valignment", "top");
-        overlay.set_property("halignment", "left");
-        // The “xpos” and “ypos” are ratio-based for textoverlay's "shaded-background" or overlay geometry
-        // For exact pixel positions, we might need different elements. We'll do best effort:
-        overlay.set_property("xpos", x);
-        overlay.set_property("ypos", y);
-
-        self.pipeline.add(&overlay)?;
-        overlay.sync_state_with_parent()?;
-
-        // Link: upstream -> overlay -> downstream
-        gst::Element::link_many(&[upstream, &overlay, downstream])
-            .map_err(|_| anyhow!("Failed to link text overlay."))?;
-
-        self.text_overlays.push(overlay);
-        Ok(())
-    }
-
-    /// Add an image overlay (like a watermark) at a given position.
-    /// We use "gdkpixbufoverlay" or "pngalpha" pipeline approach if needed.
-    /// This example uses "gdkpixbufoverlay".
-    pub fn add_image_overlay(
-        &mut self,
-        name: &str,
-        image_path: &str,
-        x: i32,
-        y: i32,
-        upstream: &gst::Element,
-        downstream: &gst::Element,
-    ) -> Result<()> {
-        let overlay = gst::ElementFactory::make("gdkpixbufoverlay", Some(name))
-            .map_err(|_| anyhow!("Failed to create gdkpixbufoverlay. Is plugin installed?"))?;
-        overlay.set_property("location", image_path);
-        overlay.set_property("offset-x", x);
-        overlay.set_property("offset-y", y);
-
-        self.pipeline.add(&overlay)?;
-        overlay.sync_state_with_parent()?;
-
-        gst::Element::link_many(&[upstream, &overlay, downstream])
-            .map_err(|_| anyhow!("Failed to link image overlay."))?;
-
-        self.image_overlays.push(overlay);
-        Ok(())
-    }
-
-    /// Let the user add multiple rotating sponsor messages. This function starts
-    /// a timer that updates the text property of a specified textoverlay element
-    /// every N seconds.
-    pub fn start_rotating_messages(
-        &mut self,
-        textoverlay_name: &str,
-        messages: Vec<String>,
-        interval: Duration,
-    ) -> Result<()> {
-        if messages.is_empty() {
-            return Err(anyhow!("No messages provided for rotation."));
-        }
-        // store messages
-        {
-            let mut vec_ref = self.rotating_msgs.lock().unwrap();
-            *vec_ref = messages;
-        }
-        let textoverlay = self
-            .pipeline
-            .by_name(textoverlay_name)
-            .ok_or_else(|| anyhow!("Text overlay {} not found in pipeline.", textoverlay_name))?;
-
-        let rotating_msgs = self.rotating_msgs.clone();
-        let index_ref = self.current_msg_index.clone();
-        let overlay_weak = textoverlay.downgrade();
-        let mc = self.main_ctx.clone();
-
-        mc.spawn_local(async move {
-            loop {
-                // Wait interval
-                glib::timeout_future_seconds(interval.as_secs_f64()).await;
-
-                let msgs = rotating_msgs.lock().unwrap();
-                let mut idx = index_ref.lock().unwrap();
-                if msgs.is_empty() {
-                    continue;
-                }
+            privacy_mode: Arc::new(AtomicBool::new(false)),
+            stack_changed: None,
+        }
+    }
+
+    /// Call `f` with the whole stack whenever an overlay is added or removed
+    /// or its text provider changes, e.g. to rebind hotkeys. `f` must not use
+    /// the manager itself.
+    pub fn connect_stack_changed(&mut self, f: impl Fn(&[OverlayDesc]) + 'static) {
+        self.stack_changed = Some(Box::new(f));
+    }
 
-                let message = &msgs[*idx];
-                if let Some(overlay) = overlay_weak.upgrade() {
-                    overlay.set_property("text", message.as_str());
+    /// Link `upstream` to `downstream` through the (empty) overlay chain.
+    /// Both must already be in the bin.
+    pub fn attach(&mut self, upstream: &gst::Element, downstream: &gst::Element) -> Result<()> {
+        let privacy = gst::ElementFactory::make("cairooverlay", Some("overlay_privacy_mode"))
+            .map_err(|_| anyhow!("Failed to create cairooverlay. Is plugin installed?"))?;
+        let on = self.privacy_mode.clone();
+        privacy.connect("draw", false, move |args| {
+            if on.load(Ordering::Relaxed) {
+                if let Ok(cr) = args[1].get::<cairo::Context>() {
+                    cr.set_source_rgb(0.0, 0.0, 0.0);
+                    let _ = cr.paint();
                 }
-                *idx = (*idx + 1) % msgs.len();
             }
+            None
+        });
+        self.bin.add(&privacy)?;
+        gst::Element::link_many(&[upstream, &privacy, downstream])
+            .map_err(|_| anyhow!("Failed to link the overlay chain."))?;
+        privacy.sync_state_with_parent()?;
+        self.ends = Some((upstream.clone(), privacy));
+        Ok(())
+    }
+
+    /// Black out the whole program at once, overlays included, e.g. while
+    /// something private is on screen. Takes effect from the next frame.
+    pub fn set_privacy_mode(&self, on: bool) {
+        self.privacy_mode.store(on, Ordering::Relaxed);
+    }
+
+    pub fn privacy_mode(&self) -> bool {
+        self.privacy_mode.load(Ordering::Relaxed)
+    }
+
+    /// Put an overlay on top of the stack. IDs must be unique.
+    pub fn add_overlay(&mut self, desc: OverlayDesc) -> Result<()> {
+        if self.get(&desc.id).is_some() {
+            return Err(anyhow!("An overlay with id '{}' already exists.", desc.id));
+        }
+        let draw = DrawState {
+            subtitle_key: self.subtitle_key(&desc.id),
+            ..Default::default()
+        };
+        let element = build_element(&desc, draw.clone())?;
+        let mut item = OverlayItem {
+            desc,
+            element,
+            draw,
+            monitor: None,
+            provider_monitor: None,
+        };
+        item.apply();
+        let element = item.element.clone();
+        self.bin.add(&element)?;
+        let mut chain = self.chain_elements();
+        chain.push(element.clone());
+        if let Err(e) = self.relink(chain) {
+            let _ = self.bin.remove(&element);
+            return Err(e);
+        }
+        element.sync_state_with_parent()?;
+        self.items.push(item);
+        self.notify_stack_changed();
+        Ok(())
+    }
+
+    /// Add a text overlay with its top-left corner at `x`, `y`.
+    pub fn add_text_overlay(&mut self, id: &str, message: &str, x: i32, y: i32, style: TextStyle) -> Result<()> {
+        self.add_overlay(OverlayDesc {
+            id: id.to_string(),
+            x,
+            y,
+            visible: true,
+            kind: OverlayKind::Text {
+                text: message.to_string(),
+                anchor: Anchor::TopLeft,
+                style,
+                rotation: None,
+                provider: None,
+            },
+        })
+    }
+
+    /// Pixelate, blur or black out a fixed area of the program.
+    pub fn add_privacy_mask(&mut self, id: &str, x: i32, y: i32, width: i32, height: i32, effect: MaskEffect) -> Result<()> {
+        self.add_overlay(OverlayDesc {
+            id: id.to_string(),
+            x,
+            y,
+            visible: true,
+            kind: OverlayKind::PrivacyMask {
+                effect,
+                strength: default_mask_strength(),
+                width,
+                height,
+                track: None,
+            },
+        })
+    }
+
+    /// Add an image overlay (like a watermark) with its top-left corner at `x`, `y`.
+    pub fn add_image_overlay(&mut self, id: &str, image_path: &str, x: i32, y: i32) -> Result<()> {
+        self.add_overlay(OverlayDesc {
+            id: id.to_string(),
+            x,
+            y,
+            visible: true,
+            kind: OverlayKind::Image {
+                path: image_path.to_string(),
+                opacity: 1.0,
+                scale: 1.0,
+            },
+        })
+    }
+
+    /// Take an overlay out of the chain and stop it.
+    pub fn remove_overlay(&mut self, id: &str) -> Result<()> {
+        let pos = self.position(id)?;
+        let mut chain = self.chain_elements();
+        let element = chain.remove(pos);
+        self.relink(chain)?;
+        self.items.remove(pos);
+        subtitles::set_text(&self.subtitle_key(id), None);
+        // Unlinked now, so nothing streams through it any more
+        let bin = self.bin.clone();
+        self.main_ctx.invoke(move || {
+            let _ = element.set_state(gst::State::Null);
+            let _ = bin.remove(&element);
         });
+        self.notify_stack_changed();
+        Ok(())
+    }
+
+    /// Move an overlay to `index` in the stack (0 is the bottom).
+    pub fn reorder(&mut self, id: &str, index: usize) -> Result<()> {
+        let pos = self.position(id)?;
+        let index = index.min(self.items.len() - 1);
+        if pos == index {
+            return Ok(());
+        }
+        let mut chain = self.chain_elements();
+        let element = chain.remove(pos);
+        chain.insert(index, element);
+        self.relink(chain)?;
+        let item = self.items.remove(pos);
+        self.items.insert(index, item);
+        Ok(())
+    }
+
+    /// Replace the text of a text overlay.
+    pub fn update_text(&mut self, id: &str, text: &str) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::Text { text: current, .. } => *current = text.to_string(),
+            _ => return Err(anyhow!("Overlay '{}' is not a text overlay.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
+
+    /// Restyle a text overlay and change which point of it sits at its x/y.
+    pub fn set_text_style(&mut self, id: &str, anchor: Anchor, style: TextStyle) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::Text { anchor: a, style: s, .. } => {
+                *a = anchor;
+                *s = style;
+            }
+            _ => return Err(anyhow!("Overlay '{}' is not a text overlay.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
 
+    /// Move an overlay to `x`, `y` (its anchor point, for text).
+    pub fn move_overlay(&mut self, id: &str, x: i32, y: i32) -> Result<()> {
+        let item = self.item_mut(id)?;
+        item.desc.x = x;
+        item.desc.y = y;
+        item.apply();
         Ok(())
     }
+
+    /// Set the opacity (0 to 1) of an image or animation.
+    pub fn set_opacity(&mut self, id: &str, value: f64) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::Image { opacity, .. } | OverlayKind::Animation { opacity, .. } => *opacity = value.clamp(0.0, 1.0),
+            _ => return Err(anyhow!("Overlay '{}' is not an image.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
+
+    /// Scale an image or animation; 1 is its own size.
+    pub fn set_scale(&mut self, id: &str, value: f64) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::Image { scale, .. } | OverlayKind::Animation { scale, .. } => *scale = value.max(0.01),
+            _ => return Err(anyhow!("Overlay '{}' is not an image.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
+
+    /// Show or hide an overlay; hidden overlays stay in the chain.
+    pub fn set_visibility(&mut self, id: &str, visible: bool) -> Result<()> {
+        let item = self.item_mut(id)?;
+        item.desc.visible = visible;
+        item.apply();
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&OverlayDesc> {
+        self.items.iter().find(|i| i.desc.id == id).map(|i| &i.desc)
+    }
+
+    /// All overlays, bottom to top.
+    pub fn overlays(&self) -> impl Iterator<Item = &OverlayDesc> {
+        self.items.iter().map(|i| &i.desc)
+    }
+
+    /// The current stack, for saving.
+    pub fn to_stack(&self) -> OverlayStack {
+        OverlayStack {
+            overlays: self.overlays().cloned().collect(),
+        }
+    }
+
+    /// Replace every overlay with the ones of `stack`.
+    pub fn load_stack(&mut self, stack: &OverlayStack) -> Result<()> {
+        let ids: Vec<OverlayId> = self.items.iter().map(|i| i.desc.id.clone()).collect();
+        for id in ids {
+            self.remove_overlay(&id)?;
+        }
+        for desc in &stack.overlays {
+            if let Err(e) = self.add_overlay(desc.clone()) {
+                eprintln!("Skipping overlay '{}': {:?}", desc.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the stack to `OverlayStack::default_path()`.
+    pub fn save(&self) -> Result<()> {
+        self.to_stack().save_to_file(&OverlayStack::default_path())
+    }
+
+    /// Cycle a text overlay through messages (e.g. sponsors). Replaces a
+    /// rotation that is already running on it.
+    pub fn start_rotation(&mut self, id: &str, config: RotationConfig) -> Result<()> {
+        self.set_rotation(id, Some(config))
+    }
+
+    /// Stop a rotation; the overlay shows its own text again.
+    pub fn stop_rotation(&mut self, id: &str) -> Result<()> {
+        self.set_rotation(id, None)
+    }
+
+    /// Replace the messages of a running rotation. The rotation starts over.
+    pub fn update_rotation(&mut self, id: &str, messages: Vec<RotationMessage>) -> Result<()> {
+        let config = match &self.items[self.position(id)?].desc.kind {
+            OverlayKind::Text { rotation: Some(config), .. } => RotationConfig {
+                messages,
+                ..config.clone()
+            },
+            _ => return Err(anyhow!("Overlay '{}' has no rotation running.", id)),
+        };
+        self.set_rotation(id, Some(config))
+    }
+
+    /// Fill a text overlay from a live provider (clock, timer, file, counter),
+    /// or go back to its fixed text with `None`.
+    pub fn set_text_provider(&mut self, id: &str, provider: Option<TextProvider>) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::Text { provider: current, .. } => *current = provider,
+            _ => return Err(anyhow!("Overlay '{}' is not a text overlay.", id)),
+        }
+        item.apply();
+        self.notify_stack_changed();
+        Ok(())
+    }
+
+    /// Start an overlay's countdown over.
+    pub fn restart_provider(&mut self, id: &str) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match item.draw.provider.lock().unwrap().as_mut() {
+            Some(state) => state.restart(),
+            None => return Err(anyhow!("Overlay '{}' has no text provider.", id)),
+        }
+        Ok(())
+    }
+
+    /// Change the title and subtitle of a lower third. A shown one changes in place.
+    pub fn set_lower_third_text(&mut self, id: &str, title: &str, subtitle: &str) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::LowerThird { title: t, subtitle: s, .. } => {
+                *t = title.to_string();
+                *s = subtitle.to_string();
+            }
+            _ => return Err(anyhow!("Overlay '{}' is not a lower third.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
+
+    /// Switch a lower third to another template, or re-read its template
+    /// file after it was edited.
+    pub fn set_lower_third_template(&mut self, id: &str, template: &str) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::LowerThird { template: t, .. } => *t = template.to_string(),
+            _ => return Err(anyhow!("Overlay '{}' is not a lower third.", id)),
+        }
+        *item.draw.lower_third.lock().unwrap() = None;
+        item.apply();
+        Ok(())
+    }
+
+    /// Change how a privacy mask hides what is under it.
+    pub fn set_mask_effect(&mut self, id: &str, effect: MaskEffect, strength: f64) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::PrivacyMask { effect: e, strength: s, .. } => {
+                *e = effect;
+                *s = strength.max(1.0);
+            }
+            _ => return Err(anyhow!("Overlay '{}' is not a privacy mask.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
+
+    /// Move and resize the fixed area of a privacy mask.
+    pub fn set_mask_area(&mut self, id: &str, x: i32, y: i32, width: i32, height: i32) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::PrivacyMask { width: w, height: h, .. } => {
+                *w = width;
+                *h = height;
+            }
+            _ => return Err(anyhow!("Overlay '{}' is not a privacy mask.", id)),
+        }
+        item.desc.x = x;
+        item.desc.y = y;
+        item.apply();
+        Ok(())
+    }
+
+    /// Make a privacy mask follow windows by title, or go back to its fixed
+    /// area with `None`.
+    pub fn set_mask_tracking(&mut self, id: &str, track: Option<TrackWindow>) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::PrivacyMask { track: t, .. } => *t = track,
+            _ => return Err(anyhow!("Overlay '{}' is not a privacy mask.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
+
+    /// Show a hidden overlay or hide a shown one; lower thirds animate.
+    pub fn toggle_visibility(&mut self, id: &str) -> Result<bool> {
+        let visible = !self.item_mut(id)?.desc.visible;
+        self.set_visibility(id, visible)?;
+        Ok(visible)
+    }
+
+    fn set_rotation(&mut self, id: &str, config: Option<RotationConfig>) -> Result<()> {
+        let item = self.item_mut(id)?;
+        match &mut item.desc.kind {
+            OverlayKind::Text { rotation, .. } => *rotation = config,
+            _ => return Err(anyhow!("Overlay '{}' is not a text overlay.", id)),
+        }
+        item.apply();
+        Ok(())
+    }
+
+    fn position(&self, id: &str) -> Result<usize> {
+        self.items
+            .iter()
+            .position(|i| i.desc.id == id)
+            .ok_or_else(|| anyhow!("No overlay with id '{}'", id))
+    }
+
+    // Internal: overlay IDs are only unique per manager, and the recording
+    // stamp runs a second one
+    fn subtitle_key(&self, id: &str) -> String {
+        format!("{}/{}", self.bin.name(), id)
+    }
+
+    fn item_mut(&mut self, id: &str) -> Result<&mut OverlayItem> {
+        let pos = self.position(id)?;
+        Ok(&mut self.items[pos])
+    }
+
+    fn notify_stack_changed(&self) {
+        if let Some(f) = &self.stack_changed {
+            let descs: Vec<OverlayDesc> = self.overlays().cloned().collect();
+            f(&descs);
+        }
+    }
+
+    fn chain_elements(&self) -> Vec<gst::Element> {
+        self.items.iter().map(|i| i.element.clone()).collect()
+    }
+
+    // Internal: relink upstream ! chain... ! downstream. Done from an idle probe
+    // on upstream's src pad, so no buffer is in the chain meanwhile; the overlays
+    // have no queues, so upstream's thread is the only one pushing through them.
+    // A probe that has not run by the timeout is cancelled, so it can never
+    // rewire the chain behind the caller's back.
+    fn relink(&self, chain: Vec<gst::Element>) -> Result<()> {
+        let (upstream, downstream) = self
+            .ends
+            .clone()
+            .ok_or_else(|| anyhow!("Overlay chain is not attached to the pipeline."))?;
+        let src = upstream.static_pad("src").ok_or_else(|| anyhow!("Overlay chain start has no src pad"))?;
+        let old = self.chain_elements();
+        let state = Arc::new((Mutex::new(RelinkState::default()), Condvar::new()));
+        let probe_state = state.clone();
+        let probe = src.add_probe(gst::PadProbeType::IDLE, move |_, _| {
+            let (lock, done) = &*probe_state;
+            let mut st = lock.lock().unwrap();
+            if st.cancelled {
+                return gst::PadProbeReturn::Remove;
+            }
+            let mut links: Vec<&gst::Element> = vec![&upstream];
+            links.extend(old.iter());
+            links.push(&downstream);
+            for pair in links.windows(2) {
+                pair[0].unlink(pair[1]);
+            }
+            let mut links: Vec<&gst::Element> = vec![&upstream];
+            links.extend(chain.iter());
+            links.push(&downstream);
+            st.result = Some(gst::Element::link_many(&links).map_err(|_| anyhow!("Failed to relink the overlay chain.")));
+            done.notify_all();
+            gst::PadProbeReturn::Remove
+        });
+        // The probe runs right away unless a buffer is on its way; wait for it
+        let (lock, done) = &*state;
+        let (mut st, _) = done
+            .wait_timeout_while(lock.lock().unwrap(), RELINK_TIMEOUT, |st| st.result.is_none())
+            .unwrap();
+        if let Some(linked) = st.result.take() {
+            return linked;
+        }
+        st.cancelled = true;
+        drop(st);
+        if let Some(probe) = probe {
+            src.remove_probe(probe);
+        }
+        Err(anyhow!("Timed out relinking the overlay chain."))
+    }
+}
+
+// Outcome of a relink, shared with its idle probe
+#[derive(Default)]
+struct RelinkState {
+    result: Option<Result<()>>,
+    cancelled: bool,
+}
+
+fn build_element(desc: &OverlayDesc, draw: DrawState) -> Result<gst::Element> {
+    let name = format!("overlay_{}", desc.id);
+    if let OverlayKind::Image { .. } = desc.kind {
+        return gst::ElementFactory::make("gdkpixbufoverlay", Some(&name))
+            .map_err(|_| anyhow!("Failed to create gdkpixbufoverlay. Is plugin installed?"));
+    }
+    let overlay = gst::ElementFactory::make("cairooverlay", Some(&name))
+        .map_err(|_| anyhow!("Failed to create cairooverlay. Is plugin installed?"))?;
+    overlay.connect("draw", false, move |args| {
+        let cr = match args[1].get::<cairo::Context>() {
+            Ok(cr) => cr,
+            Err(_) => return None,
+        };
+        let desc = draw.desc.lock().unwrap();
+        let desc = match desc.as_ref() {
+            // A hidden lower third still draws while animating out
+            Some(desc) if desc.visible || matches!(desc.kind, OverlayKind::LowerThird { .. }) => desc,
+            Some(desc) => {
+                subtitles::set_text(&draw.subtitle_key, None);
+                return None;
+            }
+            None => return None,
+        };
+        let (x, y) = (desc.x as f64, desc.y as f64);
+        match &desc.kind {
+            OverlayKind::Text { text, anchor, style, .. } => {
+                let rotation = draw.rotation.lock().unwrap();
+                // Rotating messages also go to the recording's subtitles
+                subtitles::set_text(&draw.subtitle_key, rotation.as_ref().and_then(|r| r.current_text()).as_deref());
+                let rotated = match rotation.as_ref() {
+                    Some(rotation) => rotation.draw(&cr, style, x, y, *anchor),
+                    None => false,
+                };
+                drop(rotation);
+                if !rotated {
+                    match draw.provider.lock().unwrap().as_ref() {
+                        Some(provider) => draw_text(&cr, &provider.render(text), style, x, y, *anchor),
+                        None => draw_text(&cr, text, style, x, y, *anchor),
+                    }
+                }
+            }
+            OverlayKind::Animation { looping, opacity, scale, .. } => {
+                let state = draw.animation.lock().unwrap();
+                if let Some((animation, started)) = state.as_ref().and_then(|s| Some((s.animation.clone()?, s.started))) {
+                    drop(state);
+                    draw_frame(&cr, animation.frame_at(started.elapsed(), *looping), x, y, *scale, *opacity);
+                }
+            }
+            OverlayKind::LowerThird { title, subtitle, .. } => {
+                if let Some(state) = draw.lower_third.lock().unwrap().as_ref() {
+                    let caption = match (state.reveal.shown(), subtitle.is_empty()) {
+                        (false, _) => None,
+                        (true, true) => Some(title.clone()),
+                        (true, false) => Some(format!("{}\n{}", title, subtitle)),
+                    };
+                    subtitles::set_text(&draw.subtitle_key, caption.as_deref());
+                    let progress = state.reveal.progress(&state.template);
+                    draw_lower_third(&cr, &state.template, title, subtitle, x, y, progress);
+                }
+            }
+            OverlayKind::PrivacyMask { effect, strength, width, height, .. } => {
+                let fixed = MaskRect {
+                    x,
+                    y,
+                    width: *width as f64,
+                    height: *height as f64,
+                };
+                let tracked = draw.track.lock().unwrap().as_ref().map(|t| t.rects.lock().unwrap().clone());
+                match tracked {
+                    Some(Some(rects)) => {
+                        for rect in rects {
+                            draw_mask(&cr, *effect, *strength, rect);
+                        }
+                    }
+                    _ => draw_mask(&cr, *effect, *strength, fixed),
+                }
+            }
+            OverlayKind::Image { .. } => {}
+        }
+        None
+    });
+    Ok(overlay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: i32 = 64;
+
+    // Internal: run one white frame through the overlay chain and return the
+    // raw BGRA a branch of the tee after it writes to its file, as the
+    // recording and stream branches hang off the program tee
+    fn recorded_frame(setup: impl FnOnce(&mut OverlayManager)) -> Vec<u8> {
+        gst::init().unwrap();
+        let pipeline = gst::Pipeline::new(Some("overlay_test"));
+        let src = gst::ElementFactory::make("videotestsrc", None).unwrap();
+        src.set_property("num-buffers", 1i32);
+        src.set_property_from_str("pattern", "white");
+        let caps = gst::ElementFactory::make("capsfilter", None).unwrap();
+        caps.set_property(
+            "caps",
+            gst::Caps::builder("video/x-raw")
+                .field("format", "BGRA")
+                .field("width", SIZE)
+                .field("height", SIZE)
+                .build(),
+        );
+        let tee = gst::ElementFactory::make("tee", None).unwrap();
+        let queue = gst::ElementFactory::make("queue", None).unwrap();
+        let sink = gst::ElementFactory::make("filesink", None).unwrap();
+        let path = std::env::temp_dir().join(format!("waycord_overlay_{}_{:?}.raw", std::process::id(), std::thread::current().id()));
+        sink.set_property("location", path.to_str().unwrap());
+        pipeline.add_many(&[&src, &caps, &tee, &queue, &sink]).unwrap();
+        src.link(&caps).unwrap();
+        gst::Element::link_many(&[&tee, &queue, &sink]).unwrap();
+
+        let mut mgr = OverlayManager::new(pipeline.clone().upcast(), MainContext::default());
+        mgr.attach(&caps, &tee).unwrap();
+        setup(&mut mgr);
+
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let msg = pipeline
+            .bus()
+            .unwrap()
+            .timed_pop_filtered(gst::ClockTime::from_seconds(10), &[gst::MessageType::Eos, gst::MessageType::Error])
+            .expect("pipeline did not finish");
+        pipeline.set_state(gst::State::Null).unwrap();
+        if let gst::MessageView::Error(err) = msg.view() {
+            panic!("pipeline failed: {}", err.error());
+        }
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(data.len(), (SIZE * SIZE * 4) as usize);
+        data
+    }
+
+    fn pixel(data: &[u8], x: i32, y: i32) -> &[u8] {
+        let at = ((y * SIZE + x) * 4) as usize;
+        &data[at..at + 4]
+    }
+
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+    #[test]
+    fn privacy_mask_reaches_the_tee_branches() {
+        let data = recorded_frame(|mgr| {
+            mgr.add_privacy_mask("mask", 0, 0, SIZE / 2, SIZE / 2, MaskEffect::Black).unwrap();
+        });
+        assert_eq!(pixel(&data, 8, 8), BLACK);
+        assert_eq!(pixel(&data, 48, 48), WHITE);
+        assert_eq!(pixel(&data, 48, 8), WHITE);
+    }
+
+    #[test]
+    fn privacy_mode_blacks_out_the_tee_branches() {
+        let data = recorded_frame(|mgr| mgr.set_privacy_mode(true));
+        assert_eq!(pixel(&data, 8, 8), BLACK);
+        assert_eq!(pixel(&data, 48, 48), BLACK);
+    }
 }
@@ -0,0 +1,140 @@
+// src/extensions/privacy_mask.rs
+
+use gtk4::cairo;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use crate::devices::compositor::visible_windows;
+
+// How often tracked windows are looked up
+const TRACK_POLL: Duration = Duration::from_millis(250);
+
+/// What a privacy mask does to the pixels under it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskEffect {
+    /// Soft blur; `strength` is roughly the blur radius in pixels
+    Blur,
+    /// Blocks of `strength` pixels
+    #[default]
+    Pixelate,
+    /// Solid black
+    Black,
+}
+
+/// Follow the windows whose title contains `title`, mapping their compositor
+/// coordinates onto the canvas: canvas = window * scale + offset.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackWindow {
+    pub title: String,
+    #[serde(default)]
+    pub offset_x: i32,
+    #[serde(default)]
+    pub offset_y: i32,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Grow the masked area by this many pixels on each side
+    #[serde(default)]
+    pub padding: i32,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// A rectangle in canvas pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaskRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Apply `effect` to `rect` of the frame `cr` draws on.
+pub fn draw_mask(cr: &cairo::Context, effect: MaskEffect, strength: f64, rect: MaskRect) {
+    if rect.width < 1.0 || rect.height < 1.0 {
+        return;
+    }
+    cr.save().ok();
+    cr.rectangle(rect.x, rect.y, rect.width, rect.height);
+    cr.clip();
+    match effect {
+        MaskEffect::Black => {
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+            let _ = cr.paint();
+        }
+        MaskEffect::Blur | MaskEffect::Pixelate => {
+            let filter = if effect == MaskEffect::Blur { cairo::Filter::Bilinear } else { cairo::Filter::Nearest };
+            if downscaled_paint(cr, rect, strength.max(2.0), filter).is_err() {
+                // Never leave the area readable
+                cr.set_source_rgb(0.0, 0.0, 0.0);
+                let _ = cr.paint();
+            }
+        }
+    }
+    cr.restore().ok();
+}
+
+// Internal: shrink the area by `factor` into a scratch surface (averaging the
+// pixels), then paint it back scaled up: blocky with Nearest, blurred with Bilinear
+fn downscaled_paint(cr: &cairo::Context, rect: MaskRect, factor: f64, filter: cairo::Filter) -> Result<(), cairo::Error> {
+    let target = cr.target();
+    target.flush();
+    let small_w = (rect.width / factor).ceil().max(1.0) as i32;
+    let small_h = (rect.height / factor).ceil().max(1.0) as i32;
+    let small = cairo::ImageSurface::create(cairo::Format::ARgb32, small_w, small_h)?;
+    {
+        let scratch = cairo::Context::new(&small)?;
+        scratch.scale(small_w as f64 / rect.width, small_h as f64 / rect.height);
+        scratch.set_source_surface(&target, -rect.x, -rect.y)?;
+        scratch.source().set_filter(cairo::Filter::Good);
+        scratch.source().set_extend(cairo::Extend::Pad);
+        scratch.paint()?;
+    }
+    cr.translate(rect.x, rect.y);
+    cr.scale(rect.width / small_w as f64, rect.height / small_h as f64);
+    cr.set_source_surface(&small, 0.0, 0.0)?;
+    cr.source().set_filter(filter);
+    cr.source().set_extend(cairo::Extend::Pad);
+    cr.paint()
+}
+
+/// Canvas rectangles of the windows matched by a `TrackWindow`, kept up to
+/// date by a background thread. None until the compositor has answered;
+/// the thread stops once the returned handle is dropped.
+pub fn track_windows(track: TrackWindow) -> Arc<Mutex<Option<Vec<MaskRect>>>> {
+    let rects = Arc::new(Mutex::new(None));
+    let weak: Weak<Mutex<Option<Vec<MaskRect>>>> = Arc::downgrade(&rects);
+    thread::spawn(move || {
+        let mut warned = false;
+        while let Some(rects) = weak.upgrade() {
+            let windows = visible_windows();
+            if windows.is_none() && !warned {
+                eprintln!("Window tracking for privacy masks needs Hyprland (hyprctl) or Sway (swaymsg).");
+                warned = true;
+            }
+            let needle = track.title.to_lowercase();
+            *rects.lock().unwrap() = windows.map(|windows| {
+                windows
+                    .into_iter()
+                    .filter(|w| w.title.to_lowercase().contains(&needle))
+                    .map(|w| {
+                        let pad = track.padding as f64;
+                        MaskRect {
+                            x: w.x as f64 * track.scale + track.offset_x as f64 - pad,
+                            y: w.y as f64 * track.scale + track.offset_y as f64 - pad,
+                            width: w.width as f64 * track.scale + 2.0 * pad,
+                            height: w.height as f64 * track.scale + 2.0 * pad,
+                        }
+                    })
+                    .collect()
+            });
+            drop(rects);
+            thread::sleep(TRACK_POLL);
+        }
+    });
+    rects
+}
@@ -0,0 +1,165 @@
+// src/extensions/recording_stamp.rs
+
+use gstreamer as gst;
+use glib::MainContext;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::extensions::overlays::{OverlayDesc, OverlayKind, OverlayManager};
+use crate::extensions::text_providers::TextProvider;
+use crate::extensions::text_style::{Anchor, TextBackground, TextStyle};
+
+/// An image burned into every recorded frame.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watermark {
+    pub path: String,
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default = "default_watermark_opacity")]
+    pub opacity: f64,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+}
+
+/// Watermark and metadata text stamped on recordings only; the preview and
+/// streams are left alone. Positions are pixels of the recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingStamp {
+    #[serde(default)]
+    pub enabled: bool,
+    /// "{time}" (local, with milliseconds), "{host}", "{user}" and "{id}"
+    /// (this recording's ID) are filled in
+    #[serde(default = "default_stamp_text")]
+    pub text: String,
+    #[serde(default = "default_margin")]
+    pub x: i32,
+    #[serde(default = "default_margin")]
+    pub y: i32,
+    #[serde(default)]
+    pub anchor: Anchor,
+    #[serde(default = "default_stamp_style")]
+    pub style: TextStyle,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<Watermark>,
+}
+
+impl Default for RecordingStamp {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: default_stamp_text(),
+            x: default_margin(),
+            y: default_margin(),
+            anchor: Anchor::TopLeft,
+            style: default_stamp_style(),
+            watermark: None,
+        }
+    }
+}
+
+fn default_watermark_opacity() -> f64 {
+    0.5
+}
+fn default_scale() -> f64 {
+    1.0
+}
+fn default_stamp_text() -> String {
+    "{time}  {user}@{host}  {id}".to_string()
+}
+fn default_margin() -> i32 {
+    16
+}
+fn default_stamp_style() -> TextStyle {
+    TextStyle {
+        font: "Monospace 14".to_string(),
+        background: Some(TextBackground {
+            color: 0x99000000,
+            padding: 6.0,
+            corner_radius: 0.0,
+        }),
+        ..TextStyle::default()
+    }
+}
+
+impl RecordingStamp {
+    pub fn default_path() -> PathBuf {
+        glib::user_config_dir().join("waycord").join("recording_stamp.toml")
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The saved stamp settings, or disabled defaults if there are none or
+    /// they are unreadable.
+    pub fn load_or_default() -> Self {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load_from_file(&path).unwrap_or_else(|e| {
+            eprintln!("Ignoring saved recording stamp: {:?}", e);
+            Self::default()
+        })
+    }
+
+    /// Stamp the frames flowing from `upstream` to `downstream` of a
+    /// recording's bin, which must not be linked to each other. The
+    /// returned manager holds the overlays and must live as long as the
+    /// recording.
+    pub fn attach(
+        &self,
+        bin: &gst::Bin,
+        upstream: &gst::Element,
+        downstream: &gst::Element,
+        recording_id: &str,
+    ) -> Result<OverlayManager> {
+        let mut overlays = OverlayManager::new(bin.clone(), MainContext::default());
+        overlays.attach(upstream, downstream)?;
+        if let Some(watermark) = &self.watermark {
+            let added = overlays.add_overlay(OverlayDesc {
+                id: "rec_watermark".to_string(),
+                x: watermark.x,
+                y: watermark.y,
+                visible: true,
+                kind: OverlayKind::Image {
+                    path: watermark.path.clone(),
+                    opacity: watermark.opacity,
+                    scale: watermark.scale,
+                },
+            });
+            // The text stamp matters more than the image
+            if let Err(e) = added {
+                eprintln!("Recording without watermark: {:?}", e);
+            }
+        }
+        overlays.add_overlay(OverlayDesc {
+            id: "rec_stamp".to_string(),
+            x: self.x,
+            y: self.y,
+            visible: true,
+            kind: OverlayKind::Text {
+                text: self.text.clone(),
+                anchor: self.anchor,
+                style: self.style.clone(),
+                rotation: None,
+                provider: Some(TextProvider::Stamp {
+                    recording_id: recording_id.to_string(),
+                }),
+            },
+        })?;
+        Ok(overlays)
+    }
+}
@@ -0,0 +1,411 @@
+// src/extensions/scene_automation.rs
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::encoder::{monitor_audio_levels, LevelMonitor, VOLUME_DATA};
+use crate::devices::compositor::{focused_window, FocusedWindow};
+use crate::extensions::scene_switcher::SceneSwitcher;
+use crate::extensions::source_registry::SourceId;
+
+// How many fired rules the history keeps
+const HISTORY_LEN: usize = 50;
+// How often the focused window is queried
+const FOCUS_POLL: Duration = Duration::from_secs(1);
+
+/// Rules plus the profile that selects which of them run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutomationConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_profile")]
+    pub profile: String,
+    #[serde(default)]
+    pub rules: Vec<AutomationRule>,
+}
+
+impl Default for AutomationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            profile: default_profile(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Switch to `scene` when `trigger` fires.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutomationRule {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Profiles the rule runs in; empty means every profile
+    #[serde(default)]
+    pub profiles: Vec<String>,
+    pub trigger: RuleTrigger,
+    /// Name of the scene to switch to
+    pub scene: String,
+    /// For focus and silence rules: go back to the previous scene once the
+    /// condition no longer holds
+    #[serde(default)]
+    pub revert: bool,
+}
+
+/// What makes a rule fire.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleTrigger {
+    /// The focused window's title or app ID contains the given text (case-insensitive).
+    /// Needs Hyprland or Sway.
+    WindowFocus {
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        app_id: Option<String>,
+    },
+    /// An audio source (PipeWire node name) peaks below `threshold_db` for
+    /// `seconds`. Its level is measured while the rule is loaded.
+    Silence {
+        source: String,
+        #[serde(default = "default_silence_db")]
+        threshold_db: f64,
+        seconds: f64,
+    },
+    /// Every day at a local time, "HH:MM"
+    Schedule { at: String },
+    /// `seconds` after a scene became active; only while `from_scene` is on
+    /// screen when given
+    Timer {
+        seconds: f64,
+        #[serde(default)]
+        from_scene: Option<String>,
+    },
+    /// A media file source played to its end
+    MediaEnd { source: SourceId },
+}
+
+fn default_true() -> bool {
+    true
+}
+fn default_profile() -> String {
+    "Default".to_string()
+}
+fn default_silence_db() -> f64 {
+    -50.0
+}
+
+impl AutomationConfig {
+    /// Where the rules are kept between runs.
+    pub fn default_path() -> PathBuf {
+        glib::user_config_dir().join("waycord").join("automation.toml")
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read automation rules {}: {}", path.display(), e))?;
+        toml::from_str(&text).map_err(|e| anyhow!("Invalid automation rules: {}", e))
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        let text = toml::to_string_pretty(self).map_err(|e| anyhow!("Failed to encode TOML: {}", e))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// The saved rules, or none if there are none yet.
+    pub fn load_or_default() -> Self {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load_from_file(&path).unwrap_or_else(|e| {
+            eprintln!("Ignoring saved automation rules: {:?}", e);
+            Self::default()
+        })
+    }
+}
+
+impl AutomationRule {
+    fn runs_in(&self, profile: &str) -> bool {
+        self.enabled && (self.profiles.is_empty() || self.profiles.iter().any(|p| p == profile))
+    }
+}
+
+#[derive(Default)]
+struct RuleState {
+    // Whether the condition held on the last tick
+    active: bool,
+    // Since when a silence condition has held
+    since: Option<Instant>,
+    // Scene to go back to when a reverting rule lets go
+    previous_scene: Option<usize>,
+    // Day (y, m, d) a schedule rule last fired
+    fired_on: Option<(i32, i32, i32)>,
+}
+
+/// Evaluates the rules a couple of times per second and switches scenes
+/// through the scene switcher's configured transition.
+pub struct AutomationEngine {
+    config: AutomationConfig,
+    states: Vec<RuleState>,
+    // Shared with the focus thread, which stops once this is dropped
+    focused: Option<Arc<Mutex<Option<FocusedWindow>>>>,
+    // Measures the sources silence rules listen to
+    levels: Option<LevelMonitor>,
+    scene: Option<usize>,
+    scene_since: Instant,
+    history: VecDeque<String>,
+}
+
+impl AutomationEngine {
+    pub fn new(config: AutomationConfig) -> Self {
+        let mut engine = Self {
+            config: AutomationConfig::default(),
+            states: Vec::new(),
+            focused: None,
+            levels: None,
+            scene: None,
+            scene_since: Instant::now(),
+            history: VecDeque::new(),
+        };
+        engine.set_config(config);
+        engine
+    }
+
+    pub fn config(&self) -> &AutomationConfig {
+        &self.config
+    }
+
+    /// Replace the rules; their state starts over.
+    pub fn set_config(&mut self, config: AutomationConfig) {
+        let needs_focus = config
+            .rules
+            .iter()
+            .any(|r| matches!(r.trigger, RuleTrigger::WindowFocus { .. }));
+        if !needs_focus {
+            self.focused = None;
+        } else if self.focused.is_none() {
+            self.focused = Some(spawn_focus_watcher());
+        }
+        let silence_sources: Vec<String> = config
+            .rules
+            .iter()
+            .filter_map(|r| match &r.trigger {
+                RuleTrigger::Silence { source, .. } => Some(source.clone()),
+                _ => None,
+            })
+            .collect();
+        // Drop the old monitor first, which clears its levels
+        self.levels = None;
+        if !silence_sources.is_empty() {
+            self.levels = monitor_audio_levels(&silence_sources)
+                .map_err(|e| eprintln!("Silence rules cannot measure audio levels: {:?}", e))
+                .ok();
+        }
+        self.states = config.rules.iter().map(|_| RuleState::default()).collect();
+        self.config = config;
+    }
+
+    /// Fired rules, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &String> {
+        self.history.iter()
+    }
+
+    /// Check every rule once.
+    pub fn tick(&mut self, switcher: &mut SceneSwitcher) {
+        let current = switcher.current_scene_index();
+        if self.scene != Some(current) {
+            self.scene = Some(current);
+            self.scene_since = Instant::now();
+        }
+        let ended = switcher.registry().take_ended();
+        if !self.config.enabled {
+            return;
+        }
+        let focused = self.focused.as_ref().and_then(|f| f.lock().unwrap().clone());
+        let now = glib::DateTime::now_local().ok();
+        let levels = VOLUME_DATA.lock().unwrap().clone();
+
+        for idx in 0..self.config.rules.len() {
+            let rule = self.config.rules[idx].clone();
+            if !rule.runs_in(&self.config.profile) {
+                continue;
+            }
+            let state = &mut self.states[idx];
+            // (fire now, condition still holds) for level triggers; edge triggers only fire
+            let (fire, holds) = match &rule.trigger {
+                RuleTrigger::WindowFocus { title, app_id } => {
+                    let matches = focused.as_ref().map(|w| window_matches(w, title, app_id)).unwrap_or(false);
+                    (matches && !state.active, matches)
+                }
+                RuleTrigger::Silence { source, threshold_db, seconds } => {
+                    let silent = levels.get(source).map(|peak| *peak < *threshold_db).unwrap_or(false);
+                    if !silent {
+                        state.since = None;
+                    } else if state.since.is_none() {
+                        state.since = Some(Instant::now());
+                    }
+                    let long_enough = state
+                        .since
+                        .map(|t| t.elapsed().as_secs_f64() >= *seconds)
+                        .unwrap_or(false);
+                    (long_enough && !state.active, long_enough)
+                }
+                RuleTrigger::Schedule { at } => {
+                    let due = match (&now, parse_time_of_day(at)) {
+                        (Some(now), Some((h, m))) => {
+                            let today = (now.year(), now.month(), now.day_of_month());
+                            let due = now.hour() == h && now.minute() == m && state.fired_on != Some(today);
+                            if due {
+                                state.fired_on = Some(today);
+                            }
+                            due
+                        }
+                        _ => false,
+                    };
+                    (due, false)
+                }
+                RuleTrigger::Timer { seconds, from_scene } => {
+                    let on_scene = match from_scene {
+                        Some(name) => switcher.scenes().get(current).map(|s| &s.name == name).unwrap_or(false),
+                        None => true,
+                    };
+                    let elapsed = on_scene && self.scene_since.elapsed().as_secs_f64() >= *seconds;
+                    (elapsed && !state.active, elapsed)
+                }
+                RuleTrigger::MediaEnd { source } => (ended.contains(source), false),
+            };
+
+            if fire {
+                let target = switcher.scenes().iter().position(|s| s.name == rule.scene);
+                match target {
+                    Some(target) if target != current => {
+                        state.previous_scene = Some(current);
+                        match switcher.switch_to_scene(target) {
+                            Ok(()) => self.log(format!("Rule '{}' switched to scene '{}'", rule.name, rule.scene)),
+                            Err(e) => self.log(format!("Rule '{}' failed: {:?}", rule.name, e)),
+                        }
+                    }
+                    Some(_) => {}
+                    None => self.log(format!("Rule '{}': no scene named '{}'", rule.name, rule.scene)),
+                }
+            } else if !holds && self.states[idx].active && rule.revert {
+                // Condition let go: return if nothing else moved us on
+                let state = &mut self.states[idx];
+                let on_rule_scene =
+                    switcher.scenes().get(switcher.current_scene_index()).map(|s| s.name == rule.scene) == Some(true);
+                if let (Some(previous), true) = (state.previous_scene.take(), on_rule_scene) {
+                    if switcher.switch_to_scene(previous).is_ok() {
+                        self.log(format!("Rule '{}' reverted to scene {}", rule.name, previous));
+                    }
+                }
+            }
+            self.states[idx].active = holds;
+        }
+    }
+
+    fn log(&mut self, line: String) {
+        println!("Automation: {}", line);
+        let stamp = glib::DateTime::now_local()
+            .and_then(|t| t.format("%H:%M:%S"))
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        self.history.push_back(format!("{} {}", stamp, line));
+        if self.history.len() > HISTORY_LEN {
+            self.history.pop_front();
+        }
+    }
+}
+
+fn window_matches(window: &FocusedWindow, title: &Option<String>, app_id: &Option<String>) -> bool {
+    let contains = |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.to_lowercase());
+    let title_ok = title.as_ref().map(|t| contains(&window.title, t)).unwrap_or(true);
+    let app_ok = app_id.as_ref().map(|a| contains(&window.app_id, a)).unwrap_or(true);
+    (title.is_some() || app_id.is_some()) && title_ok && app_ok
+}
+
+fn parse_time_of_day(at: &str) -> Option<(i32, i32)> {
+    let (h, m) = at.trim().split_once(':')?;
+    let (h, m) = (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?);
+    ((0..24).contains(&h) && (0..60).contains(&m)).then_some((h, m))
+}
+
+/// Poll the compositor for the focused window on a background thread,
+/// which stops once the returned handle is dropped.
+fn spawn_focus_watcher() -> Arc<Mutex<Option<FocusedWindow>>> {
+    let focused = Arc::new(Mutex::new(None));
+    let weak: Weak<Mutex<Option<FocusedWindow>>> = Arc::downgrade(&focused);
+    thread::spawn(move || {
+        let mut warned = false;
+        while let Some(focused) = weak.upgrade() {
+            let window = focused_window();
+            if window.is_none() && !warned {
+                eprintln!("Window focus rules need Hyprland (hyprctl) or Sway (swaymsg).");
+                warned = true;
+            }
+            *focused.lock().unwrap() = window;
+            drop(focused);
+            thread::sleep(FOCUS_POLL);
+        }
+    });
+    focused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(title: &str, app_id: &str) -> FocusedWindow {
+        FocusedWindow {
+            title: title.to_string(),
+            app_id: app_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_time_of_day_accepts_hours_and_minutes() {
+        assert_eq!(parse_time_of_day("09:30"), Some((9, 30)));
+        assert_eq!(parse_time_of_day(" 0:00 "), Some((0, 0)));
+        assert_eq!(parse_time_of_day("23:59"), Some((23, 59)));
+    }
+
+    #[test]
+    fn parse_time_of_day_rejects_out_of_range_and_malformed() {
+        assert_eq!(parse_time_of_day("24:00"), None);
+        assert_eq!(parse_time_of_day("12:60"), None);
+        assert_eq!(parse_time_of_day("-1:10"), None);
+        assert_eq!(parse_time_of_day("1230"), None);
+        assert_eq!(parse_time_of_day("ab:cd"), None);
+        assert_eq!(parse_time_of_day(""), None);
+    }
+
+    #[test]
+    fn window_matches_title_and_app_id_case_insensitively() {
+        let w = window("Release notes - Mozilla Firefox", "firefox");
+        assert!(window_matches(&w, &Some("firefox".into()), &None));
+        assert!(window_matches(&w, &None, &Some("FireFox".into())));
+        assert!(window_matches(&w, &Some("release".into()), &Some("fire".into())));
+    }
+
+    #[test]
+    fn window_matches_needs_every_given_field() {
+        let w = window("Terminal", "foot");
+        assert!(!window_matches(&w, &Some("terminal".into()), &Some("kitty".into())));
+        assert!(!window_matches(&w, &Some("editor".into()), &None));
+    }
+
+    #[test]
+    fn window_matches_nothing_without_criteria() {
+        assert!(!window_matches(&window("Terminal", "foot"), &None, &None));
+    }
+}
@@ -0,0 +1,708 @@
+// src/extensions/scene_collection.rs
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::devices::camera::CameraConfig;
+use crate::extensions::camera_effects::{
+    BackgroundEffect, CameraEffectChain, CameraEffects, CameraMask, ChromaKey, ChromaKeyColor,
+};
+use crate::extensions::scene_switcher::{Scene, SceneSource};
+use crate::extensions::source_registry::{SourceDef, SourceId, SourceKind, SourceRegistry};
+use crate::extensions::source_transform::SourceTransform;
+
+/// A shareable set of sources and scenes. Sources are described by kind and
+/// settings, never by element handles, so a collection can be saved and loaded.
+/// Scenes refer to sources by ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneCollection {
+    pub name: String,
+    #[serde(default)]
+    pub canvas: Canvas,
+    pub sources: Vec<SourceDef>,
+    pub scenes: Vec<SceneDesc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Canvas {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneDesc {
+    pub name: String,
+    #[serde(default)]
+    pub sources: Vec<SourceDesc>,
+    #[serde(default)]
+    pub groups: Vec<GroupDesc>,
+}
+
+/// Sources that move and scale together. Items in a group have their geometry
+/// relative to the group origin, multiplied by the group scale, which must be
+/// positive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupDesc {
+    pub name: String,
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    #[serde(default = "default_one")]
+    pub scale: f64,
+}
+
+impl GroupDesc {
+    /// Canvas geometry of an item placed in this group.
+    pub fn to_canvas(&self, g: &Geometry) -> Geometry {
+        let scale = |v: i32| (v as f64 * self.scale).round() as i32;
+        Geometry {
+            x: self.x + scale(g.x),
+            y: self.y + scale(g.y),
+            width: scale(g.width),
+            height: scale(g.height),
+        }
+    }
+
+    /// Inverse of `to_canvas`, exact for geometry `to_canvas` produced at
+    /// scales of 1 and above. Smaller scales lose detail to rounding, which
+    /// is why scenes keep the group-local geometry rather than recomputing it.
+    pub fn from_canvas(&self, g: &Geometry) -> Geometry {
+        let unscale = |v: i32| (v as f64 / self.scale).round() as i32;
+        Geometry {
+            x: unscale(g.x - self.x),
+            y: unscale(g.y - self.y),
+            width: unscale(g.width),
+            height: unscale(g.height),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// A source placed in a scene.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceDesc {
+    /// ID of a source in the collection's `sources`
+    pub source: SourceId,
+    pub geometry: Geometry,
+    #[serde(default = "default_alpha")]
+    pub alpha: f64,
+    /// Higher values are drawn on top
+    #[serde(default)]
+    pub zorder: u32,
+    #[serde(default)]
+    pub filters: Vec<FilterDesc>,
+    /// Name of a group in the scene's `groups`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "SourceTransform::is_identity")]
+    pub transform: SourceTransform,
+}
+
+/// A per-source video filter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FilterDesc {
+    ColorBalance {
+        #[serde(default)]
+        brightness: f64,
+        #[serde(default = "default_one")]
+        contrast: f64,
+        #[serde(default = "default_one")]
+        saturation: f64,
+    },
+    /// color is "green", "blue" or "#rrggbb"
+    ChromaKey {
+        color: String,
+        #[serde(default = "default_key_angle")]
+        angle: f32,
+        #[serde(default = "default_key_noise")]
+        noise_level: f32,
+    },
+    Circle,
+    RoundedCorners { radius: f64 },
+    Mirror,
+    BackgroundBlur { sigma: f64 },
+    BackgroundReplace { image_path: String },
+}
+
+fn default_alpha() -> f64 {
+    1.0
+}
+fn default_one() -> f64 {
+    1.0
+}
+fn default_key_angle() -> f32 {
+    20.0
+}
+fn default_key_noise() -> f32 {
+    2.0
+}
+
+/// Scenes built from a collection, plus the effect chains feeding their pads.
+pub struct LoadedCollection {
+    pub scenes: Vec<Scene>,
+    pub effect_chains: Vec<(u32, CameraEffectChain)>,
+}
+
+impl SceneCollection {
+    /// Where the working collection is kept between runs.
+    pub fn default_path() -> PathBuf {
+        glib::user_config_dir().join("waycord").join("scenes.json")
+    }
+
+    /// The layout used when no collection has been saved: the screen full
+    /// canvas with the camera (if any) on top.
+    pub fn default_collection(camera: Option<&CameraConfig>) -> Self {
+        let canvas = Canvas::default();
+        let mut sources = vec![SourceDef {
+            id: "screen".to_string(),
+            name: "Screen".to_string(),
+            kind: SourceKind::Screen,
+        }];
+        let mut items = vec![SourceDesc {
+            source: "screen".to_string(),
+            geometry: Geometry {
+                x: 0,
+                y: 0,
+                width: canvas.width,
+                height: canvas.height,
+            },
+            alpha: 1.0,
+            zorder: 0,
+            filters: Vec::new(),
+            group: None,
+            transform: SourceTransform::default(),
+        }];
+        if let Some(cam) = camera {
+            sources.push(SourceDef {
+                id: "camera".to_string(),
+                name: "Camera".to_string(),
+                kind: SourceKind::from_camera(cam),
+            });
+            items.push(SourceDesc {
+                source: "camera".to_string(),
+                geometry: Geometry {
+                    x: 100,
+                    y: 100,
+                    width: cam.mode.width,
+                    height: cam.mode.height,
+                },
+                alpha: 1.0,
+                zorder: 1,
+                filters: Vec::new(),
+                group: None,
+                transform: SourceTransform::default(),
+            });
+        }
+        Self {
+            name: "Default".to_string(),
+            canvas,
+            sources,
+            scenes: vec![SceneDesc {
+                name: "Scene0".to_string(),
+                sources: items,
+                groups: Vec::new(),
+            }],
+        }
+    }
+
+    /// Load a collection; the format follows the extension (.toml, otherwise JSON).
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read scene collection {}: {}", path.display(), e))?;
+        if is_toml(path) {
+            toml::from_str(&text).map_err(|e| anyhow!("Invalid TOML scene collection {}: {}", path.display(), e))
+        } else {
+            serde_json::from_str(&text).map_err(|e| anyhow!("Invalid JSON scene collection {}: {}", path.display(), e))
+        }
+    }
+
+    /// Save the collection; the format follows the extension (.toml, otherwise JSON).
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        let text = if is_toml(path) {
+            toml::to_string_pretty(self).map_err(|e| anyhow!("Failed to encode TOML: {}", e))?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Start every source in the registry and request a compositor pad for
+    /// each scene item. Pads start hidden; the scene switcher shows the active scene.
+    pub fn instantiate(&self, registry: &mut SourceRegistry) -> Result<LoadedCollection> {
+        self.check_scene_sources()?;
+        for scene in &self.scenes {
+            if let Some(group) = scene.groups.iter().find(|g| !(g.scale.is_finite() && g.scale > 0.0)) {
+                return Err(anyhow!(
+                    "Group '{}' in scene '{}' has scale {}; it must be positive",
+                    group.name,
+                    scene.name,
+                    group.scale
+                ));
+            }
+        }
+        registry.set_canvas(self.canvas.width, self.canvas.height);
+        for def in &self.sources {
+            registry.add_source(def.clone())?;
+        }
+
+        let mut scenes = Vec::new();
+        let mut effect_chains = Vec::new();
+        for scene in &self.scenes {
+            let mut sources = Vec::new();
+            for item in &scene.sources {
+                let kind = registry
+                    .get(&item.source)
+                    .map(|def| def.kind.clone())
+                    .ok_or_else(|| anyhow!("Scene '{}' uses unknown source '{}'", scene.name, item.source))?;
+                let geometry = match &item.group {
+                    Some(name) => scene
+                        .groups
+                        .iter()
+                        .find(|g| &g.name == name)
+                        .ok_or_else(|| anyhow!("Scene '{}' has no group '{}'", scene.name, name))?
+                        .to_canvas(&item.geometry),
+                    None => item.geometry,
+                };
+                // Cameras always get an effect chain so effects can be changed later
+                let effects = effects_from_filters(&item.filters);
+                let (pad_index, chain) = registry.request_pad(
+                    &item.source,
+                    effects.is_some() || kind.is_camera(),
+                    geometry.width,
+                    geometry.height,
+                )?;
+                if let Some(chain) = chain {
+                    effect_chains.push((pad_index, chain));
+                }
+                sources.push(SceneSource {
+                    source_id: item.source.clone(),
+                    pad_index,
+                    x: geometry.x,
+                    y: geometry.y,
+                    width: geometry.width,
+                    height: geometry.height,
+                    alpha: item.alpha,
+                    zorder: item.zorder,
+                    effects,
+                    group: item.group.clone(),
+                    local: item.group.as_ref().map(|_| item.geometry),
+                    transform: item.transform,
+                });
+            }
+            scenes.push(Scene {
+                name: scene.name.clone(),
+                sources,
+                groups: scene.groups.clone(),
+            });
+        }
+
+        Ok(LoadedCollection { scenes, effect_chains })
+    }
+
+    /// Every scene source must name an existing scene and must not end up
+    /// inside itself, which would loop the pipeline.
+    pub fn check_scene_sources(&self) -> Result<()> {
+        for def in &self.sources {
+            if let Some(scene) = def.kind.nested_scene() {
+                if self.scene_contains(scene, &def.id, 0)? {
+                    return Err(anyhow!("Scene source '{}' shows a scene containing itself", def.id));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scene_contains(&self, scene_name: &str, id: &str, depth: usize) -> Result<bool> {
+        if depth > self.scenes.len() {
+            return Ok(true); // only a cycle nests deeper than the number of scenes
+        }
+        let scene = self
+            .scenes
+            .iter()
+            .find(|s| s.name == scene_name)
+            .ok_or_else(|| anyhow!("No scene named '{}' for a scene source", scene_name))?;
+        for item in &scene.sources {
+            if item.source == id {
+                return Ok(true);
+            }
+            let nested = self
+                .sources
+                .iter()
+                .find(|d| d.id == item.source)
+                .and_then(|d| d.kind.nested_scene());
+            if let Some(nested) = nested {
+                if self.scene_contains(nested, id, depth + 1)? {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Fold a filter list into the effect settings of an effect chain.
+pub fn effects_from_filters(filters: &[FilterDesc]) -> Option<CameraEffects> {
+    if filters.is_empty() {
+        return None;
+    }
+    let mut fx = CameraEffects::default();
+    for filter in filters {
+        match filter {
+            FilterDesc::ColorBalance { brightness, contrast, saturation } => {
+                fx.balance.brightness = *brightness;
+                fx.balance.contrast = *contrast;
+                fx.balance.saturation = *saturation;
+            }
+            FilterDesc::ChromaKey { color, angle, noise_level } => {
+                let color = match color.as_str() {
+                    "green" => ChromaKeyColor::Green,
+                    "blue" => ChromaKeyColor::Blue,
+                    hex => match parse_hex_rgb(hex) {
+                        Some((r, g, b)) => ChromaKeyColor::Custom(r, g, b),
+                        None => {
+                            eprintln!("Ignoring chroma key with unknown color '{}'", hex);
+                            continue;
+                        }
+                    },
+                };
+                fx.chroma_key = Some(ChromaKey {
+                    color,
+                    angle: *angle,
+                    noise_level: *noise_level,
+                });
+            }
+            FilterDesc::Circle => fx.mask = Some(CameraMask::Circle),
+            FilterDesc::RoundedCorners { radius } => fx.mask = Some(CameraMask::RoundedRect { radius: *radius }),
+            FilterDesc::Mirror => fx.mirror = true,
+            FilterDesc::BackgroundBlur { sigma } => fx.background = Some(BackgroundEffect::Blur { sigma: *sigma }),
+            FilterDesc::BackgroundReplace { image_path } => {
+                fx.background = Some(BackgroundEffect::Replace {
+                    image_path: image_path.clone(),
+                })
+            }
+        }
+    }
+    Some(fx)
+}
+
+/// The inverse of `effects_from_filters`, used when exporting.
+pub fn filters_from_effects(fx: &CameraEffects) -> Vec<FilterDesc> {
+    let mut filters = Vec::new();
+    let defaults = CameraEffects::default();
+    if fx.balance != defaults.balance {
+        filters.push(FilterDesc::ColorBalance {
+            brightness: fx.balance.brightness,
+            contrast: fx.balance.contrast,
+            saturation: fx.balance.saturation,
+        });
+    }
+    if let Some(key) = fx.chroma_key {
+        let color = match key.color {
+            ChromaKeyColor::Green => "green".to_string(),
+            ChromaKeyColor::Blue => "blue".to_string(),
+            ChromaKeyColor::Custom(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        };
+        filters.push(FilterDesc::ChromaKey {
+            color,
+            angle: key.angle,
+            noise_level: key.noise_level,
+        });
+    }
+    match fx.mask {
+        Some(CameraMask::Circle) => filters.push(FilterDesc::Circle),
+        Some(CameraMask::RoundedRect { radius }) => filters.push(FilterDesc::RoundedCorners { radius }),
+        None => {}
+    }
+    if fx.mirror {
+        filters.push(FilterDesc::Mirror);
+    }
+    match &fx.background {
+        Some(BackgroundEffect::Blur { sigma }) => filters.push(FilterDesc::BackgroundBlur { sigma: *sigma }),
+        Some(BackgroundEffect::Replace { image_path }) => filters.push(FilterDesc::BackgroundReplace {
+            image_path: image_path.clone(),
+        }),
+        None => {}
+    }
+    filters
+}
+
+/// "#rrggbb" as (r, g, b).
+pub(crate) fn parse_hex_rgb(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(((value >> 16) as u8, (value >> 8) as u8, value as u8))
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension().map(|e| e == "toml").unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(x: i32, y: i32, scale: f64) -> GroupDesc {
+        GroupDesc {
+            name: "g".to_string(),
+            x,
+            y,
+            scale,
+        }
+    }
+
+    fn geometry(x: i32, y: i32, width: i32, height: i32) -> Geometry {
+        Geometry { x, y, width, height }
+    }
+
+    #[test]
+    fn to_canvas_offsets_and_scales() {
+        let g = group(100, 50, 2.0);
+        assert_eq!(g.to_canvas(&geometry(10, 20, 320, 180)), geometry(120, 90, 640, 360));
+    }
+
+    #[test]
+    fn from_canvas_inverts_to_canvas() {
+        let local = geometry(-15, 7, 333, 211);
+        for scale in [1.0, 1.5, 2.0, 3.25] {
+            let g = group(40, -12, scale);
+            assert_eq!(g.from_canvas(&g.to_canvas(&local)), local, "scale {}", scale);
+        }
+    }
+
+    #[test]
+    fn from_canvas_at_scale_one_is_a_translation() {
+        let g = group(30, 40, 1.0);
+        assert_eq!(g.from_canvas(&geometry(35, 47, 100, 80)), geometry(5, 7, 100, 80));
+    }
+
+    #[test]
+    fn to_canvas_rounds_fractional_sizes() {
+        let g = group(0, 0, 0.5);
+        assert_eq!(g.to_canvas(&geometry(3, 5, 101, 99)), geometry(2, 3, 51, 50));
+    }
+
+    // Internal: a collection using groups, filters, transforms and most source kinds
+    fn sample_collection() -> SceneCollection {
+        let mut collection = SceneCollection::default_collection(Some(&CameraConfig::test_pattern(640, 480, 30)));
+        collection.name = "Talk".to_string();
+        collection.canvas = Canvas { width: 1920, height: 1080 };
+        collection.sources.extend([
+            SourceDef {
+                id: "logo".to_string(),
+                name: "Logo".to_string(),
+                kind: SourceKind::Image {
+                    path: "/tmp/logo.png".to_string(),
+                },
+            },
+            SourceDef {
+                id: "intro".to_string(),
+                name: "Intro".to_string(),
+                kind: SourceKind::MediaFile {
+                    uri: "file:///tmp/intro.webm".to_string(),
+                    looping: true,
+                    restart_on_activate: false,
+                    audio: true,
+                },
+            },
+            SourceDef {
+                id: "main".to_string(),
+                name: "Main scene".to_string(),
+                kind: SourceKind::Scene {
+                    scene: "Scene0".to_string(),
+                },
+            },
+        ]);
+        let mut transform = SourceTransform::default();
+        transform.crop.left = 10;
+        transform.rotation = 90;
+        transform.flip_horizontal = true;
+        collection.scenes.push(SceneDesc {
+            name: "Picture in picture".to_string(),
+            sources: vec![
+                SourceDesc {
+                    source: "main".to_string(),
+                    geometry: geometry(0, 0, 1920, 1080),
+                    alpha: 1.0,
+                    zorder: 0,
+                    filters: Vec::new(),
+                    group: None,
+                    transform: SourceTransform::default(),
+                },
+                SourceDesc {
+                    source: "camera".to_string(),
+                    geometry: geometry(10, 20, 320, 240),
+                    alpha: 0.5,
+                    zorder: 2,
+                    filters: vec![
+                        FilterDesc::ChromaKey {
+                            color: "#00ff00".to_string(),
+                            angle: 20.0,
+                            noise_level: 2.0,
+                        },
+                        FilterDesc::Circle,
+                        FilterDesc::BackgroundBlur { sigma: 4.5 },
+                    ],
+                    group: Some("corner".to_string()),
+                    transform,
+                },
+                SourceDesc {
+                    source: "logo".to_string(),
+                    geometry: geometry(0, 0, 100, 100),
+                    alpha: 1.0,
+                    zorder: 1,
+                    filters: Vec::new(),
+                    group: Some("corner".to_string()),
+                    transform: SourceTransform::default(),
+                },
+            ],
+            groups: vec![GroupDesc {
+                name: "corner".to_string(),
+                x: 1500,
+                y: 700,
+                scale: 1.25,
+            }],
+        });
+        collection
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("waycord_collection_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn save_then_load_gives_the_same_collection() {
+        let collection = sample_collection();
+        for name in ["round_trip.json", "round_trip.toml"] {
+            let path = temp_path(name);
+            collection.save_to_file(&path).unwrap();
+            let loaded = SceneCollection::load_from_file(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.unwrap(), collection, "{}", name);
+        }
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        let collection = sample_collection();
+        let json = temp_path("format.json");
+        let toml = temp_path("format.toml");
+        collection.save_to_file(&json).unwrap();
+        collection.save_to_file(&toml).unwrap();
+        let json_text = std::fs::read_to_string(&json).unwrap();
+        let toml_text = std::fs::read_to_string(&toml).unwrap();
+        std::fs::remove_file(&json).unwrap();
+        std::fs::remove_file(&toml).unwrap();
+        assert!(serde_json::from_str::<serde_json::Value>(&json_text).is_ok());
+        assert!(toml::from_str::<toml::Value>(&toml_text).is_ok());
+        assert!(serde_json::from_str::<serde_json::Value>(&toml_text).is_err());
+    }
+
+    // Internal: a scene source showing `scene`, placed full canvas in `into`
+    fn nest(collection: &mut SceneCollection, id: &str, scene: &str, into: &str) {
+        collection.sources.push(SourceDef {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind: SourceKind::Scene { scene: scene.to_string() },
+        });
+        let into = collection.scenes.iter_mut().find(|s| s.name == into).unwrap();
+        into.sources.push(SourceDesc {
+            source: id.to_string(),
+            geometry: geometry(0, 0, 1280, 720),
+            alpha: 1.0,
+            zorder: 5,
+            filters: Vec::new(),
+            group: None,
+            transform: SourceTransform::default(),
+        });
+    }
+
+    fn with_scenes(names: &[&str]) -> SceneCollection {
+        let mut collection = SceneCollection::default_collection(None);
+        for name in names {
+            collection.scenes.push(SceneDesc {
+                name: name.to_string(),
+                sources: Vec::new(),
+                groups: Vec::new(),
+            });
+        }
+        collection
+    }
+
+    #[test]
+    fn scene_sources_may_nest_without_cycles() {
+        let mut collection = with_scenes(&["A", "B"]);
+        nest(&mut collection, "show_a", "A", "Scene0");
+        nest(&mut collection, "show_b", "B", "A");
+        // The same scene twice is fine, only containing itself is not
+        nest(&mut collection, "show_b_again", "B", "Scene0");
+        assert!(collection.check_scene_sources().is_ok());
+    }
+
+    #[test]
+    fn scene_source_in_its_own_scene_is_rejected() {
+        let mut collection = with_scenes(&["A"]);
+        nest(&mut collection, "show_a", "A", "A");
+        assert!(collection.check_scene_sources().is_err());
+    }
+
+    #[test]
+    fn indirect_scene_cycles_are_rejected() {
+        let mut collection = with_scenes(&["A", "B", "C"]);
+        nest(&mut collection, "show_b", "B", "A");
+        nest(&mut collection, "show_c", "C", "B");
+        nest(&mut collection, "show_a", "A", "C");
+        assert!(collection.check_scene_sources().is_err());
+    }
+
+    #[test]
+    fn scene_source_of_a_missing_scene_is_rejected() {
+        let mut collection = with_scenes(&[]);
+        nest(&mut collection, "show_gone", "Gone", "Scene0");
+        assert!(collection.check_scene_sources().is_err());
+    }
+
+    #[test]
+    fn missing_or_invalid_files_are_errors() {
+        assert!(SceneCollection::load_from_file(&temp_path("missing.json")).is_err());
+        for (name, text) in [
+            ("broken.json", "{ \"name\": "),
+            ("broken.toml", "name = "),
+            // Valid syntax, but scenes are missing
+            ("incomplete.json", "{ \"name\": \"x\", \"sources\": [] }"),
+            ("unknown_kind.toml", "name = \"x\"\nscenes = []\n[[sources]]\nid = \"a\"\nname = \"A\"\nkind = { type = \"hologram\" }\n"),
+        ] {
+            let path = temp_path(name);
+            std::fs::write(&path, text).unwrap();
+            let loaded = SceneCollection::load_from_file(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert!(loaded.is_err(), "{} loaded", name);
+        }
+    }
+}
@@ -4,14 +4,36 @@ use gstreamer as gst;
 use gstreamer::prelude::*;
 use anyhow::{anyhow, Result};
 use glib::MainContext;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, Instant};
+use std::time::Duration;
+
+use crate::extensions::camera_effects::{CameraEffectChain, CameraEffects};
+use crate::extensions::scene_collection::{
+    effects_from_filters, filters_from_effects, Canvas, FilterDesc, Geometry, GroupDesc, SceneCollection,
+    SceneDesc, SourceDesc,
+};
+use crate::extensions::source_registry::{SourceDef, SourceId, SourceKind, SourceRegistry};
+use crate::devices::screen::capture_area;
+use crate::extensions::source_transform::{apply_pad_layout, ScaleMode, SourceTransform, TransformStage};
+use crate::extensions::transitions::{Easing, Fade, Transition, TransitionContext};
+
+// Keyframes start this many frames ahead, so the first one is not already in the past
+const TRANSITION_LEAD_FRAMES: u64 = 2;
+
+// The transition whose keyframes are playing, with the running time of its last keyframe
+type RunningTransition = (gst::ClockTime, Box<dyn Transition>, TransitionContext);
+
+// A scene item copied onto a scene source's compositor: source, pad there, effect chain
+type NestedItem = (SourceId, u32, Option<CameraEffectChain>);
 
 #[derive(Clone)]
 pub struct SceneSource {
-    /// The element providing the video (e.g. pipewiresrc, v4l2src, test video, etc.)
-    pub element: gst::Element,
-    /// The compositor sink pad index or name
+    /// The registered source providing the video
+    pub source_id: SourceId,
+    /// The compositor sink pad index or name (each scene item has its own pad)
     pub pad_index: u32,
     /// Current geometry or alpha
     pub x: i32,
@@ -19,16 +41,51 @@ pub struct SceneSource {
     pub width: i32,
     pub height: i32,
     pub alpha: f64,
+    /// Stacking order on the compositor, higher is on top
+    pub zorder: u32,
+    /// Camera effects for this source in this scene (only used if an effect chain is registered)
+    pub effects: Option<CameraEffects>,
+    /// Group this source moves with, by name
+    pub group: Option<String>,
+    /// Geometry relative to `group`. Moving or scaling the group recomputes the
+    /// canvas geometry from this, so repeated group changes do not drift.
+    pub local: Option<Geometry>,
+    /// Crop, flips, rotation and scale mode
+    pub transform: SourceTransform,
+}
+
+impl SceneSource {
+    /// Canvas geometry of this source.
+    pub fn geometry(&self) -> Geometry {
+        Geometry {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Put geometry, alpha, z-order and transform on the pad showing this source.
+    pub fn apply_to_pad(&self, pad: &gst::Pad, stage: Option<&TransformStage>) {
+        apply_pad_layout(pad, self.x, self.y, self.width, self.height, &self.transform);
+        pad.set_property("alpha", self.alpha);
+        pad.set_property("zorder", self.zorder);
+        if let Some(stage) = stage {
+            stage.apply(&self.transform, self.width, self.height);
+        }
+    }
 }
 
 /// A single “scene” with multiple sources arranged in a compositor.
+/// Source geometry is always in canvas coordinates, also for grouped sources.
 pub struct Scene {
     pub name: String,
     pub sources: Vec<SceneSource>,
+    pub groups: Vec<GroupDesc>,
 }
 
 /// SceneSwitcher manages multiple Scenes on a single GStreamer compositor.
-/// Transitions can be triggered to fade or wipe from one scene to another.
+/// Transitions (see `extensions::transitions`) move from one scene to another.
 pub struct SceneSwitcher {
     pipeline: gst::Pipeline,
     compositor: gst::Element,
@@ -37,14 +94,28 @@ pub struct SceneSwitcher {
     transition_duration: Duration,
     // Store a reference to the MainContext or similar for scheduling fade tasks
     main_ctx: MainContext,
-    // We keep track of the states of alpha for transitions
-    alpha_map: Arc<Mutex<Vec<f64>>>,
+    // Used by `switch_to_scene`
+    default_transition: Option<Box<dyn Transition>>,
+    running_transition: Rc<RefCell<Option<RunningTransition>>>,
+    // Per-pad counter so a new geometry animation cancels the previous one
+    animation_generations: Arc<Mutex<HashMap<u32, u64>>>,
+    // Same for crop and angle animations
+    transform_generations: Arc<Mutex<HashMap<u32, u64>>>,
+    // Effect chains keyed by the compositor pad they feed
+    effect_chains: HashMap<u32, CameraEffectChain>,
+    // Capture inputs shared by all scenes
+    registry: SourceRegistry,
+    // Items rendered by each scene source, in the order of the shown scene's sources
+    nested: HashMap<SourceId, Vec<NestedItem>>,
+    // Name of the loaded collection, kept when saving
+    collection_name: String,
 }
 
 impl SceneSwitcher {
     /// Provide a reference to the pipeline, a single “compositor” element,
     /// plus an initial set of scenes. Scenes must have their sources added
-    /// to the pipeline & linked, but the arrangement is done here.
+    /// to the pipeline & linked, but the arrangement is done here. Usually the
+    /// scenes are empty and come from `load_collection` instead.
     /// transition_duration is how long transitions (fade/wipe) last.
     pub fn new(
         pipeline: gst::Pipeline,
@@ -55,8 +126,8 @@ impl SceneSwitcher {
     ) -> Self {
         // Add the compositor to the pipeline
         pipeline.add(&compositor).unwrap();
-        // The alpha map will track alpha for each pad. Let's assume an upper bound
-        let alpha_map = Arc::new(Mutex::new(vec![1.0; 32])); // up to 32 pads
+
+        let registry = SourceRegistry::new(pipeline.clone(), compositor.clone());
 
         Self {
             pipeline,
@@ -65,12 +136,19 @@ impl SceneSwitcher {
             current_scene_index: 0,
             transition_duration,
             main_ctx,
-            alpha_map,
+            default_transition: None,
+            running_transition: Rc::new(RefCell::new(None)),
+            animation_generations: Arc::new(Mutex::new(HashMap::new())),
+            transform_generations: Arc::new(Mutex::new(HashMap::new())),
+            effect_chains: HashMap::new(),
+            registry,
+            nested: HashMap::new(),
+            collection_name: "Default".to_string(),
         }
     }
 
-    /// Adds a new scene to the switcher. The sources must already be part
-    /// of the pipeline (e.g. pipeline.add(&source.element) done externally).
+    /// Adds a new scene to the switcher. The sources must already have pads
+    /// from the registry (see `add_source_to_scene` for adding them later).
     pub fn add_scene(&mut self, scene: Scene) -> Result<()> {
         self.scenes.push(scene);
         Ok(())
@@ -82,12 +160,38 @@ impl SceneSwitcher {
             return Err(anyhow!("Scene index out of range."));
         }
         self.current_scene_index = index;
+        // Stop any transition still animating the pads
+        self.stop_running_transition();
         self.apply_scene_layout(index)?;
+        self.restart_scene_media(index, None);
         Ok(())
     }
 
     /// Switch from current scene to another scene with a fade transition
     pub fn fade_to_scene(&mut self, new_index: usize) -> Result<()> {
+        let fade = Fade {
+            duration: self.transition_duration,
+            easing: Easing::Linear,
+        };
+        self.transition_to_scene(new_index, Box::new(fade))
+    }
+
+    /// Transition used by `switch_to_scene`; a fade until one is set.
+    pub fn set_transition(&mut self, transition: Box<dyn Transition>) {
+        self.default_transition = Some(transition);
+    }
+
+    /// Switch to another scene with the configured transition.
+    pub fn switch_to_scene(&mut self, new_index: usize) -> Result<()> {
+        match self.default_transition.as_ref().map(|t| t.boxed_clone()) {
+            Some(transition) => self.transition_to_scene(new_index, transition),
+            None => self.fade_to_scene(new_index),
+        }
+    }
+
+    /// Switch to another scene with the given transition. A transition that is
+    /// still running is cut short.
+    pub fn transition_to_scene(&mut self, new_index: usize, mut transition: Box<dyn Transition>) -> Result<()> {
         if new_index >= self.scenes.len() {
             return Err(anyhow!("Scene index out of range."));
         }
@@ -96,16 +200,497 @@ impl SceneSwitcher {
             return Ok(()); // no-op
         }
         self.current_scene_index = new_index;
+        self.stop_running_transition();
+        self.restart_scene_media(new_index, Some(old_idx));
+
+        // Only the two scenes involved may be visible
+        for (idx, scene) in self.scenes.iter().enumerate() {
+            if idx == old_idx || idx == new_index {
+                continue;
+            }
+            for src in &scene.sources {
+                if let Some(pad) = self.compositor.static_pad(&format!("sink_{}", src.pad_index)) {
+                    pad.set_property("alpha", 0.0);
+                }
+            }
+        }
+
+        // Move the new scene's geometry in place, but alpha=0
+        for src in &self.scenes[new_index].sources {
+            let pad_name = format!("sink_{}", src.pad_index);
+            let pad = self
+                .compositor
+                .static_pad(&pad_name)
+                .ok_or_else(|| anyhow!("Cannot find pad for transition: {}", pad_name))?;
+            src.apply_to_pad(&pad, self.registry.transform_stage(src.pad_index).as_ref());
+            pad.set_property("alpha", 0.0);
+        }
+        self.apply_scene_effects(new_index)?;
+
+        let from = self.scenes[old_idx].sources.clone();
+        let to = self.scenes[new_index].sources.clone();
+        let wipes = from
+            .iter()
+            .chain(to.iter())
+            .filter_map(|s| Some((s.pad_index, self.registry.wipe_stage(s.pad_index)?)))
+            .collect();
+        let ctx = TransitionContext::new(
+            self.pipeline.clone(),
+            self.compositor.clone(),
+            self.canvas_size(),
+            from,
+            to,
+            wipes,
+        );
+
+        if let Err(e) = transition.start(&ctx) {
+            transition.cleanup(&ctx);
+            ctx.finish();
+            return Err(e);
+        }
+
+        let name = transition.name().to_string();
+        let duration = transition.duration();
+        let easing = transition.easing();
+
+        // Record one keyframe per output frame, starting a little after the
+        // pipeline's running time now; the context converts them to the time each
+        // element syncs on, so the compositor applies exact values to every frame.
+        let frame = self.frame_duration();
+        let now = match self.pipeline.current_running_time() {
+            Some(now) => now,
+            None => {
+                // Not playing: nothing to animate, jump to the end
+                transition.update(&ctx, 1.0);
+                transition.cleanup(&ctx);
+                ctx.finish();
+                return Ok(());
+            }
+        };
+        let frames = (duration.as_nanos() as u64).div_ceil(frame.nseconds().max(1));
+        let first = now.nseconds() + frame.nseconds() * TRANSITION_LEAD_FRAMES;
+        for i in 0..=frames {
+            let t = if frames == 0 { 1.0 } else { i as f64 / frames as f64 };
+            ctx.set_frame_time(Some(gst::ClockTime::from_nseconds(first + frame.nseconds() * i)));
+            transition.update(&ctx, easing.apply(t));
+        }
+        ctx.set_frame_time(None);
 
-        // We'll do a fade out old scene, fade in new scene
-        let duration = self.transition_duration;
-        self.fade_transition(old_idx, new_index, duration)?;
+        // Tidy up once the last keyframe has been rendered
+        let end = gst::ClockTime::from_nseconds(first + frame.nseconds() * frames);
+        let done_after = Duration::from_nanos(frame.nseconds() * (frames + TRANSITION_LEAD_FRAMES + 1));
+        *self.running_transition.borrow_mut() = Some((end, transition, ctx));
+        let running = self.running_transition.clone();
+        self.main_ctx.spawn_local(async move {
+            glib::timeout_future(done_after).await;
+            let mut slot = running.borrow_mut();
+            if slot.as_ref().map(|(last, _, _)| *last) != Some(end) {
+                return; // interrupted by a newer transition
+            }
+            if let Some((_, mut transition, ctx)) = slot.take() {
+                ctx.clear_keyframes();
+                transition.cleanup(&ctx);
+                ctx.finish();
+            }
+        });
 
-        println!("Faded from scene {} to scene {}", old_idx, new_index);
+        println!("{} from scene {} to scene {}", name, old_idx, new_index);
         Ok(())
     }
 
+    /// Duration of one output frame, from the compositor's negotiated framerate (30 fps until negotiated).
+    pub fn frame_duration(&self) -> gst::ClockTime {
+        let (num, den) = self
+            .compositor
+            .static_pad("src")
+            .and_then(|pad| pad.current_caps())
+            .and_then(|caps| caps.structure(0)?.get::<gst::Fraction>("framerate").ok())
+            .map(|f| (f.numer(), f.denom()))
+            .filter(|(num, den)| *num > 0 && *den > 0)
+            .unwrap_or((30, 1));
+        gst::ClockTime::from_nseconds(1_000_000_000 * den as u64 / num as u64)
+    }
+
+    /// All scenes, in switching order.
+    pub fn scenes(&self) -> &[Scene] {
+        &self.scenes
+    }
+
+    /// The sources available to scenes.
+    pub fn registry(&self) -> &SourceRegistry {
+        &self.registry
+    }
+
+    /// Mutable access, e.g. to add or remove sources.
+    pub fn registry_mut(&mut self) -> &mut SourceRegistry {
+        &mut self.registry
+    }
+
+    /// Register a source. Scene sources start showing their scene right away.
+    pub fn add_source(&mut self, def: SourceDef) -> Result<()> {
+        if let Some(scene) = def.kind.nested_scene() {
+            if !self.scenes.iter().any(|s| s.name == scene) {
+                return Err(anyhow!("No scene named '{}' for a scene source", scene));
+            }
+        }
+        let id = def.id.clone();
+        self.registry.add_source(def)?;
+        if self.registry.scene_compositor(&id).is_some() {
+            self.sync_scene_source(&id)?;
+        }
+        Ok(())
+    }
+
+    /// The program compositor pad with the given index.
+    pub fn compositor_pad(&self, pad_index: u32) -> Option<gst::Pad> {
+        self.compositor.static_pad(&format!("sink_{}", pad_index))
+    }
+
+    /// Compositor pad of the first camera in a scene.
+    pub fn camera_pad_index(&self, scene_index: usize) -> Option<u32> {
+        self.scenes
+            .get(scene_index)?
+            .sources
+            .iter()
+            .find(|s| self.registry.get(&s.source_id).map(|def| def.kind.is_camera()).unwrap_or(false))
+            .map(|s| s.pad_index)
+    }
+
+    /// Show a registered source in a scene; it gets its own compositor pad, so the
+    /// same source can sit at a different spot in every scene. Returns the pad index.
+    pub fn add_source_to_scene(
+        &mut self,
+        scene_index: usize,
+        source_id: &str,
+        geometry: Geometry,
+        filters: &[FilterDesc],
+    ) -> Result<u32> {
+        if scene_index >= self.scenes.len() {
+            return Err(anyhow!("Invalid scene index."));
+        }
+        let kind = self
+            .registry
+            .get(source_id)
+            .map(|def| def.kind.clone())
+            .ok_or_else(|| anyhow!("No source with id '{}'", source_id))?;
+        if kind.nested_scene().is_some() {
+            // Refuse to show a scene inside itself
+            let mut check = self.to_collection("check");
+            check.scenes[scene_index].sources.push(SourceDesc {
+                source: source_id.to_string(),
+                geometry,
+                alpha: 1.0,
+                zorder: 0,
+                filters: Vec::new(),
+                group: None,
+                transform: SourceTransform::default(),
+            });
+            check.check_scene_sources()?;
+        }
+        let effects = effects_from_filters(filters);
+        let (pad_index, chain) = self.registry.request_pad(
+            source_id,
+            effects.is_some() || kind.is_camera(),
+            geometry.width,
+            geometry.height,
+        )?;
+        if let Some(chain) = chain {
+            self.effect_chains.insert(pad_index, chain);
+        }
+        let zorder = self.scenes[scene_index].sources.iter().map(|s| s.zorder + 1).max().unwrap_or(0);
+        self.scenes[scene_index].sources.push(SceneSource {
+            source_id: source_id.to_string(),
+            pad_index,
+            x: geometry.x,
+            y: geometry.y,
+            width: geometry.width,
+            height: geometry.height,
+            alpha: 1.0,
+            zorder,
+            effects,
+            group: None,
+            local: None,
+            transform: SourceTransform::default(),
+        });
+        self.refresh_scene_sources(scene_index);
+        if scene_index == self.current_scene_index {
+            self.apply_scene_layout(scene_index)?;
+        }
+        Ok(pad_index)
+    }
+
+    /// Stop a registered source and take it out of every scene.
+    pub fn remove_source(&mut self, source_id: &str) -> Result<()> {
+        for scene in self.scenes.iter_mut() {
+            scene.sources.retain(|s| s.source_id != source_id);
+        }
+        let registry = &self.registry;
+        self.effect_chains
+            .retain(|pad, _| registry.source_for_pad(*pad).map(|def| def.id != source_id).unwrap_or(true));
+        self.nested.remove(source_id);
+        self.registry.remove_source(source_id)?;
+        for idx in 0..self.scenes.len() {
+            self.refresh_scene_sources(idx);
+        }
+        Ok(())
+    }
+
+    /// Take a source out of a scene and release its compositor pad. The source
+    /// itself stays registered.
+    pub fn remove_source_from_scene(&mut self, scene_index: usize, source_pad_index: u32) -> Result<()> {
+        let scene = self
+            .scenes
+            .get_mut(scene_index)
+            .ok_or_else(|| anyhow!("Invalid scene index."))?;
+        let pos = scene
+            .sources
+            .iter()
+            .position(|s| s.pad_index == source_pad_index)
+            .ok_or_else(|| anyhow!("No source found with pad_index={} in scene {}", source_pad_index, scene.name))?;
+        scene.sources.remove(pos);
+        self.effect_chains.remove(&source_pad_index);
+        self.registry.release_pad(source_pad_index)?;
+        self.refresh_scene_sources(scene_index);
+        Ok(())
+    }
+
+    /// Put sources of a scene into a new group. The group origin is the top-left
+    /// corner of the sources' bounding box, at scale 1.
+    pub fn create_group(&mut self, scene_index: usize, name: &str, source_pad_indices: &[u32]) -> Result<()> {
+        let scene = self
+            .scenes
+            .get_mut(scene_index)
+            .ok_or_else(|| anyhow!("Invalid scene index."))?;
+        if scene.groups.iter().any(|g| g.name == name) {
+            return Err(anyhow!("Scene {} already has a group '{}'", scene.name, name));
+        }
+        let members: Vec<&mut SceneSource> = scene
+            .sources
+            .iter_mut()
+            .filter(|s| source_pad_indices.contains(&s.pad_index))
+            .collect();
+        if members.is_empty() {
+            return Err(anyhow!("No sources to group in scene {}", scene.name));
+        }
+        let group = GroupDesc {
+            name: name.to_string(),
+            x: members.iter().map(|s| s.x).min().unwrap_or(0),
+            y: members.iter().map(|s| s.y).min().unwrap_or(0),
+            scale: 1.0,
+        };
+        for member in members {
+            member.group = Some(name.to_string());
+            member.local = Some(group.from_canvas(&member.geometry()));
+        }
+        scene.groups.push(group);
+        Ok(())
+    }
+
+    /// Dissolve a group. Its sources stay where they are.
+    pub fn remove_group(&mut self, scene_index: usize, name: &str) -> Result<()> {
+        let scene = self
+            .scenes
+            .get_mut(scene_index)
+            .ok_or_else(|| anyhow!("Invalid scene index."))?;
+        let pos = scene
+            .groups
+            .iter()
+            .position(|g| g.name == name)
+            .ok_or_else(|| anyhow!("Scene {} has no group '{}'", scene.name, name))?;
+        scene.groups.remove(pos);
+        for src in scene.sources.iter_mut().filter(|s| s.group.as_deref() == Some(name)) {
+            src.group = None;
+            src.local = None;
+        }
+        Ok(())
+    }
+
+    /// Move a group's origin to `x`,`y` and set its scale (which must be
+    /// positive); every source in it follows, eased over `duration` when the
+    /// scene is on screen.
+    pub fn transform_group(
+        &mut self,
+        scene_index: usize,
+        name: &str,
+        x: i32,
+        y: i32,
+        scale: f64,
+        duration: Duration,
+    ) -> Result<()> {
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(anyhow!("Group scale must be positive, not {}", scale));
+        }
+        let scene = self
+            .scenes
+            .get_mut(scene_index)
+            .ok_or_else(|| anyhow!("Invalid scene index."))?;
+        let group = scene
+            .groups
+            .iter_mut()
+            .find(|g| g.name == name)
+            .ok_or_else(|| anyhow!("Scene {} has no group '{}'", scene.name, name))?;
+        let old = group.clone();
+        group.x = x;
+        group.y = y;
+        group.scale = scale;
+        let new = group.clone();
+
+        let moves: Vec<(u32, Geometry)> = scene
+            .sources
+            .iter()
+            .filter(|s| s.group.as_deref() == Some(name))
+            .map(|s| {
+                let local = s.local.unwrap_or_else(|| old.from_canvas(&s.geometry()));
+                (s.pad_index, new.to_canvas(&local))
+            })
+            .collect();
+        for (pad_index, g) in moves {
+            self.animate_source_geometry(scene_index, pad_index, g.x, g.y, g.width, g.height, duration)?;
+        }
+        Ok(())
+    }
+
+    /// Describe the current scenes (including live geometry changes) as a collection.
+    pub fn to_collection(&self, name: &str) -> SceneCollection {
+        let (width, height) = self.canvas_size();
+        SceneCollection {
+            name: name.to_string(),
+            canvas: Canvas { width, height },
+            sources: self.registry.sources().cloned().collect(),
+            scenes: self
+                .scenes
+                .iter()
+                .map(|scene| SceneDesc {
+                    name: scene.name.clone(),
+                    sources: scene
+                        .sources
+                        .iter()
+                        .map(|src| {
+                            // Grouped items are stored relative to their group
+                            let group = src
+                                .group
+                                .as_ref()
+                                .and_then(|name| scene.groups.iter().find(|g| &g.name == name));
+                            let geometry = match group {
+                                Some(g) => src.local.unwrap_or_else(|| g.from_canvas(&src.geometry())),
+                                None => src.geometry(),
+                            };
+                            SourceDesc {
+                                source: src.source_id.clone(),
+                                geometry,
+                                alpha: src.alpha,
+                                zorder: src.zorder,
+                                filters: src.effects.as_ref().map(filters_from_effects).unwrap_or_default(),
+                                group: group.map(|g| g.name.clone()),
+                                transform: src.transform,
+                            }
+                        })
+                        .collect(),
+                    groups: scene.groups.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Replace all sources and scenes with the ones from a collection. The old
+    /// sources are stopped and removed from the pipeline, their compositor pads
+    /// released. Shows the collection's first scene.
+    pub fn load_collection(&mut self, collection: &SceneCollection) -> Result<()> {
+        if collection.scenes.is_empty() {
+            return Err(anyhow!("Scene collection '{}' has no scenes.", collection.name));
+        }
+        self.remove_all_sources();
+        let loaded = collection.instantiate(&mut self.registry)?;
+        self.scenes = loaded.scenes;
+        for (pad_index, chain) in loaded.effect_chains {
+            self.effect_chains.insert(pad_index, chain);
+        }
+        for def in &collection.sources {
+            if def.kind.nested_scene().is_some() {
+                self.sync_scene_source(&def.id)?;
+            }
+        }
+        self.set_initial_scene(0)?;
+        self.collection_name = collection.name.clone();
+        println!("Loaded scene collection '{}' ({} scenes)", collection.name, self.scenes.len());
+        Ok(())
+    }
+
+    /// Write the current scenes to the working collection file, so edits survive a restart.
+    pub fn save_collection(&self) -> Result<()> {
+        self.current_collection().save_to_file(&SceneCollection::default_path())
+    }
+
+    /// The live scenes, under the name of the collection they came from.
+    pub fn current_collection(&self) -> SceneCollection {
+        self.to_collection(&self.collection_name)
+    }
+
+    /// Index of the scene that is currently on screen.
+    pub fn current_scene_index(&self) -> usize {
+        self.current_scene_index
+    }
+
+    /// Output size of the compositor, read from its negotiated caps (1280x720 until negotiated).
+    pub fn canvas_size(&self) -> (i32, i32) {
+        self.compositor
+            .static_pad("src")
+            .and_then(|pad| pad.current_caps())
+            .and_then(|caps| {
+                let s = caps.structure(0)?;
+                Some((s.get::<i32>("width").ok()?, s.get::<i32>("height").ok()?))
+            })
+            .unwrap_or((1280, 720))
+    }
+
+    /// How compositor coordinates land on the canvas through the first screen
+    /// source of the current scene, as (scale, offset_x, offset_y) with
+    /// canvas = compositor * scale + offset. Taken from the live pad, so a zoom
+    /// in progress counts, but later moves or zooms are not followed. Rotation
+    /// and flips are ignored, and a stretched source uses its horizontal scale.
+    /// None without a screen source, or before it is negotiated.
+    pub fn screen_mapping(&self) -> Option<(f64, i32, i32)> {
+        let scene = self.scenes.get(self.current_scene_index)?;
+        let src = scene
+            .sources
+            .iter()
+            .find(|s| matches!(self.registry.get(&s.source_id).map(|def| &def.kind), Some(SourceKind::Screen)))?;
+        let pad = self.compositor_pad(src.pad_index)?;
+        // Frame size after cropping, as the compositor gets it
+        let caps = pad.current_caps()?;
+        let s = caps.structure(0)?;
+        let (cw, ch) = (s.get::<i32>("width").ok()? as f64, s.get::<i32>("height").ok()? as f64);
+        let (left, right, top, _) = match self.registry.transform_stage(src.pad_index) {
+            Some(stage) => stage.current_crop(),
+            None => {
+                let crop = &src.transform.crop;
+                (crop.left, crop.right, crop.top, crop.bottom)
+            }
+        };
+        // Frame pixels per compositor unit, for scaled outputs
+        let (area_x, area_y, area_w, _) = capture_area(&src.source_id).unwrap_or((0, 0, 0, 0));
+        let frame_w = cw + (left + right) as f64;
+        let k = if area_w > 0 { frame_w / area_w as f64 } else { 1.0 };
+
+        let (pad_x, pad_y) = (pad.property::<i32>("xpos") as f64, pad.property::<i32>("ypos") as f64);
+        let (pad_w, pad_h) = (pad.property::<i32>("width"), pad.property::<i32>("height"));
+        // 0 means the input size
+        let (pad_w, pad_h) = if pad_w <= 0 || pad_h <= 0 { (cw, ch) } else { (pad_w as f64, pad_h as f64) };
+        let (sx, sy) = (pad_w / cw, pad_h / ch);
+        let (pad_scale, x0, y0) = if src.transform.scale_mode == ScaleMode::Fit {
+            let fit = sx.min(sy);
+            (fit, pad_x + (pad_w - cw * fit) / 2.0, pad_y + (pad_h - ch * fit) / 2.0)
+        } else {
+            (sx, pad_x, pad_y)
+        };
+        let scale = k * pad_scale;
+        let offset_x = x0 - (area_x as f64 * k + left as f64) * pad_scale;
+        let offset_y = y0 - (area_y as f64 * k + top as f64) * pad_scale;
+        Some((scale, offset_x.round() as i32, offset_y.round() as i32))
+    }
+
     /// For advanced users: move or resize a source in real-time, e.g. from a GUI.
+    /// The new geometry is remembered in the scene.
     pub fn update_source_geometry(
         &mut self,
         scene_index: usize,
@@ -114,15 +699,56 @@ impl SceneSwitcher {
         y: i32,
         width: i32,
         height: i32,
+    ) -> Result<()> {
+        self.animate_source_geometry(scene_index, source_pad_index, x, y, width, height, Duration::ZERO)
+    }
+
+    /// Like `update_source_geometry`, but eases the pad from its current geometry to
+    /// the new one over `duration`. If the scene is not on screen, the geometry is
+    /// only stored and applied the next time the scene becomes active.
+    pub fn animate_source_geometry(
+        &mut self,
+        scene_index: usize,
+        source_pad_index: u32,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        duration: Duration,
     ) -> Result<()> {
         if scene_index >= self.scenes.len() {
             return Err(anyhow!("Invalid scene index."));
         }
-        let scene = &self.scenes[scene_index];
+        let scene = &mut self.scenes[scene_index];
+        let scene_name = scene.name.clone();
         // find the matching source
-        let maybe_source = scene.sources.iter().find(|s| s.pad_index == source_pad_index);
-        if maybe_source.is_none() {
-            return Err(anyhow!("No source found with pad_index={} in scene {}", source_pad_index, scene.name));
+        let source = scene
+            .sources
+            .iter_mut()
+            .find(|s| s.pad_index == source_pad_index)
+            .ok_or_else(|| anyhow!("No source found with pad_index={} in scene {}", source_pad_index, scene_name))?;
+        source.x = x;
+        source.y = y;
+        source.width = width;
+        source.height = height;
+        // A grouped source moved on its own gets a new place in its group;
+        // one moved by its group keeps the local geometry it was placed from
+        let group = source
+            .group
+            .as_ref()
+            .and_then(|name| scene.groups.iter().find(|g| &g.name == name));
+        if let Some(group) = group {
+            let canvas = source.geometry();
+            if source.local.map(|local| group.to_canvas(&local)) != Some(canvas) {
+                source.local = Some(group.from_canvas(&canvas));
+            }
+        }
+        let transform = source.transform;
+        // Scene sources showing this scene jump straight to the new layout
+        self.refresh_scene_sources(scene_index);
+
+        if scene_index != self.current_scene_index {
+            return Ok(());
         }
 
         // We'll set property on the compositor's sink pad
@@ -132,85 +758,383 @@ impl SceneSwitcher {
             .static_pad(&sink_pad_name)
             .ok_or_else(|| anyhow!("Compositor pad not found: {}", sink_pad_name))?;
 
-        pad.set_property("xpos", x);
-        pad.set_property("ypos", y);
-        pad.set_property("width", width);
-        pad.set_property("height", height);
+        // Bump the generation so an animation still running on this pad stops
+        let generation = {
+            let mut gens = self.animation_generations.lock().unwrap();
+            let entry = gens.entry(source_pad_index).or_insert(0);
+            *entry += 1;
+            *entry
+        };
+
+        // The fill crop follows the new box right away
+        if let Some(stage) = self.registry.transform_stage(source_pad_index) {
+            stage.apply(&transform, width, height);
+        }
+        if duration.is_zero() {
+            apply_pad_layout(&pad, x, y, width, height, &transform);
+            return Ok(());
+        }
+
+        let from = [
+            pad.property::<i32>("xpos"),
+            pad.property::<i32>("ypos"),
+            pad.property::<i32>("width"),
+            pad.property::<i32>("height"),
+        ];
+        let to = [x, y, width, height];
+        let steps = ((duration.as_secs_f64() * 30.0).ceil() as u32).max(1);
+        let step_time = duration / steps;
+        let pad_weak = pad.downgrade();
+        let generations = self.animation_generations.clone();
+
+        self.main_ctx.spawn_local(async move {
+            for step in 1..=steps {
+                if generations.lock().unwrap().get(&source_pad_index) != Some(&generation) {
+                    return; // superseded by a newer animation
+                }
+                let pad = match pad_weak.upgrade() {
+                    Some(p) => p,
+                    None => return,
+                };
+                let t = Easing::EaseInOut.apply(step as f64 / steps as f64);
+                let lerp = |i: usize| from[i] + ((to[i] - from[i]) as f64 * t).round() as i32;
+                apply_pad_layout(&pad, lerp(0), lerp(1), lerp(2), lerp(3), &transform);
+                glib::timeout_future(step_time).await;
+            }
+        });
         Ok(())
     }
 
-    // Internal: apply layout instantly from scene
-    fn apply_scene_layout(&self, scene_index: usize) -> Result<()> {
-        let scene = &self.scenes[scene_index];
-        for src in &scene.sources {
-            let pad_name = format!("sink_{}", src.pad_index);
+    /// Set the alpha of a source (0.0 hides it) and remember it in the scene.
+    pub fn set_source_alpha(&mut self, scene_index: usize, source_pad_index: u32, alpha: f64) -> Result<()> {
+        if scene_index >= self.scenes.len() {
+            return Err(anyhow!("Invalid scene index."));
+        }
+        let scene = &mut self.scenes[scene_index];
+        let scene_name = scene.name.clone();
+        let source = scene
+            .sources
+            .iter_mut()
+            .find(|s| s.pad_index == source_pad_index)
+            .ok_or_else(|| anyhow!("No source found with pad_index={} in scene {}", source_pad_index, scene_name))?;
+        source.alpha = alpha;
+        self.refresh_scene_sources(scene_index);
+
+        if scene_index == self.current_scene_index {
+            let pad_name = format!("sink_{}", source_pad_index);
             let pad = self
                 .compositor
                 .static_pad(&pad_name)
-                .ok_or_else(|| anyhow!("Cannot find compositor pad: {}", pad_name))?;
-
-            pad.set_property("xpos", src.x);
-            pad.set_property("ypos", src.y);
-            pad.set_property("width", src.width);
-            pad.set_property("height", src.height);
-            pad.set_property("alpha", src.alpha);
+                .ok_or_else(|| anyhow!("Compositor pad not found: {}", pad_name))?;
+            pad.set_property("alpha", alpha);
         }
         Ok(())
     }
 
-    // Internal: fade out old scene, fade in new scene
-    fn fade_transition(&mut self, old_idx: usize, new_idx: usize, dur: Duration) -> Result<()> {
-        // Move the new scene's geometry in place, but alpha=0
-        let new_scene = &self.scenes[new_idx];
-        for src in &new_scene.sources {
-            let pad_name = format!("sink_{}", src.pad_index);
+    /// Stack a source at `zorder` (higher is on top) and remember it in the scene.
+    pub fn set_source_zorder(&mut self, scene_index: usize, source_pad_index: u32, zorder: u32) -> Result<()> {
+        if scene_index >= self.scenes.len() {
+            return Err(anyhow!("Invalid scene index."));
+        }
+        let scene = &mut self.scenes[scene_index];
+        let scene_name = scene.name.clone();
+        let source = scene
+            .sources
+            .iter_mut()
+            .find(|s| s.pad_index == source_pad_index)
+            .ok_or_else(|| anyhow!("No source found with pad_index={} in scene {}", source_pad_index, scene_name))?;
+        source.zorder = zorder;
+        self.refresh_scene_sources(scene_index);
+
+        if scene_index == self.current_scene_index {
+            let pad_name = format!("sink_{}", source_pad_index);
             let pad = self
                 .compositor
                 .static_pad(&pad_name)
-                .ok_or_else(|| anyhow!("Cannot find pad for fade in: {}", pad_name))?;
-            pad.set_property("xpos", src.x);
-            pad.set_property("ypos", src.y);
-            pad.set_property("width", src.width);
-            pad.set_property("height", src.height);
-            pad.set_property("alpha", 0.0);
+                .ok_or_else(|| anyhow!("Compositor pad not found: {}", pad_name))?;
+            pad.set_property("zorder", zorder);
+        }
+        Ok(())
+    }
+
+    /// Change the crop, flips, rotation and scale mode of a source. If the scene
+    /// is on screen, crop and free angle are eased over `duration`; the other
+    /// settings change at once. Every crop step changes the frame size, so an
+    /// animated crop renegotiates caps down to the compositor about 30 times a
+    /// second; animating only the angle does not.
+    pub fn set_source_transform(
+        &mut self,
+        scene_index: usize,
+        source_pad_index: u32,
+        transform: SourceTransform,
+        duration: Duration,
+    ) -> Result<()> {
+        if scene_index >= self.scenes.len() {
+            return Err(anyhow!("Invalid scene index."));
         }
+        let scene = &mut self.scenes[scene_index];
+        let scene_name = scene.name.clone();
+        let source = scene
+            .sources
+            .iter_mut()
+            .find(|s| s.pad_index == source_pad_index)
+            .ok_or_else(|| anyhow!("No source found with pad_index={} in scene {}", source_pad_index, scene_name))?;
+        let from = source.transform;
+        source.transform = transform;
+        let (x, y, width, height) = (source.x, source.y, source.width, source.height);
+        self.refresh_scene_sources(scene_index);
+
+        if scene_index != self.current_scene_index {
+            return Ok(());
+        }
+        let pad_name = format!("sink_{}", source_pad_index);
+        let pad = self
+            .compositor
+            .static_pad(&pad_name)
+            .ok_or_else(|| anyhow!("Compositor pad not found: {}", pad_name))?;
+        apply_pad_layout(&pad, x, y, width, height, &transform);
+        let stage = match self.registry.transform_stage(source_pad_index) {
+            Some(stage) => stage,
+            None => return Ok(()),
+        };
 
-        // We'll step alpha from 1->0 for old scene, 0->1 for new scene
-        // over dur. We'll use a Glib timeout with ~30 fps approach
-        let steps = 30;
-        let step_time = dur / steps;
-        let main_ctx = self.main_ctx.clone();
-        let alpha_map = self.alpha_map.clone();
-        let compositor_weak = self.compositor.downgrade();
-        let old_scene_cloned = self.scenes[old_idx].sources.clone();
-        let new_scene_cloned = self.scenes[new_idx].sources.clone();
-
-        main_ctx.spawn_local(async move {
-            let mut progress = 0;
-            while progress <= steps {
-                let frac = progress as f64 / steps as f64;
-                let old_alpha = 1.0 - frac;
-                let new_alpha = frac;
-
-                if let Some(comp) = compositor_weak.upgrade() {
-                    for src in &old_scene_cloned {
-                        let pad_name = format!("sink_{}", src.pad_index);
-                        if let Some(pad) = comp.static_pad(&pad_name) {
-                            pad.set_property("alpha", old_alpha);
-                        }
-                    }
-                    for src in &new_scene_cloned {
-                        let pad_name = format!("sink_{}", src.pad_index);
-                        if let Some(pad) = comp.static_pad(&pad_name) {
-                            pad.set_property("alpha", new_alpha);
-                        }
-                    }
+        let generation = {
+            let mut gens = self.transform_generations.lock().unwrap();
+            let entry = gens.entry(source_pad_index).or_insert(0);
+            *entry += 1;
+            *entry
+        };
+        if duration.is_zero() {
+            stage.apply(&transform, width, height);
+            return Ok(());
+        }
+
+        let steps = ((duration.as_secs_f64() * 30.0).ceil() as u32).max(1);
+        let step_time = duration / steps;
+        let generations = self.transform_generations.clone();
+        self.main_ctx.spawn_local(async move {
+            for step in 1..=steps {
+                if generations.lock().unwrap().get(&source_pad_index) != Some(&generation) {
+                    return; // superseded by a newer transform
                 }
-                progress += 1;
-                glib::timeout_future_seconds(step_time.as_secs_f64()).await;
+                let t = Easing::EaseInOut.apply(step as f64 / steps as f64);
+                stage.apply(&from.lerp(&transform, t), width, height);
+                glib::timeout_future(step_time).await;
             }
         });
+        Ok(())
+    }
+
+    /// Register the effect chain feeding a compositor pad, so scenes can
+    /// configure effects for that source.
+    pub fn register_effect_chain(&mut self, pad_index: u32, chain: CameraEffectChain) {
+        self.effect_chains.insert(pad_index, chain);
+        if let Err(e) = self.apply_scene_effects(self.current_scene_index) {
+            eprintln!("Failed to apply camera effects: {:?}", e);
+        }
+    }
 
+    /// Effects configured for a source in a scene.
+    pub fn source_effects(&self, scene_index: usize, source_pad_index: u32) -> Option<CameraEffects> {
+        self.scenes
+            .get(scene_index)?
+            .sources
+            .iter()
+            .find(|s| s.pad_index == source_pad_index)?
+            .effects
+            .clone()
+    }
+
+    /// Change the effects of a source in a scene; applied live if the scene is on screen.
+    pub fn set_source_effects(
+        &mut self,
+        scene_index: usize,
+        source_pad_index: u32,
+        effects: CameraEffects,
+    ) -> Result<()> {
+        if scene_index >= self.scenes.len() {
+            return Err(anyhow!("Invalid scene index."));
+        }
+        let scene = &mut self.scenes[scene_index];
+        let scene_name = scene.name.clone();
+        let source = scene
+            .sources
+            .iter_mut()
+            .find(|s| s.pad_index == source_pad_index)
+            .ok_or_else(|| anyhow!("No source found with pad_index={} in scene {}", source_pad_index, scene_name))?;
+        source.effects = Some(effects);
+        self.refresh_scene_sources(scene_index);
+
+        if scene_index == self.current_scene_index {
+            self.apply_scene_effects(scene_index)?;
+        }
+        Ok(())
+    }
+
+    // Internal: push each source's effects into its registered chain
+    fn apply_scene_effects(&self, scene_index: usize) -> Result<()> {
+        let scene = self
+            .scenes
+            .get(scene_index)
+            .ok_or_else(|| anyhow!("Invalid scene index."))?;
+        for src in &scene.sources {
+            if let Some(chain) = self.effect_chains.get(&src.pad_index) {
+                chain.apply(&src.effects.clone().unwrap_or_default());
+            }
+        }
+        Ok(())
+    }
+
+    // Internal: bring every scene source showing the given scene up to date
+    fn refresh_scene_sources(&mut self, scene_index: usize) {
+        let name = match self.scenes.get(scene_index) {
+            Some(scene) => scene.name.clone(),
+            None => return,
+        };
+        let ids: Vec<SourceId> = self
+            .registry
+            .sources()
+            .filter(|def| def.kind.nested_scene() == Some(name.as_str()))
+            .map(|def| def.id.clone())
+            .collect();
+        for id in ids {
+            if let Err(e) = self.sync_scene_source(&id) {
+                eprintln!("Failed to update scene source '{}': {:?}", id, e);
+            }
+        }
+    }
+
+    // Internal: lay out the shown scene's items on a scene source's compositor.
+    // Pads are kept while the item list is unchanged, otherwise requested anew.
+    fn sync_scene_source(&mut self, id: &str) -> Result<()> {
+        let compositor = self
+            .registry
+            .scene_compositor(id)
+            .ok_or_else(|| anyhow!("'{}' is not a scene source", id))?;
+        let scene_name = self
+            .registry
+            .get(id)
+            .and_then(|def| def.kind.nested_scene())
+            .unwrap_or_default()
+            .to_string();
+        let sources = self
+            .scenes
+            .iter()
+            .find(|s| s.name == scene_name)
+            .map(|s| s.sources.clone())
+            .ok_or_else(|| anyhow!("No scene named '{}' for scene source '{}'", scene_name, id))?;
+
+        let wants_chain = |registry: &SourceRegistry, src: &SceneSource| {
+            src.effects.is_some() || registry.get(&src.source_id).map(|d| d.kind.is_camera()).unwrap_or(false)
+        };
+        let mut items = self.nested.remove(id).unwrap_or_default();
+        let reusable = items.len() == sources.len()
+            && items
+                .iter()
+                .zip(&sources)
+                .all(|((item_id, _, chain), src)| {
+                    item_id == &src.source_id && chain.is_some() == wants_chain(&self.registry, src)
+                });
+        if !reusable {
+            for (_, pad_index, _) in items.drain(..) {
+                // Already gone if the source was removed
+                let _ = self.registry.release_pad_on(&compositor, pad_index);
+            }
+            for src in &sources {
+                let with_effects = wants_chain(&self.registry, src);
+                let (pad_index, chain) = self.registry.request_pad_on(
+                    &compositor,
+                    &src.source_id,
+                    with_effects,
+                    src.width,
+                    src.height,
+                )?;
+                items.push((src.source_id.clone(), pad_index, chain));
+            }
+        }
+
+        for ((_, pad_index, chain), src) in items.iter().zip(&sources) {
+            let pad_name = format!("sink_{}", pad_index);
+            let pad = compositor
+                .static_pad(&pad_name)
+                .ok_or_else(|| anyhow!("Scene source pad not found: {}", pad_name))?;
+            src.apply_to_pad(&pad, self.registry.transform_stage_on(&compositor, *pad_index).as_ref());
+            if let Some(chain) = chain {
+                chain.apply(&src.effects.clone().unwrap_or_default());
+            }
+        }
+        self.nested.insert(id.to_string(), items);
+        Ok(())
+    }
+
+    // Internal: remove the keyframes of an unfinished transition. The pads keep
+    // their current values; the caller sets the layout it wants.
+    fn stop_running_transition(&self) {
+        if let Some((_, mut transition, ctx)) = self.running_transition.borrow_mut().take() {
+            ctx.clear_keyframes();
+            transition.cleanup(&ctx);
+        }
+    }
+
+    // Internal: drop all scenes and stop every registered source
+    fn remove_all_sources(&mut self) {
+        self.scenes.clear();
+        self.effect_chains.clear();
+        self.nested.clear();
+        self.registry.clear();
+        self.current_scene_index = 0;
+    }
+
+    // Internal: hide every pad that is not part of the given scene
+    fn hide_other_scenes(&self, scene_index: usize) {
+        let visible: Vec<u32> = self.scenes[scene_index].sources.iter().map(|s| s.pad_index).collect();
+        for (idx, scene) in self.scenes.iter().enumerate() {
+            if idx == scene_index {
+                continue;
+            }
+            for src in scene.sources.iter().filter(|s| !visible.contains(&s.pad_index)) {
+                if let Some(pad) = self.compositor.static_pad(&format!("sink_{}", src.pad_index)) {
+                    pad.set_property("alpha", 0.0);
+                }
+            }
+        }
+    }
+
+    // Internal: rewind media files set to restart when their scene goes live.
+    // Media that was already on screen in the previous scene keeps playing.
+    fn restart_scene_media(&self, scene_index: usize, previous: Option<usize>) {
+        let was_live = |id: &str| {
+            previous
+                .and_then(|p| self.scenes.get(p))
+                .map(|scene| scene.sources.iter().any(|s| s.source_id == id))
+                .unwrap_or(false)
+        };
+        for src in &self.scenes[scene_index].sources {
+            let restart = matches!(
+                self.registry.get(&src.source_id).map(|d| &d.kind),
+                Some(SourceKind::MediaFile { restart_on_activate: true, .. })
+            );
+            if restart && !was_live(&src.source_id) {
+                if let Err(e) = self.registry.restart_source(&src.source_id) {
+                    eprintln!("Failed to restart '{}': {:?}", src.source_id, e);
+                }
+            }
+        }
+    }
+
+    // Internal: apply layout instantly from scene
+    fn apply_scene_layout(&self, scene_index: usize) -> Result<()> {
+        self.hide_other_scenes(scene_index);
+        let scene = &self.scenes[scene_index];
+        for src in &scene.sources {
+            let pad_name = format!("sink_{}", src.pad_index);
+            let pad = self
+                .compositor
+                .static_pad(&pad_name)
+                .ok_or_else(|| anyhow!("Cannot find compositor pad: {}", pad_name))?;
+            src.apply_to_pad(&pad, self.registry.transform_stage(src.pad_index).as_ref());
+        }
+        self.apply_scene_effects(scene_index)?;
         Ok(())
     }
 }
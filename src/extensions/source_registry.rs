@@ -0,0 +1,851 @@
+// src/extensions/source_registry.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::devices::camera::{CameraConfig, CameraMode, CameraSource};
+use crate::devices::screen::{close_capture_session, request_capture_session, session_cursor, CaptureTarget, CursorMode};
+use crate::extensions::audio_visualizer::{visualizer_source, VisualizerOptions, VisualizerStyle};
+use crate::extensions::camera_effects::CameraEffectChain;
+use crate::extensions::cursor_overlay::{CursorOverlay, PROGRAM_CURSOR_OPTIONS};
+use crate::extensions::media_sources::{image_source, media_file_source, restart_media, slideshow_source, Slide};
+use crate::extensions::source_transform::{TransformStage, WipeStage};
+
+/// Stable, user-chosen identifier of a registered source (e.g. "webcam").
+pub type SourceId = String;
+
+/// A capture input or generator, independent of any scene.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceDef {
+    pub id: SourceId,
+    pub name: String,
+    pub kind: SourceKind,
+}
+
+/// What a source captures or generates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceKind {
+    /// A monitor or window picked through the ScreenCast portal
+    Screen,
+    /// A single window picked through the ScreenCast portal
+    Window,
+    /// A V4L2 camera; device "test" uses the test-pattern stand-in
+    Camera {
+        device: String,
+        #[serde(default = "default_camera_format")]
+        format: String,
+        width: i32,
+        height: i32,
+        #[serde(default = "default_fps")]
+        fps: i32,
+    },
+    /// A still image (PNG, JPEG, SVG, ...) at its own size
+    Image { path: String },
+    /// A video file or URI; its audio goes to the program audio mixer
+    MediaFile {
+        uri: String,
+        /// Start over when the end is reached
+        #[serde(default)]
+        looping: bool,
+        /// Play from the beginning whenever a scene showing it goes live
+        #[serde(default)]
+        restart_on_activate: bool,
+        #[serde(default = "default_true")]
+        audio: bool,
+    },
+    /// Images shown one after another, each for its own duration
+    Slideshow {
+        slides: Vec<Slide>,
+        #[serde(default = "default_true")]
+        looping: bool,
+        #[serde(default = "default_slideshow_width")]
+        width: i32,
+        #[serde(default = "default_slideshow_height")]
+        height: i32,
+    },
+    /// A solid color, 0xAARRGGBB
+    Color { argb: u32 },
+    /// Text on a transparent background
+    Text {
+        text: String,
+        #[serde(default = "default_font")]
+        font: String,
+        /// 0xAARRGGBB
+        #[serde(default = "default_text_color")]
+        color: u32,
+        #[serde(default = "default_text_width")]
+        width: i32,
+        #[serde(default = "default_text_height")]
+        height: i32,
+    },
+    /// A videotestsrc pattern such as "smpte" or "ball"
+    TestPattern {
+        #[serde(default = "default_pattern")]
+        pattern: String,
+    },
+    /// Another scene of the collection, rendered at canvas size on its own compositor
+    Scene { scene: String },
+    /// A drawing of what a PipeWire audio input sounds like
+    AudioVisualizer {
+        /// Node name from the audio sources list; the default input if unset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        device: Option<String>,
+        #[serde(default)]
+        style: VisualizerStyle,
+        #[serde(default = "default_visualizer_width")]
+        width: i32,
+        #[serde(default = "default_visualizer_height")]
+        height: i32,
+        #[serde(default = "default_fps")]
+        fps: i32,
+        /// 0xAARRGGBB
+        #[serde(default = "default_text_color")]
+        color: u32,
+        /// 0xAARRGGBB; 0 for transparent
+        #[serde(default = "default_visualizer_background")]
+        background: u32,
+        #[serde(default)]
+        trails: bool,
+    },
+}
+
+fn default_camera_format() -> String {
+    "YUY2".to_string()
+}
+fn default_fps() -> i32 {
+    30
+}
+fn default_font() -> String {
+    "Sans 32".to_string()
+}
+fn default_text_color() -> u32 {
+    0xffffffff
+}
+fn default_text_width() -> i32 {
+    640
+}
+fn default_text_height() -> i32 {
+    120
+}
+fn default_true() -> bool {
+    true
+}
+fn default_slideshow_width() -> i32 {
+    1280
+}
+fn default_slideshow_height() -> i32 {
+    720
+}
+fn default_visualizer_width() -> i32 {
+    640
+}
+fn default_visualizer_height() -> i32 {
+    360
+}
+fn default_visualizer_background() -> u32 {
+    0xff000000
+}
+fn default_pattern() -> String {
+    "smpte".to_string()
+}
+
+impl SourceKind {
+    pub fn from_camera(cam: &CameraConfig) -> Self {
+        let device = match &cam.source {
+            CameraSource::V4l2(path) => path.clone(),
+            CameraSource::TestPattern => "test".to_string(),
+        };
+        SourceKind::Camera {
+            device,
+            format: cam.mode.format.clone(),
+            width: cam.mode.width,
+            height: cam.mode.height,
+            fps: cam.mode.framerate.0 / cam.mode.framerate.1.max(1),
+        }
+    }
+
+    pub fn is_camera(&self) -> bool {
+        matches!(self, SourceKind::Camera { .. })
+    }
+
+    /// Name of the scene shown by a scene source.
+    pub fn nested_scene(&self) -> Option<&str> {
+        match self {
+            SourceKind::Scene { scene } => Some(scene),
+            _ => None,
+        }
+    }
+
+    /// Build the element (or bin) producing raw video for source `id`.
+    pub fn build_element(&self, id: &str, name: &str) -> Result<gst::Element> {
+        match self {
+            SourceKind::Screen => screen_source(id, name, CaptureTarget::Any),
+            SourceKind::Window => screen_source(id, name, CaptureTarget::Window),
+            SourceKind::Camera { device, format, width, height, fps } => {
+                let cam = if device == "test" {
+                    CameraConfig::test_pattern(*width, *height, *fps)
+                } else {
+                    CameraConfig::v4l2(device, CameraMode::new(format, *width, *height, *fps))
+                };
+                cam.build_source(name)
+            }
+            SourceKind::Image { path } => image_source(name, path),
+            SourceKind::MediaFile { uri, looping, .. } => media_file_source(name, uri, *looping),
+            SourceKind::Slideshow { slides, looping, width, height } => {
+                slideshow_source(name, slides.clone(), *looping, *width, *height)
+            }
+            SourceKind::Color { argb } => {
+                let src = gst::ElementFactory::make("videotestsrc", Some(name))?;
+                src.set_property("is-live", true);
+                src.set_property_from_str("pattern", "solid-color");
+                src.set_property("foreground-color", *argb);
+                Ok(src)
+            }
+            SourceKind::Text { text, font, color, width, height } => {
+                let bin = bin_from_description(
+                    name,
+                    &format!(
+                        "videotestsrc is-live=true pattern=solid-color foreground-color=0 ! \
+                         video/x-raw,format=BGRA,width={},height={} ! \
+                         textoverlay name=text valignment=center halignment=center ! videoconvert",
+                        width, height
+                    ),
+                )?;
+                let overlay = bin
+                    .downcast_ref::<gst::Bin>()
+                    .and_then(|b| b.by_name("text"))
+                    .ok_or_else(|| anyhow!("Text source has no textoverlay"))?;
+                overlay.set_property("text", text);
+                overlay.set_property("font-desc", font);
+                overlay.set_property("color", *color);
+                Ok(bin)
+            }
+            SourceKind::TestPattern { pattern } => {
+                let src = gst::ElementFactory::make("videotestsrc", Some(name))?;
+                src.set_property("is-live", true);
+                src.set_property_from_str("pattern", pattern);
+                Ok(src)
+            }
+            SourceKind::AudioVisualizer { device, style, width, height, fps, color, background, trails } => {
+                let options = VisualizerOptions {
+                    device: device.clone(),
+                    style: *style,
+                    width: *width,
+                    height: *height,
+                    fps: *fps,
+                    color: *color,
+                    background: *background,
+                    trails: *trails,
+                };
+                visualizer_source(name, &options)
+            }
+            SourceKind::Scene { .. } => {
+                // Empty until the scene switcher requests pads for the scene's items
+                let comp = gst::ElementFactory::make("compositor", Some(name))?;
+                comp.set_property_from_str("background", "transparent");
+                Ok(comp)
+            }
+        }
+    }
+}
+
+// Each screen source owns a portal session, keyed by its ID. The cursor comes as
+// metadata and is drawn here, so the program's cursor options apply to it:
+//   pipewiresrc ! videoconvert ! cairooverlay (cursor)
+fn screen_source(id: &str, name: &str, target: CaptureTarget) -> Result<gst::Element> {
+    let bin = gst::Bin::new(Some(name));
+    let src = gst::ElementFactory::make("pipewiresrc", None)?;
+    let convert = gst::ElementFactory::make("videoconvert", None)?;
+    let overlay = gst::ElementFactory::make("cairooverlay", None)
+        .map_err(|_| anyhow!("Failed to create cairooverlay. Is plugin installed?"))?;
+    bin.add_many(&[&src, &convert, &overlay])?;
+    gst::Element::link_many(&[&src, &convert, &overlay])?;
+    let out = overlay.static_pad("src").ok_or_else(|| anyhow!("cairooverlay has no src pad"))?;
+    bin.add_pad(&gst::GhostPad::with_target(Some("src"), &out)?)?;
+    CursorOverlay::attach_shared(&overlay, session_cursor(id), PROGRAM_CURSOR_OPTIONS.clone());
+
+    // Held back until the user has picked what to share
+    src.set_locked_state(true);
+    let weak_src = src.downgrade();
+    request_capture_session(id, CursorMode::Metadata, target, move |session| {
+        let src = match weak_src.upgrade() {
+            Some(src) => src,
+            None => return,
+        };
+        if let Err(e) = session.and_then(|session| session.configure_source(&src)) {
+            eprintln!("Screen cast portal unavailable ({:?}), using default PipeWire node.", e);
+        }
+        src.set_locked_state(false);
+        let _ = src.sync_state_with_parent();
+    });
+    Ok(bin.upcast())
+}
+
+fn bin_from_description(name: &str, description: &str) -> Result<gst::Element> {
+    let bin = gst::parse_bin_from_description(description, true)?;
+    bin.set_property("name", name);
+    Ok(bin.upcast())
+}
+
+/// One use of a source on the compositor:
+/// tee branch -> queue -> [effects] -> transform -> wipe -> pad.
+/// Frames only pass the wipe's smptealpha during wipe transitions.
+pub struct SourceBranch {
+    pub pad_index: u32,
+    compositor: gst::Element,
+    tee_pad: gst::Pad,
+    queue: gst::Element,
+    effects: Option<gst::Element>,
+    transform: TransformStage,
+    wipe: WipeStage,
+}
+
+// Internal: elements and request pads of a branch being built. Unless
+// disarmed once the branch is complete, dropping it unlinks and gives back
+// the pads and takes the elements out of the pipeline again.
+struct BranchGuard {
+    pipeline: gst::Pipeline,
+    elements: Vec<gst::Element>,
+    // Request pads with the element they came from
+    pads: Vec<(gst::Element, gst::Pad)>,
+    armed: bool,
+}
+
+impl BranchGuard {
+    fn new(pipeline: &gst::Pipeline) -> Self {
+        Self {
+            pipeline: pipeline.clone(),
+            elements: Vec::new(),
+            pads: Vec::new(),
+            armed: true,
+        }
+    }
+
+    fn add(&mut self, element: &gst::Element) -> Result<()> {
+        self.pipeline.add(element)?;
+        self.elements.push(element.clone());
+        Ok(())
+    }
+
+    fn request_pad(&mut self, element: &gst::Element, template: &str) -> Result<gst::Pad> {
+        let pad = element
+            .request_pad_simple(template)
+            .ok_or_else(|| anyhow!("{} refused a new {} pad", element.name(), template))?;
+        self.pads.push((element.clone(), pad.clone()));
+        Ok(pad)
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for BranchGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        for (element, pad) in self.pads.drain(..) {
+            if let Some(peer) = pad.peer() {
+                // Tee pads are sources, compositor pads sinks
+                let _ = if pad.direction() == gst::PadDirection::Src {
+                    pad.unlink(&peer)
+                } else {
+                    peer.unlink(&pad)
+                };
+            }
+            element.release_request_pad(&pad);
+        }
+        for element in self.elements.drain(..).rev() {
+            let _ = element.set_state(gst::State::Null);
+            let _ = self.pipeline.remove(&element);
+        }
+    }
+}
+
+struct RegisteredSource {
+    def: SourceDef,
+    element: gst::Element,
+    // Fixes the output size of scene sources to the canvas
+    caps: Option<gst::Element>,
+    tee: gst::Element,
+    branches: Vec<SourceBranch>,
+    // Program audio mixer pad of a media file source, once its audio shows up
+    audio_pad: Arc<Mutex<Option<gst::Pad>>>,
+}
+
+/// Owns the capture inputs. Each source runs once and is split with a tee, so
+/// any number of scenes can show it, each on its own compositor pad. Pads come
+/// from the program compositor unless another one is asked for (studio preview).
+pub struct SourceRegistry {
+    pipeline: gst::Pipeline,
+    compositor: gst::Element,
+    sources: Vec<RegisteredSource>,
+    next_element_id: u32,
+    canvas: (i32, i32),
+    // Sources that sent EOS since the last `take_ended`
+    ended: Arc<Mutex<Vec<SourceId>>>,
+    // Where media file audio is mixed into the program
+    audio_mixer: Option<gst::Element>,
+}
+
+impl SourceRegistry {
+    pub fn new(pipeline: gst::Pipeline, compositor: gst::Element) -> Self {
+        Self {
+            pipeline,
+            compositor,
+            sources: Vec::new(),
+            next_element_id: 0,
+            canvas: (1280, 720),
+            ended: Arc::new(Mutex::new(Vec::new())),
+            audio_mixer: None,
+        }
+    }
+
+    /// Mixer that media file sources added afterwards feed their audio into.
+    pub fn set_audio_mixer(&mut self, mixer: gst::Element) {
+        self.audio_mixer = Some(mixer);
+    }
+
+    /// Size scene sources render at. Only affects sources added afterwards.
+    pub fn set_canvas(&mut self, width: i32, height: i32) {
+        self.canvas = (width, height);
+    }
+
+    /// Start a source. IDs must be unique.
+    pub fn add_source(&mut self, def: SourceDef) -> Result<()> {
+        if self.get(&def.id).is_some() {
+            return Err(anyhow!("A source with id '{}' already exists.", def.id));
+        }
+        let prefix = self.element_name("src");
+        let element = def.kind.build_element(&def.id, &prefix)?;
+        let tee = gst::ElementFactory::make("tee", Some(&format!("{}_tee", prefix)))?;
+        // Sources may run with no scene using them
+        tee.set_property("allow-not-linked", true);
+        self.pipeline.add_many(&[&element, &tee])?;
+        let caps = match def.kind {
+            SourceKind::Scene { .. } => {
+                let caps = gst::ElementFactory::make("capsfilter", Some(&format!("{}_caps", prefix)))?;
+                caps.set_property(
+                    "caps",
+                    &gst::Caps::builder("video/x-raw")
+                        .field("width", self.canvas.0)
+                        .field("height", self.canvas.1)
+                        .build(),
+                );
+                self.pipeline.add(&caps)?;
+                gst::Element::link_many(&[&element, &caps, &tee])?;
+                caps.sync_state_with_parent()?;
+                Some(caps)
+            }
+            _ => {
+                element.link(&tee)?;
+                None
+            }
+        };
+        // Probes and handlers go in before the source starts, so nothing slips past them
+
+        // A file starts at time zero, which is long gone on a pipeline that has been
+        // playing; shift it to now like `restart_media` does, or every frame is late
+        let offset = match def.kind {
+            SourceKind::MediaFile { .. } if self.pipeline.current_state() == gst::State::Playing => self
+                .pipeline
+                .current_running_time()
+                .map(|t| t.nseconds() as i64)
+                .unwrap_or(0),
+            _ => 0,
+        };
+        for pad in element.src_pads() {
+            pad.set_offset(offset);
+        }
+
+        // Media audio appears as a bin pad once the file is decoded
+        let audio_pad = Arc::new(Mutex::new(None));
+        if let (SourceKind::MediaFile { audio: true, .. }, Some(mixer)) = (&def.kind, &self.audio_mixer) {
+            let mixer = mixer.clone();
+            let audio_pad = audio_pad.clone();
+            element.connect_pad_added(move |_, pad| {
+                if pad.name() != "audio" {
+                    return;
+                }
+                pad.set_offset(offset);
+                let linked = mixer
+                    .request_pad_simple("sink_%u")
+                    .ok_or_else(|| anyhow!("Audio mixer refused a new sink pad"))
+                    .and_then(|sink| {
+                        pad.link(&sink).map_err(|e| anyhow!("Failed to link media audio: {:?}", e))?;
+                        Ok(sink)
+                    });
+                match linked {
+                    Ok(sink) => *audio_pad.lock().unwrap() = Some(sink),
+                    Err(e) => eprintln!("{:?}", e),
+                }
+            });
+        }
+
+        // Note when a file-based source runs out
+        if let Some(tee_sink) = tee.static_pad("sink") {
+            let ended = self.ended.clone();
+            let id = def.id.clone();
+            tee_sink.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                if let Some(gst::PadProbeData::Event(ev)) = &info.data {
+                    if ev.type_() == gst::EventType::Eos {
+                        ended.lock().unwrap().push(id.clone());
+                    }
+                }
+                gst::PadProbeReturn::Ok
+            });
+        }
+
+        tee.sync_state_with_parent()?;
+        element.sync_state_with_parent()?;
+
+        self.sources.push(RegisteredSource {
+            def,
+            element,
+            caps,
+            tee,
+            branches: Vec::new(),
+            audio_pad,
+        });
+        Ok(())
+    }
+
+    /// Stop a source and release every compositor pad it feeds. A screen
+    /// source's portal session ends with it.
+    pub fn remove_source(&mut self, id: &str) -> Result<()> {
+        self.stop_source(id)?;
+        close_capture_session(id);
+        Ok(())
+    }
+
+    // Internal: take a source out of the pipeline. Portal sessions are kept, so
+    // a reloaded collection does not ask the user again.
+    fn stop_source(&mut self, id: &str) -> Result<()> {
+        let pos = self
+            .sources
+            .iter()
+            .position(|s| s.def.id == id)
+            .ok_or_else(|| anyhow!("No source with id '{}'", id))?;
+        let mut source = self.sources.remove(pos);
+        for branch in source.branches.drain(..) {
+            self.teardown_branch(&source.tee, branch);
+        }
+        if source.def.kind.nested_scene().is_some() {
+            // Branches of other sources feeding this scene's compositor
+            let mut feeding = Vec::new();
+            for other in self.sources.iter_mut() {
+                let (inner, kept): (Vec<_>, Vec<_>) =
+                    other.branches.drain(..).partition(|b| b.compositor == source.element);
+                other.branches = kept;
+                feeding.extend(inner.into_iter().map(|b| (other.tee.clone(), b)));
+            }
+            for (tee, branch) in feeding {
+                self.teardown_branch(&tee, branch);
+            }
+        }
+        for el in std::iter::once(&source.element).chain(source.caps.iter()).chain(std::iter::once(&source.tee)) {
+            let _ = el.set_state(gst::State::Null);
+            let _ = self.pipeline.remove(el);
+        }
+        if let (Some(pad), Some(mixer)) = (source.audio_pad.lock().unwrap().take(), &self.audio_mixer) {
+            mixer.release_request_pad(&pad);
+        }
+        Ok(())
+    }
+
+    /// Play a media file source from the beginning. Other sources are left alone.
+    pub fn restart_source(&self, id: &str) -> Result<()> {
+        let source = self
+            .sources
+            .iter()
+            .find(|s| s.def.id == id)
+            .ok_or_else(|| anyhow!("No source with id '{}'", id))?;
+        if let SourceKind::MediaFile { .. } = source.def.kind {
+            restart_media(&source.element);
+        }
+        Ok(())
+    }
+
+    /// Remove every source. Portal sessions stay open for screen sources that come back.
+    pub fn clear(&mut self) {
+        let ids: Vec<SourceId> = self.sources.iter().map(|s| s.def.id.clone()).collect();
+        for id in ids {
+            let _ = self.stop_source(&id);
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&SourceDef> {
+        self.sources.iter().find(|s| s.def.id == id).map(|s| &s.def)
+    }
+
+    /// All source definitions, in the order they were added.
+    pub fn sources(&self) -> impl Iterator<Item = &SourceDef> {
+        self.sources.iter().map(|s| &s.def)
+    }
+
+    /// Sources that reached their end since the last call.
+    pub fn take_ended(&self) -> Vec<SourceId> {
+        std::mem::take(&mut *self.ended.lock().unwrap())
+    }
+
+    /// The compositor rendering a scene source, to request pads on.
+    pub fn scene_compositor(&self, id: &str) -> Option<gst::Element> {
+        let source = self.sources.iter().find(|s| s.def.id == id)?;
+        source.def.kind.nested_scene().map(|_| source.element.clone())
+    }
+
+    /// Source feeding a program compositor pad.
+    pub fn source_for_pad(&self, pad_index: u32) -> Option<&SourceDef> {
+        self.sources
+            .iter()
+            .find(|s| s.branches.iter().any(|b| b.pad_index == pad_index && b.compositor == self.compositor))
+            .map(|s| &s.def)
+    }
+
+    /// Get a new program compositor pad showing the source. With `with_effects`,
+    /// the branch runs through an effect chain sized `width`x`height`, returned so
+    /// the caller can configure it.
+    pub fn request_pad(
+        &mut self,
+        id: &str,
+        with_effects: bool,
+        width: i32,
+        height: i32,
+    ) -> Result<(u32, Option<CameraEffectChain>)> {
+        let compositor = self.compositor.clone();
+        self.request_pad_on(&compositor, id, with_effects, width, height)
+    }
+
+    /// Like `request_pad`, on another compositor in the same pipeline.
+    pub fn request_pad_on(
+        &mut self,
+        compositor: &gst::Element,
+        id: &str,
+        with_effects: bool,
+        width: i32,
+        height: i32,
+    ) -> Result<(u32, Option<CameraEffectChain>)> {
+        let queue_name = self.element_name("branch");
+        let mut guard = BranchGuard::new(&self.pipeline);
+        let source = self
+            .sources
+            .iter_mut()
+            .find(|s| s.def.id == id)
+            .ok_or_else(|| anyhow!("No source with id '{}'", id))?;
+
+        let queue = gst::ElementFactory::make("queue", Some(&queue_name))?;
+        guard.add(&queue)?;
+        let tee_pad = guard.request_pad(&source.tee, "src_%u")?;
+        let queue_sink = queue.static_pad("sink").ok_or_else(|| anyhow!("Queue has no sink pad"))?;
+        tee_pad
+            .link(&queue_sink)
+            .map_err(|e| anyhow!("Failed to link tee branch: {:?}", e))?;
+
+        let chain = if with_effects {
+            let chain = CameraEffectChain::new(&format!("{}_fx", queue_name), width, height)?;
+            guard.add(&chain.element())?;
+            queue.link(&chain.element())?;
+            Some(chain)
+        } else {
+            None
+        };
+        let branch_src = chain.as_ref().map(|c| c.element()).unwrap_or_else(|| queue.clone());
+        let transform = TransformStage::new(&format!("{}_transform", queue_name))?;
+        let wipe = WipeStage::new(&format!("{}_wipe", queue_name))?;
+        guard.add(&transform.element())?;
+        guard.add(&wipe.element())?;
+        gst::Element::link_many(&[&branch_src, &transform.element(), &wipe.element()])?;
+
+        let comp_pad = guard.request_pad(compositor, "sink_%u")?;
+        let pad_index: u32 = comp_pad
+            .name()
+            .trim_start_matches("sink_")
+            .parse()
+            .map_err(|_| anyhow!("Unexpected compositor pad name {}", comp_pad.name()))?;
+        // Hidden until a scene shows it
+        comp_pad.set_property("alpha", 0.0f64);
+        wipe.element()
+            .static_pad("src")
+            .ok_or_else(|| anyhow!("Branch has no src pad"))?
+            .link(&comp_pad)
+            .map_err(|e| anyhow!("Failed to link source '{}': {:?}", id, e))?;
+
+        wipe.element().sync_state_with_parent()?;
+        transform.element().sync_state_with_parent()?;
+        if let Some(c) = &chain {
+            c.element().sync_state_with_parent()?;
+        }
+        queue.sync_state_with_parent()?;
+        guard.disarm();
+
+        source.branches.push(SourceBranch {
+            pad_index,
+            compositor: compositor.clone(),
+            tee_pad,
+            queue,
+            effects: chain.as_ref().map(|c| c.element()),
+            transform,
+            wipe,
+        });
+        Ok((pad_index, chain))
+    }
+
+    /// The wipe stage in front of a program compositor pad, used by wipe transitions.
+    pub fn wipe_stage(&self, pad_index: u32) -> Option<WipeStage> {
+        self.sources
+            .iter()
+            .flat_map(|s| s.branches.iter())
+            .find(|b| b.pad_index == pad_index && b.compositor == self.compositor)
+            .map(|b| b.wipe.clone())
+    }
+
+    /// The crop/flip/rotate stage in front of a program compositor pad.
+    pub fn transform_stage(&self, pad_index: u32) -> Option<TransformStage> {
+        let compositor = self.compositor.clone();
+        self.transform_stage_on(&compositor, pad_index)
+    }
+
+    /// Like `transform_stage`, for a pad obtained with `request_pad_on`.
+    pub fn transform_stage_on(&self, compositor: &gst::Element, pad_index: u32) -> Option<TransformStage> {
+        self.sources
+            .iter()
+            .flat_map(|s| s.branches.iter())
+            .find(|b| b.pad_index == pad_index && &b.compositor == compositor)
+            .map(|b| b.transform.clone())
+    }
+
+    /// Give back a program compositor pad obtained with `request_pad`.
+    pub fn release_pad(&mut self, pad_index: u32) -> Result<()> {
+        let compositor = self.compositor.clone();
+        self.release_pad_on(&compositor, pad_index)
+    }
+
+    /// Give back a pad obtained with `request_pad_on`.
+    pub fn release_pad_on(&mut self, compositor: &gst::Element, pad_index: u32) -> Result<()> {
+        let (tee, branch) = self
+            .sources
+            .iter_mut()
+            .find_map(|s| {
+                let pos = s
+                    .branches
+                    .iter()
+                    .position(|b| b.pad_index == pad_index && &b.compositor == compositor)?;
+                Some((s.tee.clone(), s.branches.remove(pos)))
+            })
+            .ok_or_else(|| anyhow!("No source uses compositor pad {} of {}", pad_index, compositor.name()))?;
+        self.teardown_branch(&tee, branch);
+        Ok(())
+    }
+
+    // Internal: unlink and drop the elements of a branch, releasing both request pads
+    fn teardown_branch(&self, tee: &gst::Element, branch: SourceBranch) {
+        if let Some(pad) = branch.compositor.static_pad(&format!("sink_{}", branch.pad_index)) {
+            if let Some(peer) = pad.peer() {
+                let _ = peer.unlink(&pad);
+            }
+            branch.compositor.release_request_pad(&pad);
+        }
+        if let Some(queue_sink) = branch.queue.static_pad("sink") {
+            let _ = branch.tee_pad.unlink(&queue_sink);
+        }
+        tee.release_request_pad(&branch.tee_pad);
+        let transform = branch.transform.element();
+        let wipe = branch.wipe.element();
+        let elements = [&wipe, &transform]
+            .into_iter()
+            .chain(branch.effects.iter())
+            .chain(std::iter::once(&branch.queue));
+        for el in elements {
+            let _ = el.set_state(gst::State::Null);
+            let _ = self.pipeline.remove(el);
+        }
+    }
+
+    fn element_name(&mut self, prefix: &str) -> String {
+        self.next_element_id += 1;
+        format!("{}{}", prefix, self.next_element_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gstreamer_app as gst_app;
+
+    const SIZE: usize = 64;
+
+    fn is_red(sample: &gst::Sample) -> bool {
+        let buffer = sample.buffer().unwrap();
+        let map = buffer.map_readable().unwrap();
+        let at = (SIZE / 2 * SIZE + SIZE / 2) * 4;
+        // BGRA
+        let (b, g, r) = (map[at], map[at + 1], map[at + 2]);
+        r > 200 && g < 60 && b < 60
+    }
+
+    #[test]
+    fn media_file_added_to_a_playing_pipeline_reaches_the_output() {
+        gst::init().unwrap();
+        let clip = std::env::temp_dir().join(format!("waycord_media_{}.mkv", std::process::id()));
+        let writer = gst::parse_launch(&format!(
+            "videotestsrc num-buffers=60 pattern=red ! video/x-raw,format=I420,width={size},height={size},framerate=30/1 \
+             ! matroskamux ! filesink location=\"{}\"",
+            clip.display(),
+            size = SIZE
+        ))
+        .unwrap();
+        writer.set_state(gst::State::Playing).unwrap();
+        writer
+            .bus()
+            .unwrap()
+            .timed_pop_filtered(gst::ClockTime::from_seconds(10), &[gst::MessageType::Eos])
+            .expect("clip was not written");
+        writer.set_state(gst::State::Null).unwrap();
+
+        let caps = format!("video/x-raw,format=BGRA,width={size},height={size},framerate=30/1", size = SIZE);
+        let pipeline = gst::parse_launch(&format!(
+            "videotestsrc is-live=true pattern=black ! {caps} ! compositor name=comp background=black \
+             ! {caps} ! appsink name=out",
+            caps = caps
+        ))
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+        let compositor = pipeline.by_name("comp").unwrap();
+        let sink = pipeline.by_name("out").unwrap().downcast::<gst_app::AppSink>().unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        // A second and a half of running time the file has to catch up with
+        for _ in 0..45 {
+            assert!(!is_red(&sink.pull_sample().unwrap()));
+        }
+
+        let mut registry = SourceRegistry::new(pipeline.clone(), compositor.clone());
+        registry
+            .add_source(SourceDef {
+                id: "clip".to_string(),
+                name: "Clip".to_string(),
+                kind: SourceKind::MediaFile {
+                    uri: clip.to_str().unwrap().to_string(),
+                    looping: false,
+                    restart_on_activate: false,
+                    audio: false,
+                },
+            })
+            .unwrap();
+        let (pad_index, _) = registry.request_pad("clip", false, SIZE as i32, SIZE as i32).unwrap();
+        let pad = compositor.static_pad(&format!("sink_{}", pad_index)).unwrap();
+        pad.set_property("width", SIZE as i32);
+        pad.set_property("height", SIZE as i32);
+        pad.set_property("alpha", 1.0f64);
+        let shown = (0..60).any(|_| is_red(&sink.pull_sample().unwrap()));
+
+        pipeline.set_state(gst::State::Null).unwrap();
+        std::fs::remove_file(&clip).unwrap();
+        assert!(shown, "the media file never reached the output");
+    }
+}
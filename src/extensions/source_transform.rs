@@ -0,0 +1,513 @@
+// src/extensions/source_transform.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Pixels removed from each edge of a source, in source pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Crop {
+    #[serde(default)]
+    pub left: i32,
+    #[serde(default)]
+    pub right: i32,
+    #[serde(default)]
+    pub top: i32,
+    #[serde(default)]
+    pub bottom: i32,
+}
+
+impl Crop {
+    pub fn edge_mut(&mut self, edge: Edge) -> &mut i32 {
+        match edge {
+            Edge::Left => &mut self.left,
+            Edge::Right => &mut self.right,
+            Edge::Top => &mut self.top,
+            Edge::Bottom => &mut self.bottom,
+        }
+    }
+}
+
+/// An edge of a source's box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Edge {
+    pub fn opposite(self) -> Self {
+        match self {
+            Edge::Left => Edge::Right,
+            Edge::Right => Edge::Left,
+            Edge::Top => Edge::Bottom,
+            Edge::Bottom => Edge::Top,
+        }
+    }
+}
+
+/// How a source is fitted into its geometry box.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleMode {
+    /// Fill the box, ignoring the aspect ratio
+    #[default]
+    Stretch,
+    /// Largest size that fits in the box, centered (letterboxed)
+    Fit,
+    /// Cover the whole box, cropping what sticks out
+    Fill,
+    /// Native size at the box's top-left corner
+    None,
+}
+
+impl ScaleMode {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "stretch" => Some(ScaleMode::Stretch),
+            "fit" => Some(ScaleMode::Fit),
+            "fill" => Some(ScaleMode::Fill),
+            "none" => Some(ScaleMode::None),
+            _ => None,
+        }
+    }
+}
+
+/// Crop, flips and rotation of a source in a scene. Crop is applied first,
+/// then the flips, then `rotation` (clockwise quarter turns) and finally `angle`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceTransform {
+    #[serde(default)]
+    pub crop: Crop,
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270
+    #[serde(default)]
+    pub rotation: u16,
+    /// Free rotation in degrees on top of `rotation`; the frame size does not change
+    #[serde(default)]
+    pub angle: f64,
+    #[serde(default)]
+    pub flip_horizontal: bool,
+    #[serde(default)]
+    pub flip_vertical: bool,
+    #[serde(default)]
+    pub scale_mode: ScaleMode,
+}
+
+impl SourceTransform {
+    pub fn is_identity(&self) -> bool {
+        *self == SourceTransform::default()
+    }
+
+    /// Whether width and height swap (a quarter or three-quarter turn).
+    pub fn swaps_axes(&self) -> bool {
+        matches!(self.rotation % 360, 90 | 270)
+    }
+
+    /// The edge of the unrotated source that ends up at `edge` on the canvas.
+    pub fn source_edge(&self, edge: Edge) -> Edge {
+        // Undo the quarter turns; a clockwise turn moves the source's left edge to the top
+        let mut e = edge;
+        for _ in 0..(self.rotation / 90) % 4 {
+            e = match e {
+                Edge::Top => Edge::Left,
+                Edge::Right => Edge::Top,
+                Edge::Bottom => Edge::Right,
+                Edge::Left => Edge::Bottom,
+            };
+        }
+        match e {
+            Edge::Left | Edge::Right if self.flip_horizontal => e.opposite(),
+            Edge::Top | Edge::Bottom if self.flip_vertical => e.opposite(),
+            _ => e,
+        }
+    }
+
+    /// Blend the animatable parts (crop and angle) towards `to`; the rest is taken from `to`.
+    pub fn lerp(&self, to: &SourceTransform, t: f64) -> SourceTransform {
+        let mix = |a: i32, b: i32| a + ((b - a) as f64 * t).round() as i32;
+        SourceTransform {
+            crop: Crop {
+                left: mix(self.crop.left, to.crop.left),
+                right: mix(self.crop.right, to.crop.right),
+                top: mix(self.crop.top, to.crop.top),
+                bottom: mix(self.crop.bottom, to.crop.bottom),
+            },
+            angle: self.angle + (to.angle - self.angle) * t,
+            ..*to
+        }
+    }
+
+    // Internal: the single videoflip method for both flips and the quarter turns
+    fn flip_method(&self) -> &'static str {
+        // Flipping both ways is a half turn
+        let (mut quarter, horizontal) = match (self.flip_horizontal, self.flip_vertical) {
+            (true, true) => (self.rotation / 90 + 2, false),
+            // A vertical flip is a horizontal flip plus a half turn
+            (false, true) => (self.rotation / 90 + 2, true),
+            (h, false) => (self.rotation / 90, h),
+        };
+        quarter %= 4;
+        match (horizontal, quarter) {
+            (false, 0) => "none",
+            (false, 1) => "clockwise",
+            (false, 2) => "rotate-180",
+            (false, _) => "counterclockwise",
+            (true, 0) => "horizontal-flip",
+            (true, 1) => "upper-right-diagonal",
+            (true, 2) => "vertical-flip",
+            (true, _) => "upper-left-diagonal",
+        }
+    }
+}
+
+// Internal: output-selector ! (into .. out) ! input-selector, plus a direct
+// link between the selectors that frames take while bypassed. Adds the
+// selectors to `bin` and ghosts its "sink" and "src" pads; starts bypassed.
+#[derive(Clone)]
+struct Selectors {
+    route: gst::Element,
+    merge: gst::Element,
+    // (output-selector, input-selector) pads of the elements and the bypass
+    active_pads: (gst::Pad, gst::Pad),
+    bypass_pads: (gst::Pad, gst::Pad),
+}
+
+impl Selectors {
+    fn new(bin: &gst::Bin, into: &gst::Pad, out: &gst::Pad) -> Result<Self> {
+        let route = gst::ElementFactory::make("output-selector", None)?;
+        // Only the branch in use has to agree on caps
+        route.set_property_from_str("pad-negotiation-mode", "active");
+        let merge = gst::ElementFactory::make("input-selector", None)?;
+        bin.add_many(&[&route, &merge])?;
+
+        let request_pads = || -> Result<(gst::Pad, gst::Pad)> {
+            let route_pad = route
+                .request_pad_simple("src_%u")
+                .ok_or_else(|| anyhow!("output-selector refused a new src pad"))?;
+            let merge_pad = merge
+                .request_pad_simple("sink_%u")
+                .ok_or_else(|| anyhow!("input-selector refused a new sink pad"))?;
+            Ok((route_pad, merge_pad))
+        };
+        let active_pads = request_pads()?;
+        active_pads.0.link(into).map_err(|e| anyhow!("Failed to link {}: {:?}", bin.name(), e))?;
+        out.link(&active_pads.1).map_err(|e| anyhow!("Failed to link {}: {:?}", bin.name(), e))?;
+        // The bypass is a direct link from one selector to the other
+        let bypass_pads = request_pads()?;
+        bypass_pads
+            .0
+            .link(&bypass_pads.1)
+            .map_err(|e| anyhow!("Failed to link the bypass of {}: {:?}", bin.name(), e))?;
+        route.set_property("active-pad", &bypass_pads.0);
+        merge.set_property("active-pad", &bypass_pads.1);
+
+        let sink = route.static_pad("sink").ok_or_else(|| anyhow!("output-selector has no sink pad"))?;
+        let src = merge.static_pad("src").ok_or_else(|| anyhow!("input-selector has no src pad"))?;
+        bin.add_pad(&gst::GhostPad::with_target(Some("sink"), &sink)?)?;
+        bin.add_pad(&gst::GhostPad::with_target(Some("src"), &src)?)?;
+        Ok(Self {
+            route,
+            merge,
+            active_pads,
+            bypass_pads,
+        })
+    }
+
+    fn set_bypassed(&self, bypass: bool) {
+        let (route_pad, merge_pad) = if bypass { &self.bypass_pads } else { &self.active_pads };
+        self.route.set_property("active-pad", route_pad);
+        self.merge.set_property("active-pad", merge_pad);
+    }
+}
+
+struct StageState {
+    // Size entering the stage, once negotiated
+    input: Option<(i32, i32)>,
+    // Frames skip the elements while the transform is the identity
+    bypassed: bool,
+    transform: SourceTransform,
+    // Box the source is scaled into, for the fill crop
+    bounds: (i32, i32),
+}
+
+/// The transform bin in every source branch:
+///   output-selector ! videocrop ! videoflip ! videoconvert ! rotate ! input-selector
+///   output-selector ! input-selector (bypass)
+/// Like the camera effect chain, the elements stay in place and `apply` only
+/// changes properties. Untransformed sources take the bypass, so they are not
+/// converted and rotated by 0 for nothing. `rotate` is left out when
+/// gst-plugins-bad is missing.
+#[derive(Clone)]
+pub struct TransformStage {
+    bin: gst::Bin,
+    crop: gst::Element,
+    flip: gst::Element,
+    rotate: Option<gst::Element>,
+    selectors: Selectors,
+    state: Arc<Mutex<StageState>>,
+}
+
+impl TransformStage {
+    pub fn new(name: &str) -> Result<Self> {
+        let bin = gst::Bin::new(Some(name));
+        let crop = gst::ElementFactory::make("videocrop", None)
+            .map_err(|_| anyhow!("Failed to create videocrop. Is gst-plugins-good installed?"))?;
+        let flip = gst::ElementFactory::make("videoflip", None)?;
+        let convert = gst::ElementFactory::make("videoconvert", None)?;
+        // Corners uncovered by a free rotation stay transparent
+        let caps = gst::ElementFactory::make("capsfilter", None)?;
+        caps.set_property("caps", gst::Caps::builder("video/x-raw").field("format", "BGRA").build());
+        let rotate = gst::ElementFactory::make("rotate", None).ok();
+
+        bin.add_many(&[&crop, &flip, &convert, &caps])?;
+        gst::Element::link_many(&[&crop, &flip, &convert, &caps])?;
+        let last = match &rotate {
+            Some(rotate) => {
+                bin.add(rotate)?;
+                caps.link(rotate)?;
+                rotate.clone()
+            }
+            None => caps.clone(),
+        };
+        let crop_sink = crop.static_pad("sink").ok_or_else(|| anyhow!("videocrop has no sink pad"))?;
+        let last_src = last.static_pad("src").ok_or_else(|| anyhow!("Transform stage has no src pad"))?;
+        let selectors = Selectors::new(&bin, &crop_sink, &last_src)?;
+
+        let state = Arc::new(Mutex::new(StageState {
+            input: None,
+            bypassed: true,
+            transform: SourceTransform::default(),
+            bounds: (0, 0),
+        }));
+
+        // The fill crop depends on the input size, which is only known once negotiated
+        let state_ref = state.clone();
+        let crop_ref = crop.clone();
+        crop_sink.connect_notify(Some("caps"), move |pad, _| {
+            let size = pad.current_caps().and_then(|caps| {
+                let s = caps.structure(0)?;
+                Some((s.get::<i32>("width").ok()?, s.get::<i32>("height").ok()?))
+            });
+            let mut state = state_ref.lock().unwrap();
+            if size.is_some() && size != state.input {
+                state.input = size;
+                update_crop(&crop_ref, &state);
+            }
+        });
+
+        Ok(Self {
+            bin,
+            crop,
+            flip,
+            rotate,
+            selectors,
+            state,
+        })
+    }
+
+    /// The bin to link into the branch.
+    pub fn element(&self) -> gst::Element {
+        self.bin.clone().upcast()
+    }
+
+    /// Crop applied right now as (left, right, top, bottom), fill crop included.
+    pub fn current_crop(&self) -> (i32, i32, i32, i32) {
+        if self.state.lock().unwrap().bypassed {
+            return (0, 0, 0, 0);
+        }
+        (
+            self.crop.property("left"),
+            self.crop.property("right"),
+            self.crop.property("top"),
+            self.crop.property("bottom"),
+        )
+    }
+
+    /// Apply a transform for a source shown in a `width`x`height` box.
+    pub fn apply(&self, transform: &SourceTransform, width: i32, height: i32) {
+        self.flip.set_property_from_str("method", transform.flip_method());
+        match &self.rotate {
+            Some(rotate) => rotate.set_property("angle", transform.angle.to_radians()),
+            None if transform.angle != 0.0 => eprintln!("Free rotation needs the rotate element (gst-plugins-bad)."),
+            None => {}
+        }
+        let mut state = self.state.lock().unwrap();
+        state.transform = *transform;
+        state.bounds = (width, height);
+        update_crop(&self.crop, &state);
+
+        let bypass = transform.is_identity();
+        if bypass != state.bypassed {
+            state.bypassed = bypass;
+            self.selectors.set_bypassed(bypass);
+        }
+    }
+}
+
+// Internal: user crop, plus the extra crop that makes a fill-mode source
+// match the aspect ratio of its box
+fn update_crop(crop: &gst::Element, state: &StageState) {
+    let t = &state.transform;
+    let (mut left, mut right, mut top, mut bottom) = (t.crop.left, t.crop.right, t.crop.top, t.crop.bottom);
+
+    if let (ScaleMode::Fill, Some((in_w, in_h))) = (t.scale_mode, state.input) {
+        let (bw, bh) = state.bounds;
+        let (cw, ch) = (in_w - left - right, in_h - top - bottom);
+        if bw > 0 && bh > 0 && cw > 0 && ch > 0 {
+            // Work in output orientation, then map back to source edges
+            let (ow, oh) = if t.swaps_axes() { (ch, cw) } else { (cw, ch) };
+            let target = bw as f64 / bh as f64;
+            let (extra_w, extra_h) = if ow as f64 / oh as f64 > target {
+                ((ow - (oh as f64 * target).round() as i32) / 2, 0)
+            } else {
+                (0, (oh - (ow as f64 / target).round() as i32) / 2)
+            };
+            let (extra_x, extra_y) = if t.swaps_axes() { (extra_h, extra_w) } else { (extra_w, extra_h) };
+            left += extra_x;
+            right += extra_x;
+            top += extra_y;
+            bottom += extra_y;
+        }
+    }
+
+    crop.set_property("left", left.max(0));
+    crop.set_property("right", right.max(0));
+    crop.set_property("top", top.max(0));
+    crop.set_property("bottom", bottom.max(0));
+}
+
+/// The wipe bin in every source branch:
+///   output-selector ! videoconvert ! smptealpha ! input-selector
+///   output-selector ! input-selector (bypass)
+/// Frames only go through `smptealpha` while a wipe transition runs, so other
+/// sources are not converted every frame.
+#[derive(Clone)]
+pub struct WipeStage {
+    bin: gst::Bin,
+    wipe: gst::Element,
+    selectors: Selectors,
+}
+
+impl WipeStage {
+    pub fn new(name: &str) -> Result<Self> {
+        let bin = gst::Bin::new(Some(name));
+        let convert = gst::ElementFactory::make("videoconvert", None)?;
+        let wipe = gst::ElementFactory::make("smptealpha", None)
+            .map_err(|_| anyhow!("Failed to create smptealpha. Is gst-plugins-good installed?"))?;
+        bin.add_many(&[&convert, &wipe])?;
+        convert.link(&wipe)?;
+        let convert_sink = convert.static_pad("sink").ok_or_else(|| anyhow!("videoconvert has no sink pad"))?;
+        let wipe_src = wipe.static_pad("src").ok_or_else(|| anyhow!("smptealpha has no src pad"))?;
+        let selectors = Selectors::new(&bin, &convert_sink, &wipe_src)?;
+        Ok(Self { bin, wipe, selectors })
+    }
+
+    /// The bin to link into the branch.
+    pub fn element(&self) -> gst::Element {
+        self.bin.clone().upcast()
+    }
+
+    /// The `smptealpha` to set the pattern and position on.
+    pub fn wipe(&self) -> &gst::Element {
+        &self.wipe
+    }
+
+    /// Send frames through the wipe, or around it again.
+    pub fn set_active(&self, active: bool) {
+        self.selectors.set_bypassed(!active);
+    }
+}
+
+/// Put a scene item's geometry, stacking and scale mode on its compositor pad.
+pub fn apply_pad_layout(
+    pad: &gst::Pad,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+    transform: &SourceTransform,
+) {
+    pad.set_property("xpos", x);
+    pad.set_property("ypos", y);
+    match transform.scale_mode {
+        ScaleMode::None => {
+            // 0 makes the compositor use the input size
+            pad.set_property("width", 0);
+            pad.set_property("height", 0);
+        }
+        mode => {
+            pad.set_property("width", width);
+            pad.set_property("height", height);
+            let policy = if mode == ScaleMode::Fit { "keep-aspect-ratio" } else { "none" };
+            pad.set_property_from_str("sizing-policy", policy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(rotation: u16, flip_horizontal: bool, flip_vertical: bool) -> SourceTransform {
+        SourceTransform {
+            rotation,
+            flip_horizontal,
+            flip_vertical,
+            ..SourceTransform::default()
+        }
+    }
+
+    #[test]
+    fn flip_method_for_quarter_turns() {
+        assert_eq!(transform(0, false, false).flip_method(), "none");
+        assert_eq!(transform(90, false, false).flip_method(), "clockwise");
+        assert_eq!(transform(180, false, false).flip_method(), "rotate-180");
+        assert_eq!(transform(270, false, false).flip_method(), "counterclockwise");
+        assert_eq!(transform(360, false, false).flip_method(), "none");
+    }
+
+    #[test]
+    fn flip_method_folds_flips_into_turns() {
+        assert_eq!(transform(0, true, false).flip_method(), "horizontal-flip");
+        assert_eq!(transform(0, false, true).flip_method(), "vertical-flip");
+        assert_eq!(transform(0, true, true).flip_method(), "rotate-180");
+        assert_eq!(transform(180, true, true).flip_method(), "none");
+        // Mirrored, then turned: the two diagonal flips
+        assert_eq!(transform(90, true, false).flip_method(), "upper-right-diagonal");
+        assert_eq!(transform(270, true, false).flip_method(), "upper-left-diagonal");
+        assert_eq!(transform(90, false, true).flip_method(), "upper-left-diagonal");
+    }
+
+    #[test]
+    fn source_edge_without_transform() {
+        let t = SourceTransform::default();
+        for edge in [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom] {
+            assert_eq!(t.source_edge(edge), edge);
+        }
+    }
+
+    #[test]
+    fn source_edge_follows_turns() {
+        // A clockwise quarter turn puts the source's left edge at the top
+        let t = transform(90, false, false);
+        assert_eq!(t.source_edge(Edge::Top), Edge::Left);
+        assert_eq!(t.source_edge(Edge::Right), Edge::Top);
+        assert_eq!(t.source_edge(Edge::Bottom), Edge::Right);
+        assert_eq!(t.source_edge(Edge::Left), Edge::Bottom);
+        assert_eq!(transform(180, false, false).source_edge(Edge::Top), Edge::Bottom);
+        assert_eq!(transform(270, false, false).source_edge(Edge::Top), Edge::Right);
+    }
+
+    #[test]
+    fn source_edge_follows_flips() {
+        assert_eq!(transform(0, true, false).source_edge(Edge::Left), Edge::Right);
+        assert_eq!(transform(0, true, false).source_edge(Edge::Top), Edge::Top);
+        assert_eq!(transform(0, false, true).source_edge(Edge::Top), Edge::Bottom);
+        // Mirrored, then turned clockwise: the mirrored right edge ends up at the top
+        assert_eq!(transform(90, true, false).source_edge(Edge::Top), Edge::Right);
+    }
+}
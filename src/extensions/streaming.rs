@@ -14,11 +14,18 @@ pub enum StreamingProtocol {
     HLS(String),
 }
 
-/// Each streaming output has its own queue, mux, sink, and possibly new elements.
+/// Each streaming output is a branch of the program tee and the program
+/// audio tee with its own encoders, mux and sink, so it carries exactly what
+/// Program shows and plays.
 pub struct StreamingOutput {
     pub protocol: StreamingProtocol,
-    /// The set of GStreamer elements used by this output branch
-    elements: Vec<gst::Element>,
+    /// queue ! videoconvert ! x264enc ! h264parse ! mux ! sink, and
+    /// queue ! audioconvert ! audioresample ! faac ! mux
+    pub bin: gst::Bin,
+    /// The program tee pad feeding `bin`
+    pub tee_pad: gst::Pad,
+    /// The program audio tee pad feeding `bin`
+    pub audio_tee_pad: gst::Pad,
 }
 
 /// Manages multi-protocol streaming, including adaptive bitrate.
@@ -54,10 +61,26 @@ impl MultiStreamingManager {
         }
     }
 
-    /// Add a streaming destination (RTMP, SRT, or HLS) to the pipeline.
+    /// Add a streaming destination (RTMP, SRT, or HLS) on a new branch of the
+    /// program tee. It starts right away when the pipeline is playing.
     pub fn add_output(&mut self, protocol: StreamingProtocol) -> Result<()> {
-        // Build queue + mux + sink based on protocol
+        let program_tee = self
+            .pipeline
+            .by_name("program_tee")
+            .ok_or_else(|| anyhow!("No program tee in the pipeline"))?;
+        let audio_tee = self
+            .pipeline
+            .by_name("program_audio_tee")
+            .ok_or_else(|| anyhow!("No program audio tee in the pipeline"))?;
+
+        // Build queue + encoder + mux + sink based on protocol
         let queue = gst::ElementFactory::make("queue", None)?;
+        let convert = gst::ElementFactory::make("videoconvert", None)?;
+        let encoder = gst::ElementFactory::make("x264enc", None)?;
+        encoder.set_property("bitrate", self.current_bitrate);
+        encoder.set_property_from_str("tune", "zerolatency");
+        encoder.set_property_from_str("speed-preset", "ultrafast");
+        let parse = gst::ElementFactory::make("h264parse", None)?;
         let mux: gst::Element;
         let sink: gst::Element;
 
@@ -82,38 +105,57 @@ impl MultiStreamingManager {
             }
         }
 
-        // Add to pipeline
-        self.pipeline.add_many(&[&queue, &mux, &sink])?;
-        queue.sync_state_with_parent()?;
-        mux.sync_state_with_parent()?;
-        sink.sync_state_with_parent()?;
-
-        // Link queue -> mux -> sink
-        gst::Element::link_many(&[&queue, &mux, &sink])
+        // Link queue -> convert -> encoder -> parse -> mux -> sink inside a bin
+        let bin = gst::Bin::new(None);
+        let elements = [&queue, &convert, &encoder, &parse, &mux, &sink];
+        bin.add_many(&elements)?;
+        gst::Element::link_many(&elements)
             .map_err(|_| anyhow!("Failed to link streaming elements for output."))?;
+        let queue_sink = queue.static_pad("sink").ok_or_else(|| anyhow!("Queue has no sink pad"))?;
+        let input = gst::GhostPad::with_target(Some("sink"), &queue_sink)?;
+        bin.add_pad(&input)?;
+
+        // Program audio: queue -> convert -> resample -> AAC -> the same mux
+        let audio_queue = gst::ElementFactory::make("queue", None)?;
+        let audio_convert = gst::ElementFactory::make("audioconvert", None)?;
+        let audio_resample = gst::ElementFactory::make("audioresample", None)?;
+        let audio_encoder = gst::ElementFactory::make("faac", None)?;
+        bin.add_many(&[&audio_queue, &audio_convert, &audio_resample, &audio_encoder])?;
+        gst::Element::link_many(&[&audio_queue, &audio_convert, &audio_resample, &audio_encoder, &mux])
+            .map_err(|_| anyhow!("Failed to link streaming audio for output."))?;
+        let audio_queue_sink = audio_queue
+            .static_pad("sink")
+            .ok_or_else(|| anyhow!("Queue has no sink pad"))?;
+        let audio_input = gst::GhostPad::with_target(Some("audio_sink"), &audio_queue_sink)?;
+        bin.add_pad(&audio_input)?;
+
+        // Add to pipeline and feed it from the program tees
+        self.pipeline.add(&bin)?;
+        let tee_pad = program_tee
+            .request_pad_simple("src_%u")
+            .ok_or_else(|| anyhow!("Program tee refused a new src pad"))?;
+        tee_pad
+            .link(&input)
+            .map_err(|e| anyhow!("Failed to link streaming output: {:?}", e))?;
+        let audio_tee_pad = audio_tee
+            .request_pad_simple("src_%u")
+            .ok_or_else(|| anyhow!("Program audio tee refused a new src pad"))?;
+        audio_tee_pad
+            .link(&audio_input)
+            .map_err(|e| anyhow!("Failed to link streaming audio: {:?}", e))?;
+        bin.sync_state_with_parent()?;
 
         // Store them
         self.outputs.push(StreamingOutput {
             protocol,
-            elements: vec![queue, mux, sink],
+            bin,
+            tee_pad,
+            audio_tee_pad,
         });
 
         Ok(())
     }
 
-    /// Link this streaming manager to the encoder’s output. Typically you'd do:
-    ///   x264enc ! tee name=t ! queue ! multi_stream_manager
-    /// So it has a single input pad from the tee or queue.
-    pub fn link_input(&mut self, src_element: &gst::Element) -> Result<()> {
-        // We link from src_element’s src pad to each output queue’s sink pad
-        for output in &self.outputs {
-            // First element in output branch is the queue
-            let queue = &output.elements[0];
-            gst::Element::link_many(&[src_element, queue])
-                .map_err(|_| anyhow!("Failed to link src_element to streaming queue."))?;
-        }
-        Ok(())
-    }
 
     /// Start adaptive bitrate monitoring. We'll track data usage every X seconds,
     /// adjust the bitrate property on the encoder if needed.
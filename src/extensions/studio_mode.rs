@@ -0,0 +1,172 @@
+// src/extensions/studio_mode.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use anyhow::{anyhow, Result};
+
+use crate::extensions::scene_switcher::SceneSwitcher;
+
+/// OBS-style studio mode. The main compositor stays the "program" (what is
+/// recorded and streamed); a second compositor renders the "preview" scene so
+/// the next scene can be staged without viewers seeing it. Both outputs are
+/// shown in `gtksink` widgets.
+pub struct StudioMode {
+    pipeline: gst::Pipeline,
+    preview_compositor: gst::Element,
+    preview_elements: Vec<gst::Element>,
+    program_elements: Vec<gst::Element>,
+    program_tee: gst::Element,
+    program_tee_pad: gst::Pad,
+    preview_widget: gtk4::Widget,
+    program_widget: gtk4::Widget,
+    preview_scene: Option<usize>,
+    // Pads on the preview compositor, released when the preview scene changes
+    preview_pads: Vec<u32>,
+}
+
+impl StudioMode {
+    /// Build the preview compositor and a program monitor on `program_tee`
+    /// (the tee after the main compositor).
+    pub fn new(pipeline: &gst::Pipeline, program_tee: &gst::Element, canvas: (i32, i32)) -> Result<Self> {
+        let preview_compositor = gst::ElementFactory::make("compositor", Some("preview_comp"))?;
+        let preview_caps = gst::ElementFactory::make("capsfilter", None)?;
+        preview_caps.set_property(
+            "caps",
+            &gst::Caps::builder("video/x-raw")
+                .field("width", canvas.0)
+                .field("height", canvas.1)
+                .build(),
+        );
+        let preview_convert = gst::ElementFactory::make("videoconvert", None)?;
+        let preview_sink = gst::ElementFactory::make("gtksink", Some("preview_sink"))?;
+
+        let program_queue = gst::ElementFactory::make("queue", None)?;
+        // A slow monitor must never hold up the program output
+        program_queue.set_property_from_str("leaky", "downstream");
+        program_queue.set_property("max-size-buffers", 2u32);
+        let program_convert = gst::ElementFactory::make("videoconvert", None)?;
+        let program_sink = gst::ElementFactory::make("gtksink", Some("program_sink"))?;
+
+        let preview_elements = vec![preview_compositor.clone(), preview_caps, preview_convert, preview_sink.clone()];
+        let program_elements = vec![program_queue.clone(), program_convert, program_sink.clone()];
+        pipeline.add_many(&preview_elements.iter().collect::<Vec<_>>())?;
+        pipeline.add_many(&program_elements.iter().collect::<Vec<_>>())?;
+        gst::Element::link_many(&preview_elements.iter().collect::<Vec<_>>())?;
+        gst::Element::link_many(&program_elements.iter().collect::<Vec<_>>())?;
+
+        let program_tee_pad = program_tee
+            .request_pad_simple("src_%u")
+            .ok_or_else(|| anyhow!("Program tee refused a new src pad"))?;
+        program_tee_pad
+            .link(&program_queue.static_pad("sink").ok_or_else(|| anyhow!("Queue has no sink pad"))?)
+            .map_err(|e| anyhow!("Failed to link program monitor: {:?}", e))?;
+
+        let preview_widget = preview_sink
+            .property::<Option<gtk4::Widget>>("widget")
+            .ok_or_else(|| anyhow!("gtksink has no widget"))?;
+        let program_widget = program_sink
+            .property::<Option<gtk4::Widget>>("widget")
+            .ok_or_else(|| anyhow!("gtksink has no widget"))?;
+
+        for el in preview_elements.iter().chain(program_elements.iter()) {
+            el.sync_state_with_parent()?;
+        }
+
+        Ok(Self {
+            pipeline: pipeline.clone(),
+            preview_compositor,
+            preview_elements,
+            program_elements,
+            program_tee: program_tee.clone(),
+            program_tee_pad,
+            preview_widget,
+            program_widget,
+            preview_scene: None,
+            preview_pads: Vec::new(),
+        })
+    }
+
+    pub fn preview_widget(&self) -> &gtk4::Widget {
+        &self.preview_widget
+    }
+
+    pub fn program_widget(&self) -> &gtk4::Widget {
+        &self.program_widget
+    }
+
+    pub fn preview_scene(&self) -> Option<usize> {
+        self.preview_scene
+    }
+
+    /// Render a scene on the preview compositor, with the same layout and effects
+    /// it has on program. Call again after editing the scene to refresh.
+    pub fn set_preview_scene(&mut self, switcher: &mut SceneSwitcher, index: usize) -> Result<()> {
+        let sources = switcher
+            .scenes()
+            .get(index)
+            .ok_or_else(|| anyhow!("Scene index out of range."))?
+            .sources
+            .clone();
+
+        self.release_preview_pads(switcher);
+        for src in &sources {
+            let is_camera = switcher
+                .registry()
+                .get(&src.source_id)
+                .map(|def| def.kind.is_camera())
+                .unwrap_or(false);
+            let (pad_index, chain) = switcher.registry_mut().request_pad_on(
+                &self.preview_compositor,
+                &src.source_id,
+                src.effects.is_some() || is_camera,
+                src.width,
+                src.height,
+            )?;
+            self.preview_pads.push(pad_index);
+            if let Some(chain) = chain {
+                chain.apply(&src.effects.clone().unwrap_or_default());
+            }
+            let pad_name = format!("sink_{}", pad_index);
+            let pad = self
+                .preview_compositor
+                .static_pad(&pad_name)
+                .ok_or_else(|| anyhow!("Preview compositor pad not found: {}", pad_name))?;
+            let stage = switcher.registry().transform_stage_on(&self.preview_compositor, pad_index);
+            src.apply_to_pad(&pad, stage.as_ref());
+        }
+        self.preview_scene = Some(index);
+        Ok(())
+    }
+
+    /// Take the preview scene to program with the switcher's transition. The
+    /// scene that was on program becomes the new preview.
+    pub fn transition(&mut self, switcher: &mut SceneSwitcher) -> Result<()> {
+        let target = self.preview_scene.ok_or_else(|| anyhow!("No scene in preview."))?;
+        let previous = switcher.current_scene_index();
+        switcher.switch_to_scene(target)?;
+        if previous != target {
+            self.set_preview_scene(switcher, previous)?;
+        }
+        Ok(())
+    }
+
+    /// Tear down the preview compositor and the program monitor.
+    pub fn shutdown(mut self, switcher: &mut SceneSwitcher) {
+        self.release_preview_pads(switcher);
+        if let Some(peer) = self.program_tee_pad.peer() {
+            let _ = self.program_tee_pad.unlink(&peer);
+        }
+        self.program_tee.release_request_pad(&self.program_tee_pad);
+        for el in self.preview_elements.iter().chain(self.program_elements.iter()) {
+            let _ = el.set_state(gst::State::Null);
+            let _ = self.pipeline.remove(el);
+        }
+    }
+
+    fn release_preview_pads(&mut self, switcher: &mut SceneSwitcher) {
+        for pad_index in self.preview_pads.drain(..) {
+            // Already gone if the collection was reloaded in the meantime
+            let _ = switcher.registry_mut().release_pad_on(&self.preview_compositor, pad_index);
+        }
+    }
+}
@@ -0,0 +1,384 @@
+// src/extensions/subtitles.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::core::encoder::recording_elapsed;
+
+// How often the muxed subtitle stream is brought up to date while nothing changes
+const MUX_INTERVAL: Duration = Duration::from_millis(500);
+
+// Text each overlay shows now, and the track of the running recording
+static SUBTITLES: Mutex<Subtitles> = Mutex::new(Subtitles {
+    shown: BTreeMap::new(),
+    track: None,
+});
+
+/// Which subtitle outputs a recording gets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Write "<recording>.srt" next to the recording
+    #[serde(default = "default_true")]
+    pub srt: bool,
+    /// Write "<recording>.vtt" next to the recording
+    #[serde(default)]
+    pub vtt: bool,
+    /// Also mux a subtitle stream into MKV recordings
+    #[serde(default = "default_true")]
+    pub embed_mkv: bool,
+}
+
+impl Default for SubtitleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            srt: true,
+            vtt: false,
+            embed_mkv: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SubtitleConfig {
+    pub fn default_path() -> PathBuf {
+        glib::user_config_dir().join("waycord").join("subtitles.toml")
+    }
+
+    pub fn load_from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    pub fn save_to_file(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The saved settings, or disabled defaults if there are none or they
+    /// are unreadable.
+    pub fn load_or_default() -> Self {
+        let path = Self::default_path();
+        if !path.exists() {
+            return Self::default();
+        }
+        Self::load_from_file(&path).unwrap_or_else(|e| {
+            eprintln!("Ignoring saved subtitle settings: {:?}", e);
+            Self::default()
+        })
+    }
+}
+
+/// One subtitle, in recording running time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+struct Track {
+    config: SubtitleConfig,
+    // Recording path without extension
+    base: String,
+    cues: Vec<Cue>,
+    // Cues still on screen, by overlay key, with their start
+    open: BTreeMap<String, (Duration, String)>,
+    // Subtitle stream of an MKV recording
+    appsrc: Option<gst_app::AppSrc>,
+    // Running time the subtitle stream has been sent up to
+    muxed: Duration,
+    // Held while the track runs; the mux thread stops once it is gone
+    alive: Arc<()>,
+}
+
+impl Track {
+    fn close(&mut self, key: &str, now: Duration) {
+        if let Some((start, text)) = self.open.remove(key) {
+            self.push(Cue { start, end: now, text });
+        }
+    }
+
+    fn push(&mut self, cue: Cue) {
+        if cue.end <= cue.start {
+            return;
+        }
+        self.cues.push(cue);
+    }
+
+    // Send the subtitle stream up to `now`. Cues overlap and overlay cues are
+    // only closed after they started, so the time since the last call is cut
+    // where the text on screen changes: each stretch becomes one buffer at its
+    // start with the shown lines, or a gap when nothing is shown so the muxer
+    // does not wait on this stream. PTS only moves forward.
+    fn mux_until(&mut self, now: Duration) {
+        let appsrc = match &self.appsrc {
+            Some(appsrc) if now > self.muxed => appsrc,
+            _ => return,
+        };
+        let from = self.muxed;
+        let shown: Vec<(Duration, Duration, &str)> = self
+            .cues
+            .iter()
+            .map(|cue| (cue.start, cue.end, cue.text.as_str()))
+            .chain(self.open.values().map(|(start, text)| (*start, now, text.as_str())))
+            .filter(|(start, end, _)| *end > from && *start < now)
+            .collect();
+        let mut edges = vec![from, now];
+        for (start, end, _) in &shown {
+            edges.extend([*start, *end].into_iter().filter(|t| *t > from && *t < now));
+        }
+        edges.sort();
+        edges.dedup();
+
+        for stretch in edges.windows(2) {
+            let (start, end) = (stretch[0], stretch[1]);
+            let pts = gst::ClockTime::from_nseconds(start.as_nanos() as u64);
+            let duration = gst::ClockTime::from_nseconds((end - start).as_nanos() as u64);
+            let lines: Vec<&str> = shown
+                .iter()
+                .filter(|(s, e, _)| *s <= start && *e >= end)
+                .map(|(_, _, text)| *text)
+                .collect();
+            if lines.is_empty() {
+                appsrc.send_event(gst::event::Gap::builder(pts).duration(duration).build());
+                continue;
+            }
+            let mut buffer = gst::Buffer::from_slice(lines.join("\n").into_bytes());
+            if let Some(buffer) = buffer.get_mut() {
+                buffer.set_pts(pts);
+                buffer.set_duration(duration);
+            }
+            if let Err(e) = appsrc.push_buffer(buffer) {
+                eprintln!("Failed to mux subtitle: {:?}", e);
+            }
+        }
+        self.muxed = now;
+    }
+}
+
+struct Subtitles {
+    shown: BTreeMap<String, String>,
+    track: Option<Track>,
+}
+
+/// Report the text an overlay shows now (a rotating message, a lower
+/// third's title), or None once it shows nothing. While recording, every
+/// change ends the overlay's current cue and starts a new one. Cheap when
+/// nothing changed, so it can be called for every frame.
+pub fn set_text(key: &str, text: Option<&str>) {
+    let text = text.filter(|t| !t.trim().is_empty());
+    let recording = {
+        let subs = SUBTITLES.lock().unwrap();
+        if subs.shown.get(key).map(String::as_str) == text {
+            return;
+        }
+        subs.track.is_some()
+    };
+    // Looked up outside the lock: the recording pipeline has its own
+    let now = if recording { recording_elapsed() } else { None };
+    let mut subs = SUBTITLES.lock().unwrap();
+    match text {
+        Some(text) => subs.shown.insert(key.to_string(), text.to_string()),
+        None => subs.shown.remove(key),
+    };
+    if let (Some(track), Some(now)) = (subs.track.as_mut(), now) {
+        // What was shown until now goes out with the old text
+        track.mux_until(now);
+        track.close(key, now);
+        if let Some(text) = text {
+            track.open.insert(key.to_string(), (now, text.to_string()));
+        }
+    }
+}
+
+/// Add an operator caption shown for `secs` from now. Captions may overlap
+/// each other and overlay text; players stack them.
+pub fn caption(text: &str, secs: f64) -> Result<()> {
+    let now = recording_elapsed().ok_or_else(|| anyhow!("Captions are only kept while recording."))?;
+    let mut subs = SUBTITLES.lock().unwrap();
+    let track = subs.track.as_mut().ok_or_else(|| anyhow!("Subtitles are off for this recording."))?;
+    track.mux_until(now);
+    track.push(Cue {
+        start: now,
+        end: now + Duration::from_secs_f64(secs.max(0.1)),
+        text: text.to_string(),
+    });
+    Ok(())
+}
+
+/// Start collecting cues for a recording saved as `base` plus its extension.
+/// Text already on screen starts at 0. With `appsrc`, the cues are also sent
+/// as a subtitle stream while recording.
+pub fn begin(config: SubtitleConfig, base: &str, appsrc: Option<gst_app::AppSrc>) {
+    let mut subs = SUBTITLES.lock().unwrap();
+    let open = subs
+        .shown
+        .iter()
+        .map(|(key, text)| (key.clone(), (Duration::ZERO, text.clone())))
+        .collect();
+    let alive = Arc::new(());
+    if appsrc.is_some() {
+        let weak = Arc::downgrade(&alive);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(MUX_INTERVAL);
+            // Looked up outside the lock, like in `set_text`
+            let now = recording_elapsed();
+            let mut subs = SUBTITLES.lock().unwrap();
+            let track = match subs.track.as_mut() {
+                Some(track) if weak.upgrade().map(|alive| Arc::ptr_eq(&alive, &track.alive)).unwrap_or(false) => track,
+                _ => break,
+            };
+            if let Some(now) = now {
+                track.mux_until(now);
+            }
+        });
+    }
+    subs.track = Some(Track {
+        config,
+        base: base.to_string(),
+        cues: Vec::new(),
+        open,
+        appsrc,
+        muxed: Duration::ZERO,
+        alive,
+    });
+}
+
+/// End the recording's track at `end` and write its sidecar files.
+pub fn finish(end: Duration) -> Result<()> {
+    let track = SUBTITLES.lock().unwrap().track.take();
+    let mut track = match track {
+        Some(track) => track,
+        None => return Ok(()),
+    };
+    track.mux_until(end);
+    let keys: Vec<String> = track.open.keys().cloned().collect();
+    for key in keys {
+        track.close(&key, end);
+    }
+    if let Some(appsrc) = &track.appsrc {
+        let _ = appsrc.end_of_stream();
+    }
+    let mut cues = track.cues;
+    // Captions are added whole, so they can end after the recording did
+    for cue in &mut cues {
+        cue.end = cue.end.min(end);
+    }
+    cues.retain(|cue| cue.end > cue.start);
+    cues.sort_by_key(|cue| cue.start);
+    if track.config.srt {
+        std::fs::write(format!("{}.srt", track.base), to_srt(&cues))?;
+    }
+    if track.config.vtt {
+        std::fs::write(format!("{}.vtt", track.base), to_webvtt(&cues))?;
+    }
+    Ok(())
+}
+
+// In both formats a blank line would end a cue early, hence the "\n\n" folding
+pub fn to_srt(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for (i, cue) in cues.iter().enumerate() {
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            timestamp(cue.start, ','),
+            timestamp(cue.end, ','),
+            cue.text.replace("\n\n", "\n")
+        );
+    }
+    out
+}
+
+pub fn to_webvtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        let _ = write!(
+            out,
+            "{} --> {}\n{}\n\n",
+            timestamp(cue.start, '.'),
+            timestamp(cue.end, '.'),
+            cue.text.replace("\n\n", "\n")
+        );
+    }
+    out
+}
+
+// Internal: HH:MM:SS,mmm (SRT) or HH:MM:SS.mmm (WebVTT)
+fn timestamp(d: Duration, separator: char) -> String {
+    let ms = d.as_millis();
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start_ms: u64, end_ms: u64, text: &str) -> Cue {
+        Cue {
+            start: Duration::from_millis(start_ms),
+            end: Duration::from_millis(end_ms),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn timestamp_pads_every_field() {
+        assert_eq!(timestamp(Duration::ZERO, ','), "00:00:00,000");
+        assert_eq!(timestamp(Duration::from_millis(1_234), '.'), "00:00:01.234");
+        assert_eq!(timestamp(Duration::from_millis(3_723_045), ','), "01:02:03,045");
+        // Hours are not wrapped
+        assert_eq!(timestamp(Duration::from_secs(100 * 3600), '.'), "100:00:00.000");
+        // Below a millisecond is dropped, not rounded
+        assert_eq!(timestamp(Duration::from_micros(999), ','), "00:00:00,000");
+    }
+
+    #[test]
+    fn srt_numbers_cues_from_one() {
+        let cues = [cue(0, 1_500, "Hello"), cue(61_000, 62_250, "Two\nlines")];
+        assert_eq!(
+            to_srt(&cues),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello\n\n2\n00:01:01,000 --> 00:01:02,250\nTwo\nlines\n\n"
+        );
+    }
+
+    #[test]
+    fn webvtt_has_a_header_and_dots() {
+        let cues = [cue(500, 2_000, "Hello")];
+        assert_eq!(to_webvtt(&cues), "WEBVTT\n\n00:00:00.500 --> 00:00:02.000\nHello\n\n");
+        assert_eq!(to_webvtt(&[]), "WEBVTT\n\n");
+        assert_eq!(to_srt(&[]), "");
+    }
+
+    #[test]
+    fn blank_lines_do_not_end_cues_early() {
+        let cues = [cue(0, 1_000, "First\n\nSecond")];
+        assert_eq!(to_srt(&cues), "1\n00:00:00,000 --> 00:00:01,000\nFirst\nSecond\n\n");
+        assert_eq!(to_webvtt(&cues), "WEBVTT\n\n00:00:00.000 --> 00:00:01.000\nFirst\nSecond\n\n");
+    }
+}
@@ -0,0 +1,196 @@
+// src/extensions/text_providers.rs
+
+use gtk4::gio;
+use gtk4::gio::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::core::encoder::recording_elapsed;
+
+// Named counters shared by every overlay and hotkey
+static COUNTERS: Mutex<BTreeMap<String, i64>> = Mutex::new(BTreeMap::new());
+
+/// Where a text overlay's value comes from. The overlay's text is a template:
+/// "{value}" in it is replaced, and an empty text shows the value alone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TextProvider {
+    /// Local wall-clock time, formatted like strftime ("%H:%M:%S", "%A %d %B", ...)
+    Clock {
+        #[serde(default = "default_clock_format")]
+        format: String,
+    },
+    /// Time recorded so far, pauses excluded; 00:00:00 when not recording
+    RecordingTime,
+    /// Counts down from `seconds` once the overlay shows it; `done_text` at zero
+    Countdown {
+        seconds: u64,
+        #[serde(default)]
+        done_text: String,
+    },
+    /// Contents of a file written by another tool (e.g. "now playing"), re-read when it changes
+    File { path: PathBuf },
+    /// A named counter, bumped by `hotkey` (a GTK accelerator such as "<Ctrl><Alt>1")
+    Counter {
+        name: String,
+        #[serde(default)]
+        start: i64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hotkey: Option<String>,
+    },
+    /// Burned-in recording metadata: "{time}" (local, with milliseconds),
+    /// "{host}", "{user}" and "{id}" in the text are filled in
+    Stamp { recording_id: String },
+}
+
+fn default_clock_format() -> String {
+    "%H:%M:%S".to_string()
+}
+
+/// A provider in use by an overlay, with what it needs between frames.
+pub struct ProviderState {
+    pub provider: TextProvider,
+    // When a countdown started; None while its overlay is hidden
+    started: Option<Instant>,
+    file_text: Arc<Mutex<String>>,
+}
+
+impl ProviderState {
+    pub fn new(provider: TextProvider) -> Self {
+        let file_text = match &provider {
+            TextProvider::File { path } => read_value_file(path),
+            _ => String::new(),
+        };
+        if let TextProvider::Counter { name, start, .. } = &provider {
+            COUNTERS.lock().unwrap().entry(name.clone()).or_insert(*start);
+        }
+        Self {
+            provider,
+            started: None,
+            file_text: Arc::new(Mutex::new(file_text)),
+        }
+    }
+
+    /// Start a countdown over.
+    pub fn restart(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// Follow the overlay's visibility: a countdown starts when its overlay
+    /// is shown and is reset when it is hidden.
+    pub fn set_shown(&mut self, shown: bool) {
+        match (shown, self.started) {
+            (true, None) => self.started = Some(Instant::now()),
+            (false, Some(_)) => self.started = None,
+            _ => {}
+        }
+    }
+
+    /// The text to draw now, from the overlay's template.
+    pub fn render(&self, template: &str) -> String {
+        let value = match &self.provider {
+            TextProvider::Stamp { recording_id } => {
+                let time = wall_clock_ms();
+                if template.is_empty() {
+                    return time;
+                }
+                return template
+                    .replace("{time}", &time)
+                    .replace("{host}", &glib::host_name())
+                    .replace("{user}", &glib::user_name().to_string_lossy())
+                    .replace("{id}", recording_id);
+            }
+            TextProvider::Clock { format } => glib::DateTime::now_local()
+                .and_then(|now| now.format(format))
+                .map(|s| s.to_string())
+                .unwrap_or_default(),
+            TextProvider::RecordingTime => format_hms(recording_elapsed().unwrap_or_default()),
+            TextProvider::Countdown { seconds, done_text } => {
+                let elapsed = self.started.map(|t| t.elapsed()).unwrap_or_default();
+                let left = Duration::from_secs(*seconds).saturating_sub(elapsed);
+                if left.is_zero() && !done_text.is_empty() {
+                    return done_text.clone();
+                }
+                // Round up, so the last second reads 00:01 rather than 00:00
+                let secs = left.as_secs() + u64::from(left.subsec_nanos() > 0);
+                if secs >= 3600 {
+                    format_hms(Duration::from_secs(secs))
+                } else {
+                    format!("{:02}:{:02}", secs / 60, secs % 60)
+                }
+            }
+            TextProvider::File { .. } => self.file_text.lock().unwrap().clone(),
+            TextProvider::Counter { name, .. } => counter(name).to_string(),
+        };
+        if template.is_empty() {
+            value
+        } else {
+            template.replace("{value}", &value)
+        }
+    }
+
+    /// For a file provider, re-read the file whenever it changes. The monitor
+    /// must be kept alive for as long as the file should be watched.
+    pub fn watch(&self) -> Option<gio::FileMonitor> {
+        let path = match &self.provider {
+            TextProvider::File { path } => path.clone(),
+            _ => return None,
+        };
+        let monitor = gio::File::for_path(&path)
+            .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+            .map_err(|e| eprintln!("Cannot watch {}: {}", path.display(), e))
+            .ok()?;
+        let file_text = self.file_text.clone();
+        monitor.connect_changed(move |_, _, _, event| {
+            if event != gio::FileMonitorEvent::Deleted {
+                *file_text.lock().unwrap() = read_value_file(&path);
+            }
+        });
+        Some(monitor)
+    }
+}
+
+/// Add `delta` to a named counter, creating it at 0 if needed.
+pub fn bump_counter(name: &str, delta: i64) -> i64 {
+    let mut counters = COUNTERS.lock().unwrap();
+    let value = counters.entry(name.to_string()).or_insert(0);
+    *value += delta;
+    *value
+}
+
+pub fn set_counter(name: &str, value: i64) {
+    COUNTERS.lock().unwrap().insert(name.to_string(), value);
+}
+
+pub fn counter(name: &str) -> i64 {
+    COUNTERS.lock().unwrap().get(name).copied().unwrap_or(0)
+}
+
+fn read_value_file(path: &Path) -> String {
+    match std::fs::read_to_string(path) {
+        Ok(text) => text.trim_end().to_string(),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path.display(), e);
+            String::new()
+        }
+    }
+}
+
+// Internal: local date and time down to the millisecond, e.g. "2024-05-01 14:03:07.412"
+fn wall_clock_ms() -> String {
+    glib::DateTime::now_local()
+        .ok()
+        .and_then(|now| {
+            let base = now.format("%Y-%m-%d %H:%M:%S").ok()?;
+            Some(format!("{}.{:03}", base, now.microsecond() / 1000))
+        })
+        .unwrap_or_default()
+}
+
+fn format_hms(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
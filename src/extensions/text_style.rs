@@ -0,0 +1,208 @@
+// src/extensions/text_style.rs
+
+use gtk4::cairo;
+use gtk4::pango;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// Which point of the text box sits at the overlay's x/y.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    // Internal: fraction of the box's width and height left of and above the anchor point
+    fn factors(self) -> (f64, f64) {
+        match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Outline {
+    #[serde(default = "default_outline_width")]
+    pub width: f64,
+    /// 0xAARRGGBB
+    #[serde(default = "default_dark")]
+    pub color: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Shadow {
+    #[serde(default = "default_shadow_offset")]
+    pub dx: f64,
+    #[serde(default = "default_shadow_offset")]
+    pub dy: f64,
+    /// 0xAARRGGBB
+    #[serde(default = "default_shadow_color")]
+    pub color: u32,
+}
+
+/// A box drawn behind the text.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TextBackground {
+    /// 0xAARRGGBB
+    #[serde(default = "default_shadow_color")]
+    pub color: u32,
+    /// Space between the text and the box edge, in pixels
+    #[serde(default = "default_padding")]
+    pub padding: f64,
+    #[serde(default)]
+    pub corner_radius: f64,
+}
+
+/// How a text overlay is drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextStyle {
+    /// Pango font description, e.g. "Sans Bold 32"
+    #[serde(default = "default_font")]
+    pub font: String,
+    /// 0xAARRGGBB
+    #[serde(default = "default_color")]
+    pub color: u32,
+    /// Parse the text as Pango markup (<b>, <span foreground=...>, ...)
+    #[serde(default)]
+    pub markup: bool,
+    /// Wrap lines longer than this many pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrap_width: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outline: Option<Outline>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow: Option<Shadow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<TextBackground>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            font: default_font(),
+            color: default_color(),
+            markup: false,
+            wrap_width: None,
+            outline: None,
+            shadow: None,
+            background: None,
+        }
+    }
+}
+
+fn default_font() -> String {
+    "Sans 24".to_string()
+}
+fn default_color() -> u32 {
+    0xffffffff
+}
+fn default_dark() -> u32 {
+    0xff000000
+}
+fn default_outline_width() -> f64 {
+    2.0
+}
+fn default_shadow_offset() -> f64 {
+    2.0
+}
+fn default_shadow_color() -> u32 {
+    0x99000000
+}
+fn default_padding() -> f64 {
+    8.0
+}
+
+/// Draw `text` with its anchor point at `x`, `y` (pixels of the cairo target).
+/// Markup that fails to parse is shown as plain text.
+pub fn draw_text(cr: &cairo::Context, text: &str, style: &TextStyle, x: f64, y: f64, anchor: Anchor) {
+    let layout = text_layout(cr, text, style);
+    let (_, logical) = layout.pixel_extents();
+    let padding = style.background.map(|b| b.padding).unwrap_or(0.0);
+    let box_w = logical.width() as f64 + 2.0 * padding;
+    let box_h = logical.height() as f64 + 2.0 * padding;
+    let (fx, fy) = anchor.factors();
+    let (bx, by) = ((x - box_w * fx).round(), (y - box_h * fy).round());
+
+    cr.save().ok();
+    if let Some(bg) = style.background {
+        rounded_rect(cr, bx, by, box_w, box_h, bg.corner_radius);
+        set_argb(cr, bg.color);
+        let _ = cr.fill();
+    }
+
+    let (tx, ty) = (bx + padding - logical.x() as f64, by + padding - logical.y() as f64);
+    if let Some(shadow) = style.shadow {
+        cr.move_to(tx + shadow.dx, ty + shadow.dy);
+        pangocairo::functions::layout_path(cr, &layout);
+        set_argb(cr, shadow.color);
+        let _ = cr.fill();
+    }
+    if let Some(outline) = style.outline {
+        cr.move_to(tx, ty);
+        pangocairo::functions::layout_path(cr, &layout);
+        set_argb(cr, outline.color);
+        // Half the stroke is covered by the glyphs drawn next
+        cr.set_line_width(outline.width * 2.0);
+        cr.set_line_join(cairo::LineJoin::Round);
+        let _ = cr.stroke();
+    }
+    cr.move_to(tx, ty);
+    set_argb(cr, style.color);
+    pangocairo::functions::show_layout(cr, &layout);
+    cr.restore().ok();
+}
+
+/// Size of the box `draw_text` fills, background padding included.
+pub fn text_size(cr: &cairo::Context, text: &str, style: &TextStyle) -> (f64, f64) {
+    let (_, logical) = text_layout(cr, text, style).pixel_extents();
+    let padding = style.background.map(|b| b.padding).unwrap_or(0.0);
+    (logical.width() as f64 + 2.0 * padding, logical.height() as f64 + 2.0 * padding)
+}
+
+fn text_layout(cr: &cairo::Context, text: &str, style: &TextStyle) -> pango::Layout {
+    let layout = pangocairo::functions::create_layout(cr);
+    layout.set_font_description(Some(&pango::FontDescription::from_string(&style.font)));
+    if let Some(width) = style.wrap_width {
+        layout.set_width(width * pango::SCALE);
+        layout.set_wrap(pango::WrapMode::WordChar);
+    }
+    if style.markup && pango::parse_markup(text, '\0').is_ok() {
+        layout.set_markup(text);
+    } else {
+        layout.set_text(text);
+    }
+    layout
+}
+
+pub(crate) fn set_argb(cr: &cairo::Context, argb: u32) {
+    let channel = |shift: u32| ((argb >> shift) & 0xff) as f64 / 255.0;
+    cr.set_source_rgba(channel(16), channel(8), channel(0), channel(24));
+}
+
+pub(crate) fn rounded_rect(cr: &cairo::Context, x: f64, y: f64, w: f64, h: f64, radius: f64) {
+    let r = radius.min(w / 2.0).min(h / 2.0).max(0.0);
+    cr.new_sub_path();
+    cr.arc(x + w - r, y + r, r, -PI / 2.0, 0.0);
+    cr.arc(x + w - r, y + h - r, r, 0.0, PI / 2.0);
+    cr.arc(x + r, y + h - r, r, PI / 2.0, PI);
+    cr.arc(x + r, y + r, r, PI, 3.0 * PI / 2.0);
+    cr.close_path();
+}
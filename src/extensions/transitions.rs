@@ -0,0 +1,899 @@
+// src/extensions/transitions.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_controller as gst_controller;
+use gstreamer_controller::prelude::*;
+use anyhow::{anyhow, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::extensions::scene_switcher::SceneSource;
+use crate::extensions::source_transform::WipeStage;
+
+// Raised above every scene while a transition needs one scene on top
+const TRANSITION_ZORDER_OFFSET: u32 = 1000;
+
+/// Curve applied to the transition's progress.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "linear" => Some(Self::Linear),
+            "ease_in" => Some(Self::EaseIn),
+            "ease_out" => Some(Self::EaseOut),
+            "ease_in_out" => Some(Self::EaseInOut),
+            _ => None,
+        }
+    }
+
+    /// Map linear progress (0..1) onto the curve.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => t * (2.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "left" => Some(Self::Left),
+            "right" => Some(Self::Right),
+            "up" => Some(Self::Up),
+            "down" => Some(Self::Down),
+            _ => None,
+        }
+    }
+
+    /// Unit offset of the movement, in canvas widths/heights.
+    fn vector(self) -> (i32, i32) {
+        match self {
+            Self::Left => (-1, 0),
+            Self::Right => (1, 0),
+            Self::Up => (0, -1),
+            Self::Down => (0, 1),
+        }
+    }
+}
+
+/// Interpolated control sources on pad/element properties, filled with one
+/// value per output frame so the compositor renders exact values for each
+/// frame regardless of how busy the UI thread is.
+#[derive(Default)]
+struct Keyframes {
+    // Running time of the frame being scheduled; None applies values immediately
+    time: Cell<Option<gst::ClockTime>>,
+    bindings: RefCell<Vec<(gst::Object, String, gst_controller::InterpolationControlSource, gst::ControlBinding)>>,
+}
+
+impl Keyframes {
+    fn set(&self, object: &gst::Object, property: &str, value: f64) -> bool {
+        let time = match self.time.get() {
+            Some(t) => sync_time(object, t),
+            None => return false,
+        };
+        let mut bindings = self.bindings.borrow_mut();
+        let existing = bindings.iter().position(|(o, p, _, _)| o == object && p == property);
+        let index = match existing {
+            Some(i) => i,
+            None => {
+                let source = gst_controller::InterpolationControlSource::new();
+                source.set_property("mode", gst_controller::InterpolationMode::Linear);
+                let binding = gst_controller::DirectControlBinding::new_absolute(object, property, &source);
+                if object.add_control_binding(&binding).is_err() {
+                    return false;
+                }
+                bindings.push((object.clone(), property.to_string(), source, binding.upcast()));
+                bindings.len() - 1
+            }
+        };
+        bindings[index].2.set(time, value);
+        true
+    }
+
+    fn clear(&self) {
+        for (object, _, _, binding) in self.bindings.borrow_mut().drain(..) {
+            object.remove_control_binding(&binding);
+        }
+    }
+}
+
+/// Controlled properties are synced on stream time, not running time: a
+/// compositor pad on the stream time of the compositor's output segment, an
+/// element such as `smptealpha` on that of its input. Convert a running time
+/// through the segment in question; without one yet, the two are the same.
+fn sync_time(object: &gst::Object, running: gst::ClockTime) -> gst::ClockTime {
+    let segment_pad = match object.downcast_ref::<gst::Pad>() {
+        Some(pad) => pad.parent_element().and_then(|e| e.static_pad("src")),
+        None => object.downcast_ref::<gst::Element>().and_then(|e| e.static_pad("sink")),
+    };
+    let segment = segment_pad
+        .and_then(|pad| pad.sticky_event::<gst::event::Segment>(0))
+        .and_then(|ev| ev.segment().downcast_ref::<gst::ClockTime>().cloned());
+    segment
+        .and_then(|seg| seg.to_stream_time(seg.position_from_running_time(running)?))
+        .unwrap_or(running)
+}
+
+/// Everything a transition may touch: the pads of both scenes and the compositor.
+/// When the transition ends, `finish` puts the pads in their final state, so
+/// transitions only have to care about the frames in between.
+///
+/// Values set from `Transition::update` become keyframes for the frame at the
+/// scheduled running time; values set from `start` apply right away.
+#[derive(Clone)]
+pub struct TransitionContext {
+    pub pipeline: gst::Pipeline,
+    pub compositor: gst::Element,
+    pub canvas: (i32, i32),
+    pub from: Vec<SceneSource>,
+    pub to: Vec<SceneSource>,
+    wipes: HashMap<u32, WipeStage>,
+    keyframes: Rc<Keyframes>,
+}
+
+impl TransitionContext {
+    pub fn new(
+        pipeline: gst::Pipeline,
+        compositor: gst::Element,
+        canvas: (i32, i32),
+        from: Vec<SceneSource>,
+        to: Vec<SceneSource>,
+        wipes: HashMap<u32, WipeStage>,
+    ) -> Self {
+        Self {
+            pipeline,
+            compositor,
+            canvas,
+            from,
+            to,
+            wipes,
+            keyframes: Rc::new(Keyframes::default()),
+        }
+    }
+
+    /// Schedule values for the frame at this running time (None: apply immediately).
+    pub fn set_frame_time(&self, time: Option<gst::ClockTime>) {
+        self.keyframes.time.set(time);
+    }
+
+    /// Drop all keyframes, leaving properties at whatever they were last set to.
+    pub fn clear_keyframes(&self) {
+        self.keyframes.clear();
+    }
+
+    /// Set a numeric property, as a keyframe while scheduling frames.
+    pub fn set_value(&self, object: &impl IsA<gst::Object>, property: &str, value: f64) {
+        let object = object.upcast_ref::<gst::Object>();
+        if self.keyframes.set(object, property, value) {
+            return;
+        }
+        let value_type = match object.find_property(property) {
+            Some(pspec) => pspec.value_type(),
+            None => return,
+        };
+        if value_type == i32::static_type() {
+            object.set_property(property, value.round() as i32);
+        } else if value_type == u32::static_type() {
+            object.set_property(property, value.round().max(0.0) as u32);
+        } else {
+            object.set_property(property, value);
+        }
+    }
+
+    pub fn pad(&self, pad_index: u32) -> Option<gst::Pad> {
+        self.compositor.static_pad(&format!("sink_{}", pad_index))
+    }
+
+    /// Set each source's alpha to its scene alpha times `factor`.
+    pub fn set_alpha(&self, sources: &[SceneSource], factor: f64) {
+        for src in sources {
+            if let Some(pad) = self.pad(src.pad_index) {
+                self.set_value(&pad, "alpha", src.alpha * factor);
+            }
+        }
+    }
+
+    /// Place sources at their scene geometry shifted by (dx, dy).
+    pub fn offset(&self, sources: &[SceneSource], dx: i32, dy: i32) {
+        for src in sources {
+            if let Some(pad) = self.pad(src.pad_index) {
+                self.set_value(&pad, "xpos", (src.x + dx) as f64);
+                self.set_value(&pad, "ypos", (src.y + dy) as f64);
+            }
+        }
+    }
+
+    /// Lift sources above every scene, keeping their order among themselves.
+    pub fn raise(&self, sources: &[SceneSource]) {
+        for src in sources {
+            if let Some(pad) = self.pad(src.pad_index) {
+                pad.set_property("zorder", src.zorder + TRANSITION_ZORDER_OFFSET);
+            }
+        }
+    }
+
+    /// The `smptealpha` in front of a pad, if the source has one.
+    pub fn wipe(&self, pad_index: u32) -> Option<&gst::Element> {
+        self.wipes.get(&pad_index).map(|stage| stage.wipe())
+    }
+
+    /// Like `wipe`, also sending the source's frames through it until `finish`.
+    pub fn start_wipe(&self, pad_index: u32) -> Option<&gst::Element> {
+        let stage = self.wipes.get(&pad_index)?;
+        stage.set_active(true);
+        Some(stage.wipe())
+    }
+
+    /// Show an extra element over both scenes, stretched to the canvas.
+    pub fn add_layer(&self, element: &gst::Element) -> Result<gst::Pad> {
+        self.pipeline.add(element)?;
+        let pad = self
+            .compositor
+            .request_pad_simple("sink_%u")
+            .ok_or_else(|| anyhow!("Compositor refused a new sink pad"))?;
+        pad.set_property("xpos", 0i32);
+        pad.set_property("ypos", 0i32);
+        pad.set_property("width", self.canvas.0);
+        pad.set_property("height", self.canvas.1);
+        pad.set_property("zorder", TRANSITION_ZORDER_OFFSET * 2);
+        pad.set_property("alpha", 0.0f64);
+        element
+            .static_pad("src")
+            .ok_or_else(|| anyhow!("Layer has no src pad"))?
+            .link(&pad)
+            .map_err(|e| anyhow!("Failed to link transition layer: {:?}", e))?;
+        element.sync_state_with_parent()?;
+        Ok(pad)
+    }
+
+    /// Undo `add_layer`.
+    pub fn remove_layer(&self, element: &gst::Element, pad: &gst::Pad) {
+        if let Some(peer) = pad.peer() {
+            let _ = peer.unlink(pad);
+        }
+        self.compositor.release_request_pad(pad);
+        let _ = element.set_state(gst::State::Null);
+        let _ = self.pipeline.remove(element);
+    }
+
+    /// Final state: old scene hidden, new scene at its layout, wipes reset.
+    pub fn finish(&self) {
+        for src in &self.from {
+            if let Some(pad) = self.pad(src.pad_index) {
+                pad.set_property("alpha", 0.0f64);
+                pad.set_property("zorder", src.zorder);
+            }
+        }
+        for src in &self.to {
+            if let Some(pad) = self.pad(src.pad_index) {
+                src.apply_to_pad(&pad, None);
+            }
+        }
+        for stage in self.wipes.values() {
+            stage.wipe().set_property("position", 0.0f64);
+            stage.set_active(false);
+        }
+    }
+}
+
+/// A way of getting from one scene to the next. The scene switcher calls
+/// `start` once, then `update` for every output frame of the transition (all
+/// up front, recorded as keyframes) with the eased progress (0..1). Once the
+/// last frame has played it calls `cleanup` and `TransitionContext::finish`.
+pub trait Transition {
+    fn name(&self) -> &str;
+    /// A fresh copy, so a configured transition can be used for many switches.
+    fn boxed_clone(&self) -> Box<dyn Transition>;
+    fn duration(&self) -> Duration;
+    fn easing(&self) -> Easing;
+    /// Set up extra elements or pad state before the first frame.
+    fn start(&mut self, _ctx: &TransitionContext) -> Result<()> {
+        Ok(())
+    }
+    fn update(&mut self, ctx: &TransitionContext, progress: f64);
+    /// Remove anything `start` added. Also called when a newer transition interrupts this one.
+    fn cleanup(&mut self, _ctx: &TransitionContext) {}
+}
+
+/// Switch instantly.
+#[derive(Clone)]
+pub struct Cut;
+
+impl Transition for Cut {
+    fn boxed_clone(&self) -> Box<dyn Transition> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &str {
+        "Cut"
+    }
+    fn duration(&self) -> Duration {
+        Duration::ZERO
+    }
+    fn easing(&self) -> Easing {
+        Easing::Linear
+    }
+    fn update(&mut self, _ctx: &TransitionContext, _progress: f64) {}
+}
+
+/// Cross-fade the two scenes.
+#[derive(Clone)]
+pub struct Fade {
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl Transition for Fade {
+    fn boxed_clone(&self) -> Box<dyn Transition> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &str {
+        "Fade"
+    }
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+    fn easing(&self) -> Easing {
+        self.easing
+    }
+    fn update(&mut self, ctx: &TransitionContext, progress: f64) {
+        ctx.set_alpha(&ctx.from, 1.0 - progress);
+        ctx.set_alpha(&ctx.to, progress);
+    }
+}
+
+/// Fade to a solid color, switch, and fade back in.
+#[derive(Clone)]
+pub struct FadeThroughColor {
+    pub duration: Duration,
+    pub easing: Easing,
+    /// 0xAARRGGBB
+    pub argb: u32,
+    layer: Option<(gst::Element, gst::Pad)>,
+}
+
+impl FadeThroughColor {
+    pub fn new(duration: Duration, easing: Easing, argb: u32) -> Self {
+        Self {
+            duration,
+            easing,
+            argb,
+            layer: None,
+        }
+    }
+}
+
+impl Transition for FadeThroughColor {
+    fn boxed_clone(&self) -> Box<dyn Transition> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &str {
+        "Fade Through Color"
+    }
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+    fn easing(&self) -> Easing {
+        self.easing
+    }
+    fn start(&mut self, ctx: &TransitionContext) -> Result<()> {
+        let color = gst::ElementFactory::make("videotestsrc", None)?;
+        color.set_property("is-live", true);
+        color.set_property_from_str("pattern", "solid-color");
+        color.set_property("foreground-color", self.argb);
+        let pad = ctx.add_layer(&color)?;
+        self.layer = Some((color, pad));
+        Ok(())
+    }
+    fn update(&mut self, ctx: &TransitionContext, progress: f64) {
+        // First half covers the old scene, second half uncovers the new one
+        let cover = if progress < 0.5 { progress * 2.0 } else { (1.0 - progress) * 2.0 };
+        if let Some((_, pad)) = &self.layer {
+            ctx.set_value(pad, "alpha", cover);
+        }
+        let switched = progress >= 0.5;
+        ctx.set_alpha(&ctx.from, if switched { 0.0 } else { 1.0 });
+        ctx.set_alpha(&ctx.to, if switched { 1.0 } else { 0.0 });
+    }
+    fn cleanup(&mut self, ctx: &TransitionContext) {
+        if let Some((element, pad)) = self.layer.take() {
+            ctx.remove_layer(&element, &pad);
+        }
+    }
+}
+
+/// Push the old scene out while the new one follows it in.
+#[derive(Clone)]
+pub struct Slide {
+    pub duration: Duration,
+    pub easing: Easing,
+    pub direction: Direction,
+}
+
+impl Transition for Slide {
+    fn boxed_clone(&self) -> Box<dyn Transition> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &str {
+        "Slide"
+    }
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+    fn easing(&self) -> Easing {
+        self.easing
+    }
+    fn start(&mut self, ctx: &TransitionContext) -> Result<()> {
+        ctx.set_alpha(&ctx.to, 1.0);
+        self.update(ctx, 0.0);
+        Ok(())
+    }
+    fn update(&mut self, ctx: &TransitionContext, progress: f64) {
+        let (out, inc) = slide_offsets(self.direction, ctx.canvas, progress);
+        ctx.offset(&ctx.from, out.0, out.1);
+        ctx.offset(&ctx.to, inc.0, inc.1);
+    }
+}
+
+// Internal: how far a slide has moved the old and the new scene at `progress`
+fn slide_offsets(direction: Direction, canvas: (i32, i32), progress: f64) -> ((i32, i32), (i32, i32)) {
+    let (vx, vy) = direction.vector();
+    let out = (
+        (vx as f64 * canvas.0 as f64 * progress) as i32,
+        (vy as f64 * canvas.1 as f64 * progress) as i32,
+    );
+    (out, swipe_offset(direction, canvas, progress))
+}
+
+// Internal: how far the incoming scene still is from its place at `progress`
+fn swipe_offset(direction: Direction, canvas: (i32, i32), progress: f64) -> (i32, i32) {
+    let (vx, vy) = direction.vector();
+    let rest = 1.0 - progress;
+    (
+        -(vx as f64 * canvas.0 as f64 * rest) as i32,
+        -(vy as f64 * canvas.1 as f64 * rest) as i32,
+    )
+}
+
+/// Move the new scene in over the old one, which stays put.
+#[derive(Clone)]
+pub struct Swipe {
+    pub duration: Duration,
+    pub easing: Easing,
+    pub direction: Direction,
+}
+
+impl Transition for Swipe {
+    fn boxed_clone(&self) -> Box<dyn Transition> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &str {
+        "Swipe"
+    }
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+    fn easing(&self) -> Easing {
+        self.easing
+    }
+    fn start(&mut self, ctx: &TransitionContext) -> Result<()> {
+        ctx.raise(&ctx.to);
+        ctx.set_alpha(&ctx.to, 1.0);
+        self.update(ctx, 0.0);
+        Ok(())
+    }
+    fn update(&mut self, ctx: &TransitionContext, progress: f64) {
+        let (dx, dy) = swipe_offset(self.direction, ctx.canvas, progress);
+        ctx.offset(&ctx.to, dx, dy);
+    }
+}
+
+/// SMPTE wipe: the old scene is masked away with a `smptealpha` pattern,
+/// revealing the new scene underneath. A soft border gives a luma-style wipe.
+#[derive(Clone)]
+pub struct Wipe {
+    pub duration: Duration,
+    pub easing: Easing,
+    /// smptealpha "type" nick, e.g. "bar-w1", "iris-rect", "clock-cw12"
+    pub pattern: String,
+    /// Width of the soft edge, 0 for a hard edge
+    pub border: i32,
+    pub invert: bool,
+}
+
+impl Transition for Wipe {
+    fn boxed_clone(&self) -> Box<dyn Transition> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &str {
+        "Wipe"
+    }
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+    fn easing(&self) -> Easing {
+        self.easing
+    }
+    fn start(&mut self, ctx: &TransitionContext) -> Result<()> {
+        for src in &ctx.from {
+            let wipe = ctx
+                .start_wipe(src.pad_index)
+                .ok_or_else(|| anyhow!("Source on pad {} cannot be wiped", src.pad_index))?;
+            wipe.set_property_from_str("type", &self.pattern);
+            wipe.set_property("border", self.border);
+            wipe.set_property("invert", self.invert);
+            wipe.set_property("position", 0.0f64);
+        }
+        ctx.raise(&ctx.from);
+        ctx.set_alpha(&ctx.to, 1.0);
+        Ok(())
+    }
+    fn update(&mut self, ctx: &TransitionContext, progress: f64) {
+        // position 0 keeps the frame opaque, 1 makes it fully transparent
+        for src in &ctx.from {
+            if let Some(wipe) = ctx.wipe(src.pad_index) {
+                ctx.set_value(wipe, "position", progress);
+            }
+        }
+    }
+}
+
+/// Play a video with alpha (e.g. VP9 WebM) over both scenes and cut at
+/// `switch_point`, while the stinger covers the screen.
+#[derive(Clone)]
+pub struct Stinger {
+    pub path: String,
+    pub duration: Duration,
+    pub easing: Easing,
+    /// Time into the stinger at which the scenes are swapped
+    pub switch_point: Duration,
+    layer: Option<(gst::Element, gst::Pad)>,
+}
+
+impl Stinger {
+    pub fn new(path: &str, duration: Duration, switch_point: Duration) -> Self {
+        Self {
+            path: path.to_string(),
+            duration,
+            easing: Easing::Linear,
+            switch_point,
+            layer: None,
+        }
+    }
+}
+
+impl Transition for Stinger {
+    fn boxed_clone(&self) -> Box<dyn Transition> {
+        Box::new(self.clone())
+    }
+    fn name(&self) -> &str {
+        "Stinger"
+    }
+    fn duration(&self) -> Duration {
+        self.duration
+    }
+    fn easing(&self) -> Easing {
+        self.easing
+    }
+    fn start(&mut self, ctx: &TransitionContext) -> Result<()> {
+        let uri = if self.path.contains("://") {
+            self.path.clone()
+        } else {
+            glib::filename_to_uri(&self.path, None)
+                .map_err(|e| anyhow!("Invalid stinger path {}: {}", self.path, e))?
+                .to_string()
+        };
+        let bin = gst::parse_bin_from_description(
+            &format!("uridecodebin uri=\"{}\" ! videoconvert ! video/x-raw,format=BGRA", uri),
+            true,
+        )?;
+        let element: gst::Element = bin.upcast();
+        let pad = ctx.add_layer(&element)?;
+        // The file starts at 0; without an offset its frames are late in the live pipeline
+        let running = element.current_running_time().unwrap_or(gst::ClockTime::ZERO);
+        for src in element.src_pads() {
+            src.set_offset(running.nseconds() as i64);
+        }
+        pad.set_property("alpha", 1.0f64);
+        self.layer = Some((element, pad));
+        Ok(())
+    }
+    fn update(&mut self, ctx: &TransitionContext, progress: f64) {
+        let switch = if self.duration.is_zero() {
+            0.0
+        } else {
+            self.switch_point.as_secs_f64() / self.duration.as_secs_f64()
+        };
+        let switched = progress >= switch;
+        ctx.set_alpha(&ctx.from, if switched { 0.0 } else { 1.0 });
+        ctx.set_alpha(&ctx.to, if switched { 1.0 } else { 0.0 });
+    }
+    fn cleanup(&mut self, ctx: &TransitionContext) {
+        if let Some((element, pad)) = self.layer.take() {
+            ctx.remove_layer(&element, &pad);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::source_transform::SourceTransform;
+    use gstreamer_app as gst_app;
+    use std::path::Path;
+
+    const SIZE: usize = 64;
+
+    const EASINGS: [Easing; 4] = [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut];
+
+    #[test]
+    fn easing_keeps_the_endpoints() {
+        for easing in EASINGS {
+            assert_eq!(easing.apply(0.0), 0.0, "{:?}", easing);
+            assert_eq!(easing.apply(1.0), 1.0, "{:?}", easing);
+            // Out of range progress is clamped
+            assert_eq!(easing.apply(-0.5), 0.0, "{:?}", easing);
+            assert_eq!(easing.apply(1.5), 1.0, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn easing_is_monotonic() {
+        for easing in EASINGS {
+            let values: Vec<f64> = (0..=100).map(|i| easing.apply(i as f64 / 100.0)).collect();
+            assert!(values.windows(2).all(|w| w[1] >= w[0]), "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn easing_shapes() {
+        assert_eq!(Easing::Linear.apply(0.25), 0.25);
+        assert!(Easing::EaseIn.apply(0.25) < 0.25);
+        assert!(Easing::EaseOut.apply(0.25) > 0.25);
+        assert_eq!(Easing::EaseInOut.apply(0.5), 0.5);
+        assert_eq!(Easing::from_id("ease_in_out"), Some(Easing::EaseInOut));
+        assert_eq!(Easing::from_id("bounce"), None);
+    }
+
+    #[test]
+    fn slide_moves_both_scenes_by_the_canvas() {
+        let canvas = (1280, 720);
+        assert_eq!(slide_offsets(Direction::Right, canvas, 0.0), ((0, 0), (-1280, 0)));
+        assert_eq!(slide_offsets(Direction::Right, canvas, 0.25), ((320, 0), (-960, 0)));
+        assert_eq!(slide_offsets(Direction::Right, canvas, 1.0), ((1280, 0), (0, 0)));
+        assert_eq!(slide_offsets(Direction::Up, canvas, 0.5), ((0, -360), (0, 360)));
+        assert_eq!(slide_offsets(Direction::Left, canvas, 1.0), ((-1280, 0), (0, 0)));
+        assert_eq!(slide_offsets(Direction::Down, canvas, 0.0), ((0, 0), (0, -720)));
+    }
+
+    #[test]
+    fn swipe_brings_the_new_scene_in_from_the_opposite_side() {
+        let canvas = (1280, 720);
+        assert_eq!(swipe_offset(Direction::Left, canvas, 0.0), (1280, 0));
+        assert_eq!(swipe_offset(Direction::Left, canvas, 0.75), (320, 0));
+        assert_eq!(swipe_offset(Direction::Down, canvas, 0.5), (0, -360));
+        for direction in [Direction::Left, Direction::Right, Direction::Up, Direction::Down] {
+            assert_eq!(swipe_offset(direction, canvas, 1.0), (0, 0));
+        }
+    }
+
+    // Internal: a segment whose stream time runs 2 s ahead of running time:
+    // running 4 s is position 1 s, which is stream time 6 s
+    fn store_offset_segment(pad: &gst::Pad) {
+        let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+        segment.set_base(gst::ClockTime::from_seconds(3));
+        segment.set_time(gst::ClockTime::from_seconds(5));
+        // Inactive pads are flushing and refuse sticky events
+        pad.set_active(true).unwrap();
+        pad.store_sticky_event(&gst::event::StreamStart::new("test")).unwrap();
+        pad.store_sticky_event(&gst::event::Segment::new(&segment)).unwrap();
+    }
+
+    #[test]
+    fn sync_time_is_running_time_without_a_segment() {
+        gst::init().unwrap();
+        let element = gst::ElementFactory::make("identity", None).unwrap();
+        let running = gst::ClockTime::from_seconds(4);
+        assert_eq!(sync_time(element.upcast_ref(), running), running);
+    }
+
+    #[test]
+    fn sync_time_follows_the_segment_elements_sync_on() {
+        gst::init().unwrap();
+        let running = gst::ClockTime::from_seconds(4);
+        // Elements sync on their input segment
+        let element = gst::ElementFactory::make("identity", None).unwrap();
+        store_offset_segment(&element.static_pad("sink").unwrap());
+        assert_eq!(sync_time(element.upcast_ref(), running), gst::ClockTime::from_seconds(6));
+
+        // Pads on the output segment of their element, like compositor sink pads
+        let element = gst::ElementFactory::make("identity", None).unwrap();
+        store_offset_segment(&element.static_pad("src").unwrap());
+        let pad = element.static_pad("sink").unwrap();
+        assert_eq!(sync_time(pad.upcast_ref(), running), gst::ClockTime::from_seconds(6));
+    }
+
+    #[test]
+    fn keyframes_are_set_at_the_stream_time_of_the_frame() {
+        gst::init().unwrap();
+        let element = gst::ElementFactory::make("smptealpha", None).unwrap();
+        store_offset_segment(&element.static_pad("sink").unwrap());
+
+        let keyframes = Keyframes::default();
+        // Without a frame time nothing is scheduled
+        assert!(!keyframes.set(element.upcast_ref(), "position", 0.9));
+        keyframes.time.set(Some(gst::ClockTime::from_seconds(4)));
+        assert!(keyframes.set(element.upcast_ref(), "position", 0.25));
+        keyframes.time.set(Some(gst::ClockTime::from_seconds(5)));
+        assert!(keyframes.set(element.upcast_ref(), "position", 0.75));
+
+        // Running 4 s and 5 s are stream time 6 s and 7 s
+        assert_eq!(value_at(&element, "position", 6000), 0.25);
+        assert_eq!(value_at(&element, "position", 7000), 0.75);
+        // One binding per property, however many keyframes
+        assert_eq!(keyframes.bindings.borrow().len(), 1);
+
+        keyframes.clear();
+        assert!(keyframes.bindings.borrow().is_empty());
+        assert!(element.control_binding("position").is_none());
+    }
+
+    fn scene_source(pad_index: u32) -> SceneSource {
+        SceneSource {
+            source_id: "source".to_string(),
+            pad_index,
+            x: 0,
+            y: 0,
+            width: SIZE as i32,
+            height: SIZE as i32,
+            alpha: 1.0,
+            zorder: 0,
+            effects: None,
+            group: None,
+            local: None,
+            transform: SourceTransform::default(),
+        }
+    }
+
+    fn value_at(object: &gst::Element, property: &str, ms: u64) -> f64 {
+        object
+            .value(property, gst::ClockTime::from_mseconds(ms))
+            .and_then(|v| v.get::<f64>().ok())
+            .unwrap()
+    }
+
+    #[test]
+    fn wipe_routes_through_smptealpha_and_keys_its_position() {
+        gst::init().unwrap();
+        let pipeline = gst::Pipeline::new(Some("wipe_test"));
+        let compositor = gst::ElementFactory::make("compositor", None).unwrap();
+        pipeline.add(&compositor).unwrap();
+        let stage = WipeStage::new("wipe_test_stage").unwrap();
+        let ctx = TransitionContext::new(
+            pipeline,
+            compositor,
+            (SIZE as i32, SIZE as i32),
+            vec![scene_source(0)],
+            Vec::new(),
+            HashMap::from([(0, stage.clone())]),
+        );
+        let mut wipe = Wipe {
+            duration: Duration::from_secs(1),
+            easing: Easing::Linear,
+            pattern: "bar-w1".to_string(),
+            border: 0,
+            invert: false,
+        };
+        wipe.start(&ctx).unwrap();
+        ctx.set_frame_time(Some(gst::ClockTime::from_seconds(1)));
+        wipe.update(&ctx, 0.25);
+        ctx.set_frame_time(Some(gst::ClockTime::from_seconds(2)));
+        wipe.update(&ctx, 0.75);
+        ctx.set_frame_time(None);
+
+        assert_eq!(value_at(stage.wipe(), "position", 1000), 0.25);
+        assert!((value_at(stage.wipe(), "position", 1500) - 0.5).abs() < 1e-9);
+        assert_eq!(value_at(stage.wipe(), "position", 2000), 0.75);
+
+        ctx.clear_keyframes();
+        ctx.finish();
+        assert_eq!(stage.wipe().property::<f64>("position"), 0.0);
+    }
+
+    // Internal: run a launch line to the end
+    fn run_to_eos(description: &str) {
+        let pipeline = gst::parse_launch(description).unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        let msg = pipeline
+            .bus()
+            .unwrap()
+            .timed_pop_filtered(gst::ClockTime::from_seconds(10), &[gst::MessageType::Eos, gst::MessageType::Error])
+            .expect("pipeline did not finish");
+        pipeline.set_state(gst::State::Null).unwrap();
+        if let gst::MessageView::Error(err) = msg.view() {
+            panic!("pipeline failed: {}", err.error());
+        }
+    }
+
+    // Internal: a two second red clip for the stinger
+    fn write_red_clip(path: &Path) {
+        run_to_eos(&format!(
+            "videotestsrc num-buffers=60 pattern=red ! video/x-raw,format=I420,width={size},height={size},framerate=30/1 \
+             ! matroskamux ! filesink location=\"{}\"",
+            path.display(),
+            size = SIZE
+        ));
+    }
+
+    fn is_red(sample: &gst::Sample) -> bool {
+        let buffer = sample.buffer().unwrap();
+        let map = buffer.map_readable().unwrap();
+        let at = (SIZE / 2 * SIZE + SIZE / 2) * 4;
+        // BGRA
+        let (b, g, r) = (map[at], map[at + 1], map[at + 2]);
+        r > 200 && g < 60 && b < 60
+    }
+
+    #[test]
+    fn stinger_shows_on_a_pipeline_that_has_been_playing() {
+        gst::init().unwrap();
+        let clip = std::env::temp_dir().join(format!("waycord_stinger_{}.mkv", std::process::id()));
+        write_red_clip(&clip);
+
+        let caps = format!("video/x-raw,format=BGRA,width={size},height={size},framerate=30/1", size = SIZE);
+        let pipeline = gst::parse_launch(&format!(
+            "videotestsrc is-live=true pattern=black ! {caps} ! compositor name=comp background=black \
+             ! {caps} ! appsink name=out",
+            caps = caps
+        ))
+        .unwrap()
+        .downcast::<gst::Pipeline>()
+        .unwrap();
+        let compositor = pipeline.by_name("comp").unwrap();
+        let sink = pipeline.by_name("out").unwrap().downcast::<gst_app::AppSink>().unwrap();
+        pipeline.set_state(gst::State::Playing).unwrap();
+        // A second and a half of running time the stinger has to catch up with
+        for _ in 0..45 {
+            assert!(!is_red(&sink.pull_sample().unwrap()));
+        }
+
+        let ctx = TransitionContext::new(
+            pipeline.clone(),
+            compositor,
+            (SIZE as i32, SIZE as i32),
+            Vec::new(),
+            Vec::new(),
+            HashMap::new(),
+        );
+        let mut stinger = Stinger::new(clip.to_str().unwrap(), Duration::from_secs(2), Duration::from_secs(1));
+        stinger.start(&ctx).unwrap();
+        let shown = (0..60).any(|_| is_red(&sink.pull_sample().unwrap()));
+
+        stinger.cleanup(&ctx);
+        pipeline.set_state(gst::State::Null).unwrap();
+        std::fs::remove_file(&clip).unwrap();
+        assert!(shown, "the stinger never reached the output");
+    }
+}
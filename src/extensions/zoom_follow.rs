@@ -0,0 +1,216 @@
+// src/extensions/zoom_follow.rs
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use glib::MainContext;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::devices::cursor::SharedCursorState;
+
+// ~60 updates per second
+const TICK: Duration = Duration::from_millis(16);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomFollowOptions {
+    /// Magnification while zoomed in (2.0 = twice as large)
+    pub zoom: f64,
+    /// Fraction of the remaining distance covered each tick (0..1, higher = snappier)
+    pub easing: f64,
+    /// Zoom in automatically on clicks
+    pub follow_clicks: bool,
+    /// How long to stay zoomed after the last click
+    pub click_hold: Duration,
+}
+
+impl Default for ZoomFollowOptions {
+    fn default() -> Self {
+        Self {
+            zoom: 2.0,
+            easing: 0.12,
+            follow_clicks: false,
+            click_hold: Duration::from_secs(2),
+        }
+    }
+}
+
+/// A compositor pad showing a screen, its un-zoomed geometry and that
+/// screen's cursor.
+struct ZoomTarget {
+    pad: glib::WeakRef<gst::Pad>,
+    base: (i32, i32, i32, i32),
+    cursor: SharedCursorState,
+}
+
+struct ZoomState {
+    options: ZoomFollowOptions,
+    /// Zoom toggled on by the hotkey
+    manual: bool,
+    current_zoom: f64,
+    /// Focus point as a fraction of the source (0..1), eased towards the cursor
+    focus: (f64, f64),
+    last_click: Option<Instant>,
+    targets: Vec<ZoomTarget>,
+    /// The tick loop is running
+    ticking: bool,
+}
+
+/// Crops and scales into the area around the cursor by enlarging the screen's
+/// compositor pad and shifting it, so the output keeps its resolution. Every
+/// registered pad gets the same zoom, following its own screen's cursor.
+/// Ticks only run while zoomed or waiting for clicks.
+pub struct ZoomFollow {
+    state: Arc<Mutex<ZoomState>>,
+    main_ctx: MainContext,
+}
+
+impl ZoomFollow {
+    pub fn new(main_ctx: &MainContext) -> Self {
+        let state = Arc::new(Mutex::new(ZoomState {
+            options: ZoomFollowOptions::default(),
+            manual: false,
+            current_zoom: 1.0,
+            focus: (0.5, 0.5),
+            last_click: None,
+            targets: Vec::new(),
+            ticking: false,
+        }));
+        Self {
+            state,
+            main_ctx: main_ctx.clone(),
+        }
+    }
+
+    /// Zoom a compositor pad; `base` is the pad's normal (x, y, width, height)
+    /// and `cursor` the cursor of the screen it shows.
+    pub fn add_target(&self, pad: &gst::Pad, base: (i32, i32, i32, i32), cursor: SharedCursorState) {
+        let mut st = self.state.lock().unwrap();
+        st.targets.retain(|t| t.pad.upgrade().is_some());
+        st.targets.push(ZoomTarget {
+            pad: pad.downgrade(),
+            base,
+            cursor,
+        });
+    }
+
+    /// Update the un-zoomed geometry of a pad, e.g. after the scene layout changed.
+    pub fn set_base_geometry(&self, pad: &gst::Pad, base: (i32, i32, i32, i32)) {
+        let mut st = self.state.lock().unwrap();
+        for target in st.targets.iter_mut() {
+            if target.pad.upgrade().as_ref() == Some(pad) {
+                target.base = base;
+            }
+        }
+    }
+
+    /// Hotkey handler: zoom in or back out.
+    pub fn toggle(&self) {
+        {
+            let mut st = self.state.lock().unwrap();
+            st.manual = !st.manual;
+        }
+        self.wake();
+    }
+
+    pub fn set_options(&self, options: ZoomFollowOptions) {
+        self.state.lock().unwrap().options = options;
+        self.wake();
+    }
+
+    pub fn options(&self) -> ZoomFollowOptions {
+        self.state.lock().unwrap().options
+    }
+
+    // Start the tick loop unless it runs already; it ends once `tick` says so
+    fn wake(&self) {
+        let mut st = self.state.lock().unwrap();
+        if st.ticking {
+            return;
+        }
+        st.ticking = true;
+        drop(st);
+        let state = self.state.clone();
+        self.main_ctx.spawn_local(async move {
+            while tick(&state) {
+                glib::timeout_future(TICK).await;
+            }
+        });
+    }
+}
+
+// One animation step. Returns false (and marks the loop stopped) once fully
+// zoomed out with nothing that could zoom in again.
+fn tick(state: &Arc<Mutex<ZoomState>>) -> bool {
+    let mut st = state.lock().unwrap();
+    st.targets.retain(|t| t.pad.upgrade().is_some());
+    let newest_click = st
+        .targets
+        .iter()
+        .filter_map(|t| t.cursor.lock().unwrap().clicks.last().map(|click| click.at))
+        .max();
+    let opts = st.options;
+    if opts.follow_clicks && newest_click.is_some() && newest_click != st.last_click {
+        st.last_click = newest_click;
+    }
+    let click_zoom = opts.follow_clicks
+        && st.last_click.map(|at| at.elapsed() < opts.click_hold).unwrap_or(false);
+
+    let target_zoom = if st.manual || click_zoom { opts.zoom.max(1.0) } else { 1.0 };
+    let ease = opts.easing.clamp(0.01, 1.0);
+    let was_idle = (st.current_zoom - 1.0).abs() < 0.001;
+    st.current_zoom += (target_zoom - st.current_zoom) * ease;
+    if (st.current_zoom - target_zoom).abs() < 0.001 {
+        st.current_zoom = target_zoom;
+    }
+    // Leave the pads alone while fully zoomed out, so scene changes are not overridden
+    if was_idle && target_zoom == 1.0 {
+        st.ticking = opts.follow_clicks;
+        return st.ticking;
+    }
+
+    let zoom = st.current_zoom;
+    let mut focus = st.focus;
+    for target in &st.targets {
+        let pad = match target.pad.upgrade() {
+            Some(p) => p,
+            None => continue,
+        };
+        let cursor_pos = {
+            let c = target.cursor.lock().unwrap();
+            if c.visible { Some((c.x, c.y)) } else { None }
+        };
+        // Cursor coordinates are in source pixels; normalise with the pad's input size
+        if let (Some((cx, cy)), Some((src_w, src_h))) = (cursor_pos, pad_input_size(&pad)) {
+            let goal = ((cx / src_w).clamp(0.0, 1.0), (cy / src_h).clamp(0.0, 1.0));
+            focus = (
+                st.focus.0 + (goal.0 - st.focus.0) * ease,
+                st.focus.1 + (goal.1 - st.focus.1) * ease,
+            );
+        }
+        let (x, y, w, h) = zoomed_geometry(target.base, zoom, focus);
+        pad.set_property("xpos", x);
+        pad.set_property("ypos", y);
+        pad.set_property("width", w);
+        pad.set_property("height", h);
+    }
+    st.focus = focus;
+    true
+}
+
+/// Scale `base` by `zoom` so the focus point lands in the middle of the base
+/// rectangle, clamped so the enlarged source always covers the base area.
+fn zoomed_geometry(base: (i32, i32, i32, i32), zoom: f64, focus: (f64, f64)) -> (i32, i32, i32, i32) {
+    let (bx, by, bw, bh) = (base.0 as f64, base.1 as f64, base.2 as f64, base.3 as f64);
+    let (w, h) = (bw * zoom, bh * zoom);
+    let x = (bx + bw / 2.0 - focus.0 * w).clamp(bx + bw - w, bx);
+    let y = (by + bh / 2.0 - focus.1 * h).clamp(by + bh - h, by);
+    (x.round() as i32, y.round() as i32, w.round() as i32, h.round() as i32)
+}
+
+fn pad_input_size(pad: &gst::Pad) -> Option<(f64, f64)> {
+    let caps = pad.current_caps()?;
+    let s = caps.structure(0)?;
+    let w = s.get::<i32>("width").ok()?;
+    let h = s.get::<i32>("height").ok()?;
+    Some((w as f64, h as f64))
+}
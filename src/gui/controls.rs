@@ -8,21 +8,28 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::core::encoder::{
-    start_recording_with_audio_sources,
+    set_program_audio_inputs,
+    start_recording,
     stop_recording,
     pause_recording,
     resume_recording,
+    monitor_audio_levels,
     VOLUME_DATA,
 };
 use crate::devices::audio::get_audio_sources;
+use crate::devices::cursor::{set_click_capture, ClickUser};
+use crate::devices::screen::CursorMode;
+use crate::extensions::cursor_overlay::{set_program_cursor_options, CursorOptions};
 
 pub fn build_controls() -> GtkBox {
     let vbox = GtkBox::new(Orientation::Vertical, 10);
 
-    // Audio sources + VU meters
+    // Audio sources + VU meters; checked sources are mixed into the program
+    // audio, which recordings and streams carry
     vbox.append(&Label::new(Some("Audio Sources:")));
     let audio_sources = get_audio_sources();
     let mut meters = Vec::new();
+    let selected_audio: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
 
     for (_id, name, desc) in audio_sources {
         let row = GtkBox::new(Orientation::Horizontal, 5);
@@ -34,12 +41,86 @@ pub fn build_controls() -> GtkBox {
         row.append(&meter);
         vbox.append(&row);
 
-        // In real usage, you'd track which checkboxes are active. We'll keep it simple.
+        check.connect_toggled(clone!(@strong selected_audio, @strong name => move |check| {
+            let mut selected = selected_audio.borrow_mut();
+            selected.retain(|s| s != &name);
+            if check.is_active() {
+                selected.push(name.clone());
+            }
+            if let Err(e) = set_program_audio_inputs(&selected) {
+                eprintln!("Audio input error: {:?}", e);
+            }
+        }));
         meters.push((name.clone(), meter));
     }
 
     vbox.append(&Separator::new(Orientation::Horizontal));
 
+    // Cursor: hidden, embedded by the compositor, or composited by us from metadata
+    vbox.append(&Label::new(Some("Cursor:")));
+    let cursor_box = ComboBoxText::new();
+    cursor_box.append(Some("embedded"), "Show cursor");
+    cursor_box.append(Some("hidden"), "Hide cursor");
+    cursor_box.append(Some("metadata"), "Composited (highlight, magnify, smooth)");
+    cursor_box.set_active(Some(0));
+    let highlight_check = CheckButton::with_label("Highlight clicks");
+    let magnify_spin = SpinButton::new(
+        Some(&Adjustment::new(1.0, 0.5, 4.0, 0.25, 0.5, 0.0)),
+        0.25,
+        2
+    );
+    let smoothing_spin = SpinButton::new(
+        Some(&Adjustment::new(0.0, 0.0, 0.95, 0.05, 0.1, 0.0)),
+        0.05,
+        2
+    );
+    let cursor_row = GtkBox::new(Orientation::Horizontal, 5);
+    cursor_row.append(&cursor_box);
+    cursor_row.append(&highlight_check);
+    cursor_row.append(&Label::new(Some("Magnify:")));
+    cursor_row.append(&magnify_spin);
+    cursor_row.append(&Label::new(Some("Smoothing:")));
+    cursor_row.append(&smoothing_spin);
+    vbox.append(&cursor_row);
+
+    // The extra options only apply when we draw the cursor ourselves
+    let set_cursor_extras = clone!(@weak highlight_check, @weak magnify_spin, @weak smoothing_spin => move |combo: &ComboBoxText| {
+        let composited = combo.active_id().as_deref() == Some("metadata");
+        highlight_check.set_sensitive(composited);
+        magnify_spin.set_sensitive(composited);
+        smoothing_spin.set_sensitive(composited);
+    });
+    set_cursor_extras(&cursor_box);
+    cursor_box.connect_changed(set_cursor_extras);
+
+    let cursor_options = Rc::new(clone!(@weak cursor_box, @weak highlight_check, @weak magnify_spin, @weak smoothing_spin
+        => @default-return CursorOptions::default(), move || {
+        CursorOptions {
+            mode: cursor_box
+                .active_id()
+                .and_then(|id| CursorMode::from_id(&id))
+                .unwrap_or(CursorMode::Embedded),
+            highlight_clicks: highlight_check.is_active(),
+            magnification: magnify_spin.value(),
+            smoothing: smoothing_spin.value(),
+        }
+    }));
+
+    // The program's screen sources draw the cursor live with these options.
+    // Mouse buttons are only read from the input devices while highlighting is on.
+    let sync_cursor = clone!(@strong cursor_options => move || {
+        let options = cursor_options();
+        set_click_capture(ClickUser::Highlight, options.mode == CursorMode::Metadata && options.highlight_clicks);
+        set_program_cursor_options(options);
+    });
+    sync_cursor();
+    highlight_check.connect_toggled(clone!(@strong sync_cursor => move |_| sync_cursor()));
+    magnify_spin.connect_value_changed(clone!(@strong sync_cursor => move |_| sync_cursor()));
+    smoothing_spin.connect_value_changed(clone!(@strong sync_cursor => move |_| sync_cursor()));
+    cursor_box.connect_changed(move |_| sync_cursor());
+
+    vbox.append(&Separator::new(Orientation::Horizontal));
+
     // Output Settings
     vbox.append(&Label::new(Some("Output Settings:")));
 
@@ -118,16 +199,18 @@ pub fn build_controls() -> GtkBox {
             let fps = fps_spin.value_as_int() as u32;
             let bitrate = bitrate_spin.value_as_int() as u32;
 
-            // For demonstration, we won't parse audio checkboxes here
-            let selected_sources = Vec::new();
-            start_recording_with_audio_sources(
-                selected_sources,
+            // The recording shows the program: its scenes, cameras, cursor and overlays,
+            // and plays the program audio
+            if let Err(e) = start_recording(
                 format,
                 filename,
                 Some((width, height)),
                 Some(fps),
                 Some(bitrate),
-            );
+            ) {
+                eprintln!("Recording error: {:?}", e);
+                return;
+            }
 
             *is_recording.borrow_mut() = true;
             stop_btn.set_sensitive(true);
@@ -159,13 +242,18 @@ pub fn build_controls() -> GtkBox {
     btn_box.append(&pause_btn);
     vbox.append(&btn_box);
 
-    // Update VU meters from VOLUME_DATA
+    // Update VU meters from VOLUME_DATA; the monitor lives as long as the meters do
+    let names: Vec<String> = meters.iter().map(|(name, _)| name.clone()).collect();
+    let monitor = monitor_audio_levels(&names)
+        .map_err(|e| eprintln!("Audio levels unavailable: {:?}", e))
+        .ok();
     timeout_add_seconds_local(1, clone!(@strong meters => move || {
+        let _ = &monitor;
         let volumes = VOLUME_DATA.lock().unwrap();
         for (name, meter) in &meters {
-            if let Some(vol) = volumes.get(name) {
-                meter.set_fraction(*vol.min(&1.0));
-            }
+            // Peaks are in dBFS
+            let fraction = volumes.get(name).map(|db| 10f64.powf(db / 20.0)).unwrap_or(0.0);
+            meter.set_fraction(fraction.clamp(0.0, 1.0));
         }
         Continue(true)
     }));
@@ -2,3 +2,4 @@ pub mod window;
 pub mod controls;
 pub mod preview;
 pub mod settings_dialog;
+pub mod scene_editor;
@@ -1,8 +1,14 @@
-use gtk4::{Box as GtkBox, Orientation};
+use gtk4::{Box as GtkBox, Orientation, Overlay};
 use gtk4::prelude::*;
 use gstreamer as gst;
 use gstreamer::prelude::*;
+use anyhow::{anyhow, Result};
 
+use crate::core::encoder::get_global_pipeline;
+use crate::gui::scene_editor::attach_scene_editor;
+
+/// The program output with the scene editor on top. Falls back to a plain
+/// screen preview if the main pipeline could not be built.
 pub fn build_preview() -> GtkBox {
     gst::init().unwrap();
 
@@ -10,17 +16,63 @@ pub fn build_preview() -> GtkBox {
     let preview_box = GtkBox::new(Orientation::Vertical, 0);
     container.append(&preview_box);
 
+    let sink_widget = match get_global_pipeline().map(program_monitor) {
+        Some(Ok(widget)) => {
+            let overlay = Overlay::new();
+            overlay.set_child(Some(&widget));
+            attach_scene_editor(&overlay);
+            Some(overlay.upcast::<gtk4::Widget>())
+        }
+        Some(Err(e)) => {
+            eprintln!("Program preview unavailable ({:?}), showing the screen only.", e);
+            screen_preview()
+        }
+        None => screen_preview(),
+    };
+    if let Some(sink_widget) = sink_widget {
+        preview_box.append(&sink_widget);
+        sink_widget.set_size_request(1280, 720);
+    }
+
+    container
+}
+
+// Internal: gtksink on a branch of the program tee; starts the main pipeline
+fn program_monitor(pipeline: &gst::Pipeline) -> Result<gtk4::Widget> {
+    let program_tee = pipeline
+        .by_name("program_tee")
+        .ok_or_else(|| anyhow!("No program tee in the pipeline"))?;
+    let queue = gst::ElementFactory::make("queue", Some("preview_queue"))?;
+    // The preview must never hold up the program output
+    queue.set_property_from_str("leaky", "downstream");
+    queue.set_property("max-size-buffers", 2u32);
+    let convert = gst::ElementFactory::make("videoconvert", None)?;
+    let sink = gst::ElementFactory::make("gtksink", Some("preview_sink_main"))?;
+    pipeline.add_many(&[&queue, &convert, &sink])?;
+    gst::Element::link_many(&[&queue, &convert, &sink])?;
+
+    let tee_pad = program_tee
+        .request_pad_simple("src_%u")
+        .ok_or_else(|| anyhow!("Program tee refused a new src pad"))?;
+    tee_pad
+        .link(&queue.static_pad("sink").ok_or_else(|| anyhow!("Queue has no sink pad"))?)
+        .map_err(|e| anyhow!("Failed to link preview: {:?}", e))?;
+
+    let widget = sink
+        .property::<Option<gtk4::Widget>>("widget")
+        .ok_or_else(|| anyhow!("gtksink has no widget"))?;
+    pipeline.set_state(gst::State::Playing)?;
+    Ok(widget)
+}
+
+// Internal: the old standalone screen preview
+fn screen_preview() -> Option<gtk4::Widget> {
     let pipeline_str = "pipewiresrc ! videoconvert ! queue ! gtksink name=sink";
     let pipeline = gst::parse_launch(pipeline_str).expect("Failed to create preview pipeline");
     let pipeline = pipeline.downcast::<gst::Pipeline>().unwrap();
 
     let sink = pipeline.by_name("sink").expect("Failed to find gtksink");
-    if let Ok(Some(sink_widget)) = sink.property::<Option<gtk4::Widget>>("widget") {
-        preview_box.append(&sink_widget);
-        sink_widget.set_size_request(1280, 720);
-    }
-
+    let widget = sink.property::<Option<gtk4::Widget>>("widget");
     pipeline.set_state(gst::State::Playing).unwrap();
-
-    container
+    widget
 }
@@ -0,0 +1,442 @@
+// src/gui/scene_editor.rs
+
+use gtk4 as gtk;
+use gtk::prelude::*;
+use glib::clone;
+use gtk::{cairo, gdk, DrawingArea, GestureDrag};
+use gstreamer::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::core::encoder::with_scene_switcher;
+use crate::extensions::scene_collection::Geometry;
+use crate::extensions::source_transform::{Crop, Edge};
+
+// Screen pixels within which an edge snaps, or a handle can be grabbed
+const SNAP_DISTANCE: f64 = 8.0;
+const HANDLE_SIZE: f64 = 8.0;
+// Smallest box a resize or crop can leave, in canvas pixels
+const MIN_SIZE: i32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Handle {
+    Move,
+    Edge(Edge),
+    Corner(Edge, Edge),
+}
+
+impl Handle {
+    fn edges(self) -> Vec<Edge> {
+        match self {
+            Handle::Move => Vec::new(),
+            Handle::Edge(e) => vec![e],
+            Handle::Corner(h, v) => vec![h, v],
+        }
+    }
+}
+
+struct Drag {
+    pad_index: u32,
+    handle: Handle,
+    start: Geometry,
+    start_crop: Crop,
+    // Alt held: handles crop instead of scaling
+    crop: bool,
+    // Source pixels per canvas pixel, for cropping
+    source_scale: (f64, f64),
+}
+
+#[derive(Default)]
+struct EditorState {
+    selected: Option<u32>,
+    drag: Option<Drag>,
+    // Snap lines to draw, in canvas coordinates
+    guides_x: Vec<i32>,
+    guides_y: Vec<i32>,
+}
+
+// Where the canvas is drawn inside the preview widget (the sink keeps the aspect ratio)
+#[derive(Clone, Copy)]
+struct View {
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl View {
+    fn new(widget_w: f64, widget_h: f64, canvas: (i32, i32)) -> Self {
+        let (cw, ch) = (canvas.0.max(1) as f64, canvas.1.max(1) as f64);
+        let scale = (widget_w / cw).min(widget_h / ch);
+        Self {
+            scale,
+            offset_x: (widget_w - cw * scale) / 2.0,
+            offset_y: (widget_h - ch * scale) / 2.0,
+        }
+    }
+
+    fn to_canvas(&self, x: f64, y: f64) -> (f64, f64) {
+        ((x - self.offset_x) / self.scale, (y - self.offset_y) / self.scale)
+    }
+
+    fn to_widget(&self, g: &Geometry) -> (f64, f64, f64, f64) {
+        (
+            self.offset_x + g.x as f64 * self.scale,
+            self.offset_y + g.y as f64 * self.scale,
+            g.width as f64 * self.scale,
+            g.height as f64 * self.scale,
+        )
+    }
+}
+
+/// Turn a preview of the program output into a scene editor. Click a source to
+/// select it, drag to move it, drag a handle to resize it; with Alt held the
+/// handles crop instead. Edges and centers snap to the canvas and the other
+/// sources unless Shift is held. Every edit goes through the scene switcher and
+/// the working scene collection is saved when the drag ends.
+pub fn attach_scene_editor(overlay: &gtk::Overlay) {
+    let area = DrawingArea::new();
+    area.set_hexpand(true);
+    area.set_vexpand(true);
+    overlay.add_overlay(&area);
+
+    let state = Rc::new(RefCell::new(EditorState::default()));
+
+    let draw_state = state.clone();
+    area.set_draw_func(move |_, cr, w, h| {
+        draw_editor(cr, w as f64, h as f64, &draw_state.borrow());
+    });
+
+    let drag = GestureDrag::new();
+    drag.set_button(gdk::BUTTON_PRIMARY);
+
+    drag.connect_drag_begin(clone!(@weak area, @strong state => move |gesture, x, y| {
+        let alt = gesture.current_event_state().contains(gdk::ModifierType::ALT_MASK);
+        begin_drag(&mut state.borrow_mut(), area.upcast_ref(), x, y, alt);
+        area.queue_draw();
+    }));
+
+    drag.connect_drag_update(clone!(@weak area, @strong state => move |gesture, dx, dy| {
+        let shift = gesture.current_event_state().contains(gdk::ModifierType::SHIFT_MASK);
+        update_drag(&mut state.borrow_mut(), area.upcast_ref(), dx, dy, !shift);
+        area.queue_draw();
+    }));
+
+    drag.connect_drag_end(clone!(@weak area, @strong state => move |_, _, _| {
+        let mut state = state.borrow_mut();
+        state.guides_x.clear();
+        state.guides_y.clear();
+        if state.drag.take().is_some() {
+            if let Some(Err(e)) = with_scene_switcher(|switcher| switcher.save_collection()) {
+                eprintln!("Failed to save scene collection: {:?}", e);
+            }
+        }
+        area.queue_draw();
+    }));
+    area.add_controller(drag);
+
+    // Scene switches and edits made elsewhere move the boxes too
+    let weak_area = area.downgrade();
+    glib::timeout_add_local(Duration::from_millis(500), move || match weak_area.upgrade() {
+        Some(area) => {
+            area.queue_draw();
+            glib::Continue(true)
+        }
+        None => glib::Continue(false),
+    });
+}
+
+// Internal: the current scene's sources as (pad, geometry), topmost first
+fn scene_boxes() -> Vec<(u32, Geometry)> {
+    with_scene_switcher(|switcher| {
+        let scene = match switcher.scenes().get(switcher.current_scene_index()) {
+            Some(scene) => scene,
+            None => return Vec::new(),
+        };
+        let mut sources: Vec<_> = scene.sources.iter().filter(|s| s.alpha > 0.0).collect();
+        sources.sort_by(|a, b| b.zorder.cmp(&a.zorder));
+        sources
+            .into_iter()
+            .map(|s| {
+                (
+                    s.pad_index,
+                    Geometry {
+                        x: s.x,
+                        y: s.y,
+                        width: s.width,
+                        height: s.height,
+                    },
+                )
+            })
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn current_view(area: &gtk::Widget) -> Option<View> {
+    let canvas = with_scene_switcher(|switcher| switcher.canvas_size())?;
+    Some(View::new(area.width() as f64, area.height() as f64, canvas))
+}
+
+fn handle_at(view: &View, g: &Geometry, x: f64, y: f64) -> Option<Handle> {
+    let (bx, by, bw, bh) = view.to_widget(g);
+    let near = |a: f64, b: f64| (a - b).abs() <= HANDLE_SIZE;
+    let (left, right) = (near(x, bx), near(x, bx + bw));
+    let (top, bottom) = (near(y, by), near(y, by + bh));
+    let inside_x = x >= bx - HANDLE_SIZE && x <= bx + bw + HANDLE_SIZE;
+    let inside_y = y >= by - HANDLE_SIZE && y <= by + bh + HANDLE_SIZE;
+    if !inside_x || !inside_y {
+        return None;
+    }
+    let h = if left { Some(Edge::Left) } else if right { Some(Edge::Right) } else { None };
+    let v = if top { Some(Edge::Top) } else if bottom { Some(Edge::Bottom) } else { None };
+    match (h, v) {
+        (Some(h), Some(v)) => Some(Handle::Corner(h, v)),
+        (Some(e), None) | (None, Some(e)) => Some(Handle::Edge(e)),
+        (None, None) if x >= bx && x <= bx + bw && y >= by && y <= by + bh => Some(Handle::Move),
+        _ => None,
+    }
+}
+
+fn begin_drag(state: &mut EditorState, area: &gtk::Widget, x: f64, y: f64, crop: bool) {
+    let view = match current_view(area) {
+        Some(v) => v,
+        None => return,
+    };
+    let boxes = scene_boxes();
+
+    // Handles of the selected source win over other sources
+    let selected_hit = state
+        .selected
+        .and_then(|pad| boxes.iter().find(|(p, _)| *p == pad))
+        .and_then(|(pad, g)| Some((*pad, *g, handle_at(&view, g, x, y)?)));
+    let hit = selected_hit.or_else(|| {
+        let (cx, cy) = view.to_canvas(x, y);
+        boxes
+            .iter()
+            .find(|(_, g)| {
+                cx >= g.x as f64 && cx <= (g.x + g.width) as f64 && cy >= g.y as f64 && cy <= (g.y + g.height) as f64
+            })
+            .map(|(pad, g)| (*pad, *g, Handle::Move))
+    });
+
+    let (pad_index, start, handle) = match hit {
+        Some(hit) => hit,
+        None => {
+            state.selected = None;
+            return;
+        }
+    };
+    state.selected = Some(pad_index);
+
+    let found = with_scene_switcher(|switcher| {
+        let scene = switcher.current_scene_index();
+        let source = switcher
+            .scenes()
+            .get(scene)
+            .and_then(|s| s.sources.iter().find(|s| s.pad_index == pad_index));
+        let start_crop = source.map(|s| s.transform.crop).unwrap_or_default();
+        // Frame size entering the compositor, i.e. after the current crop
+        let frame = switcher
+            .compositor_pad(pad_index)
+            .and_then(|pad| pad.current_caps())
+            .and_then(|caps| {
+                let s = caps.structure(0)?;
+                Some((s.get::<i32>("width").ok()?, s.get::<i32>("height").ok()?))
+            })
+            .unwrap_or((start.width, start.height));
+        (start_crop, frame)
+    });
+    let (start_crop, frame) = match found {
+        Some(found) => found,
+        None => return,
+    };
+    state.drag = Some(Drag {
+        pad_index,
+        handle,
+        start,
+        start_crop,
+        crop,
+        source_scale: (
+            frame.0 as f64 / start.width.max(1) as f64,
+            frame.1 as f64 / start.height.max(1) as f64,
+        ),
+    });
+}
+
+fn update_drag(state: &mut EditorState, area: &gtk::Widget, dx: f64, dy: f64, snapping: bool) {
+    let view = match current_view(area) {
+        Some(v) => v,
+        None => return,
+    };
+    let drag = match &state.drag {
+        Some(d) => d,
+        None => return,
+    };
+    let (dx, dy) = ((dx / view.scale).round() as i32, (dy / view.scale).round() as i32);
+    let threshold = (SNAP_DISTANCE / view.scale).round() as i32;
+    let s = drag.start;
+
+    // Snap targets: canvas edges and center, and the other sources' edges and centers
+    let canvas = with_scene_switcher(|sw| sw.canvas_size()).unwrap_or((1280, 720));
+    let mut targets_x = vec![0, canvas.0 / 2, canvas.0];
+    let mut targets_y = vec![0, canvas.1 / 2, canvas.1];
+    for (pad, g) in scene_boxes() {
+        if pad != drag.pad_index {
+            targets_x.extend([g.x, g.x + g.width / 2, g.x + g.width]);
+            targets_y.extend([g.y, g.y + g.height / 2, g.y + g.height]);
+        }
+    }
+    let snap = |values: &[i32], targets: &[i32]| -> Option<(i32, i32)> {
+        if !snapping {
+            return None;
+        }
+        values
+            .iter()
+            .flat_map(|v| targets.iter().map(move |t| (t - v, *t)))
+            .filter(|(shift, _)| shift.abs() <= threshold)
+            .min_by_key(|(shift, _)| shift.abs())
+    };
+    state.guides_x.clear();
+    state.guides_y.clear();
+
+    let mut g = s;
+    match drag.handle {
+        Handle::Move => {
+            g.x = s.x + dx;
+            g.y = s.y + dy;
+            if let Some((shift, guide)) = snap(&[g.x, g.x + g.width / 2, g.x + g.width], &targets_x) {
+                g.x += shift;
+                state.guides_x.push(guide);
+            }
+            if let Some((shift, guide)) = snap(&[g.y, g.y + g.height / 2, g.y + g.height], &targets_y) {
+                g.y += shift;
+                state.guides_y.push(guide);
+            }
+        }
+        handle => {
+            for edge in handle.edges() {
+                match edge {
+                    Edge::Left => {
+                        let mut left = s.x + dx;
+                        if let Some((shift, guide)) = snap(&[left], &targets_x) {
+                            left += shift;
+                            state.guides_x.push(guide);
+                        }
+                        left = left.min(s.x + s.width - MIN_SIZE);
+                        g.x = left;
+                        g.width = s.x + s.width - left;
+                    }
+                    Edge::Right => {
+                        let mut right = s.x + s.width + dx;
+                        if let Some((shift, guide)) = snap(&[right], &targets_x) {
+                            right += shift;
+                            state.guides_x.push(guide);
+                        }
+                        g.width = (right - s.x).max(MIN_SIZE);
+                    }
+                    Edge::Top => {
+                        let mut top = s.y + dy;
+                        if let Some((shift, guide)) = snap(&[top], &targets_y) {
+                            top += shift;
+                            state.guides_y.push(guide);
+                        }
+                        top = top.min(s.y + s.height - MIN_SIZE);
+                        g.y = top;
+                        g.height = s.y + s.height - top;
+                    }
+                    Edge::Bottom => {
+                        let mut bottom = s.y + s.height + dy;
+                        if let Some((shift, guide)) = snap(&[bottom], &targets_y) {
+                            bottom += shift;
+                            state.guides_y.push(guide);
+                        }
+                        g.height = (bottom - s.y).max(MIN_SIZE);
+                    }
+                }
+            }
+        }
+    }
+
+    with_scene_switcher(|switcher| {
+        let scene = switcher.current_scene_index();
+        if drag.crop && drag.handle != Handle::Move {
+            // Cropping keeps the scale: what leaves the box is cut from the source
+            let transform = switcher
+                .scenes()
+                .get(scene)
+                .and_then(|sc| sc.sources.iter().find(|src| src.pad_index == drag.pad_index))
+                .map(|src| src.transform);
+            if let Some(mut transform) = transform {
+                transform.crop = drag.start_crop;
+                for edge in drag.handle.edges() {
+                    let (moved, per_pixel) = match edge {
+                        Edge::Left => (g.x - s.x, drag.source_scale.0),
+                        Edge::Right => (s.width - g.width, drag.source_scale.0),
+                        Edge::Top => (g.y - s.y, drag.source_scale.1),
+                        Edge::Bottom => (s.height - g.height, drag.source_scale.1),
+                    };
+                    let value = transform.crop.edge_mut(transform.source_edge(edge));
+                    *value = (*value + (moved as f64 * per_pixel).round() as i32).max(0);
+                }
+                let _ = switcher
+                    .set_source_transform(scene, drag.pad_index, transform, Duration::ZERO)
+                    .map_err(|e| eprintln!("Crop error: {:?}", e));
+            }
+        }
+        let _ = switcher
+            .update_source_geometry(scene, drag.pad_index, g.x, g.y, g.width, g.height)
+            .map_err(|e| eprintln!("Move error: {:?}", e));
+    });
+}
+
+fn draw_editor(cr: &cairo::Context, w: f64, h: f64, state: &EditorState) {
+    let canvas = match with_scene_switcher(|switcher| switcher.canvas_size()) {
+        Some(c) => c,
+        None => return,
+    };
+    let view = View::new(w, h, canvas);
+    cr.set_line_width(1.0);
+
+    for (pad, g) in scene_boxes().iter().rev() {
+        let (x, y, bw, bh) = view.to_widget(g);
+        if Some(*pad) == state.selected {
+            cr.set_source_rgba(1.0, 0.3, 0.3, 1.0);
+            cr.set_line_width(2.0);
+            cr.rectangle(x, y, bw, bh);
+            let _ = cr.stroke();
+            cr.set_line_width(1.0);
+            // Corner and edge handles
+            for (hx, hy) in [
+                (x, y),
+                (x + bw / 2.0, y),
+                (x + bw, y),
+                (x, y + bh / 2.0),
+                (x + bw, y + bh / 2.0),
+                (x, y + bh),
+                (x + bw / 2.0, y + bh),
+                (x + bw, y + bh),
+            ] {
+                cr.rectangle(hx - HANDLE_SIZE / 2.0, hy - HANDLE_SIZE / 2.0, HANDLE_SIZE, HANDLE_SIZE);
+                let _ = cr.fill();
+            }
+        } else {
+            cr.set_source_rgba(1.0, 1.0, 1.0, 0.35);
+            cr.rectangle(x, y, bw, bh);
+            let _ = cr.stroke();
+        }
+    }
+
+    cr.set_source_rgba(0.2, 0.8, 1.0, 0.9);
+    for gx in &state.guides_x {
+        let x = view.offset_x + *gx as f64 * view.scale;
+        cr.move_to(x, 0.0);
+        cr.line_to(x, h);
+    }
+    for gy in &state.guides_y {
+        let y = view.offset_y + *gy as f64 * view.scale;
+        cr.move_to(0.0, y);
+        cr.line_to(w, y);
+    }
+    let _ = cr.stroke();
+}
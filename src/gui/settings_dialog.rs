@@ -3,19 +3,41 @@ use gtk::{prelude::*, Orientation};
 use libadwaita::{Window as AdwWindow, WindowTitleButtons};
 use glib::{clone, MainContext};
 use anyhow::Result;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::time::Duration;
 
 use crate::core::encoder::{
     get_global_pipeline,
-    get_global_scene_switcher,
-    get_global_overlay_manager,
-    get_global_streaming_manager,
+    load_scene_collection,
+    with_automation,
+    with_overlay_manager,
+    with_scene_switcher,
+    with_streaming_manager,
 };
+use crate::devices::audio::get_audio_sources;
 use crate::extensions::{
+    audio_visualizer::VisualizerStyle,
     hardware_accel::setup_unified_hardware_accel,
     streaming::{MultiStreamingManager, StreamingProtocol},
     scene_switcher::SceneSwitcher,
-    overlays::OverlayManager,
+    overlays::{OverlayDesc, OverlayKind, OverlayManager, OverlayStack},
+    lower_third::{draw_lower_third, LowerThirdTemplate, Reveal},
+    privacy_mask::{MaskEffect, TrackWindow},
+    recording_stamp::RecordingStamp,
+    subtitles::{caption, SubtitleConfig},
+    message_rotation::{RotationConfig, RotationMessage, RotationTransition},
+    text_style::TextStyle,
+    scene_collection::{parse_hex_rgb, Geometry, SceneCollection},
+    scene_automation::AutomationConfig,
+    media_sources::{Slide as SlideshowSlide, SlideTransition},
+    source_registry::{SourceDef, SourceKind},
+    transitions::{Cut, Direction, Easing, Fade, FadeThroughColor, Slide, Stinger, Swipe, Transition, Wipe},
+    camera_effects::{
+        segmentation_element, set_segmentation_element, BackgroundEffect, CameraEffects, CameraMask,
+        ChromaKey, ChromaKeyColor, ColorBalance,
+    },
     plugin_system::{PluginManager, GLOBAL_PLUGIN_MANAGER, load_plugins_from_folder},
 };
 
@@ -39,7 +61,7 @@ impl SettingsDialog {
         header_bar.pack_end(&buttons);
         dialog.set_titlebar(Some(&header_bar));
 
-        // A stack for pages: Hardware Accel, Scenes, Overlays, Streaming, Plugins
+        // A stack for pages: Hardware Accel, Scenes, Automation, Overlays, Streaming, Plugins
         let stack = gtk::Stack::new();
         stack.set_transition_type(gtk::StackTransitionType::SlideLeftRight);
         stack.set_transition_duration(500);
@@ -64,9 +86,193 @@ impl SettingsDialog {
         let scene_label = gtk::Label::new(Some("Manage Scenes & Transitions."));
         scene_box.append(&scene_label);
 
-        let fade_btn = gtk::Button::with_label("Fade to Next Scene (1)");
+        // Transition to the next scene
+        let transition_combo = gtk::ComboBoxText::new();
+        for (id, label) in [
+            ("cut", "Cut"),
+            ("fade", "Fade"),
+            ("fade_black", "Fade Through Black"),
+            ("slide", "Slide"),
+            ("swipe", "Swipe"),
+            ("wipe", "SMPTE Wipe"),
+            ("stinger", "Stinger"),
+        ] {
+            transition_combo.append(Some(id), label);
+        }
+        transition_combo.set_active_id(Some("fade"));
+        scene_box.append(&transition_combo);
+
+        let transition_row = gtk::Box::new(Orientation::Horizontal, 6);
+        transition_row.append(&gtk::Label::new(Some("Duration (ms):")));
+        let transition_duration_spin = gtk::SpinButton::with_range(0.0, 10000.0, 50.0);
+        transition_duration_spin.set_value(1000.0);
+        transition_row.append(&transition_duration_spin);
+        let easing_combo = gtk::ComboBoxText::new();
+        for (id, label) in [
+            ("linear", "Linear"),
+            ("ease_in", "Ease In"),
+            ("ease_out", "Ease Out"),
+            ("ease_in_out", "Ease In/Out"),
+        ] {
+            easing_combo.append(Some(id), label);
+        }
+        easing_combo.set_active_id(Some("ease_in_out"));
+        transition_row.append(&easing_combo);
+        let direction_combo = gtk::ComboBoxText::new();
+        for (id, label) in [("left", "Left"), ("right", "Right"), ("up", "Up"), ("down", "Down")] {
+            direction_combo.append(Some(id), label);
+        }
+        direction_combo.set_active_id(Some("left"));
+        transition_row.append(&direction_combo);
+        scene_box.append(&transition_row);
+
+        let wipe_entry = gtk::Entry::new();
+        wipe_entry.set_placeholder_text(Some("Wipe pattern, e.g. bar-w1, iris-rect, clock-cw12"));
+        scene_box.append(&wipe_entry);
+        let stinger_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let stinger_entry = gtk::Entry::new();
+        stinger_entry.set_placeholder_text(Some("Stinger video (with alpha)"));
+        stinger_entry.set_hexpand(true);
+        stinger_row.append(&stinger_entry);
+        stinger_row.append(&gtk::Label::new(Some("Switch at (ms):")));
+        let stinger_point_spin = gtk::SpinButton::with_range(0.0, 10000.0, 50.0);
+        stinger_point_spin.set_value(500.0);
+        stinger_row.append(&stinger_point_spin);
+        scene_box.append(&stinger_row);
+
+        let fade_btn = gtk::Button::with_label("Transition to Next Scene");
         scene_box.append(&fade_btn);
 
+        // Sources: registered once, shown in any number of scenes
+        scene_box.append(&gtk::Label::new(Some("Sources:")));
+        let source_id_entry = gtk::Entry::new();
+        source_id_entry.set_placeholder_text(Some("Source ID, e.g. logo"));
+        scene_box.append(&source_id_entry);
+        let source_kind_combo = gtk::ComboBoxText::new();
+        for (id, label) in [
+            ("screen", "Screen"),
+            ("window", "Window"),
+            ("image", "Image (path)"),
+            ("media", "Media File (path or URI)"),
+            ("media_loop", "Looping Media File (path or URI)"),
+            ("slideshow", "Slideshow (comma-separated paths)"),
+            ("color", "Color (0xAARRGGBB)"),
+            ("text", "Text"),
+            ("pattern", "Test Pattern (name)"),
+            ("scene", "Scene (name)"),
+            ("visualizer_spectrum", "Audio Spectrum (color 0xAARRGGBB)"),
+            ("visualizer_waveform", "Audio Waveform (color 0xAARRGGBB)"),
+            ("visualizer_oscilloscope", "Audio Oscilloscope (color 0xAARRGGBB)"),
+            ("visualizer_synaesthesia", "Audio Synaesthesia (color 0xAARRGGBB)"),
+        ] {
+            source_kind_combo.append(Some(id), label);
+        }
+        source_kind_combo.set_active_id(Some("image"));
+        scene_box.append(&source_kind_combo);
+        // Input drawn by the audio visualizer sources
+        let visualizer_device_combo = gtk::ComboBoxText::new();
+        visualizer_device_combo.append(Some(""), "Default audio input");
+        for (_id, name, desc) in get_audio_sources() {
+            visualizer_device_combo.append(Some(&name), &format!("{} ({})", desc, name));
+        }
+        visualizer_device_combo.set_active_id(Some(""));
+        scene_box.append(&visualizer_device_combo);
+        let source_value_entry = gtk::Entry::new();
+        source_value_entry.set_placeholder_text(Some("Path, URI, color, text, pattern or scene name"));
+        scene_box.append(&source_value_entry);
+        let source_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let add_source_btn = gtk::Button::with_label("Add Source");
+        let show_source_btn = gtk::Button::with_label("Add to Active Scene");
+        let remove_source_btn = gtk::Button::with_label("Remove Source");
+        source_row.append(&add_source_btn);
+        source_row.append(&show_source_btn);
+        source_row.append(&remove_source_btn);
+        scene_box.append(&source_row);
+
+        // Scene collections: import/export as .json or .toml
+        scene_box.append(&gtk::Label::new(Some("Scene Collection (.json or .toml):")));
+        let collection_entry = gtk::Entry::new();
+        collection_entry.set_text(&SceneCollection::default_path().to_string_lossy());
+        scene_box.append(&collection_entry);
+        let collection_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let import_btn = gtk::Button::with_label("Import Collection");
+        let export_btn = gtk::Button::with_label("Export Collection");
+        collection_row.append(&import_btn);
+        collection_row.append(&export_btn);
+        scene_box.append(&collection_row);
+
+        // Camera effects for the camera in the active scene
+        scene_box.append(&gtk::Label::new(Some("Camera Effects (active scene):")));
+        let mirror_check = gtk::CheckButton::with_label("Mirror");
+        scene_box.append(&mirror_check);
+
+        let chroma_combo = gtk::ComboBoxText::new();
+        chroma_combo.append(Some("none"), "No chroma key");
+        chroma_combo.append(Some("green"), "Green screen");
+        chroma_combo.append(Some("blue"), "Blue screen");
+        chroma_combo.append(Some("custom"), "Custom color");
+        chroma_combo.set_active(Some(0));
+        let chroma_color_entry = gtk::Entry::new();
+        chroma_color_entry.set_placeholder_text(Some("#rrggbb"));
+        chroma_color_entry.set_sensitive(false);
+        chroma_combo.connect_changed(clone!(@weak chroma_color_entry => move |combo| {
+            chroma_color_entry.set_sensitive(combo.active_id().as_deref() == Some("custom"));
+        }));
+        let chroma_row = gtk::Box::new(Orientation::Horizontal, 5);
+        chroma_row.append(&chroma_combo);
+        chroma_row.append(&chroma_color_entry);
+        scene_box.append(&chroma_row);
+
+        let mask_combo = gtk::ComboBoxText::new();
+        mask_combo.append(Some("none"), "No mask");
+        mask_combo.append(Some("circle"), "Circle");
+        mask_combo.append(Some("rounded"), "Rounded corners");
+        mask_combo.set_active(Some(0));
+        scene_box.append(&mask_combo);
+
+        let background_combo = gtk::ComboBoxText::new();
+        background_combo.append(Some("none"), "Background unchanged");
+        background_combo.append(Some("blur"), "Blur background");
+        background_combo.append(Some("replace"), "Replace background");
+        background_combo.set_active(Some(0));
+        scene_box.append(&background_combo);
+        // Background effects need an element that cuts the person out (RGBA)
+        let segmentation_check = gtk::CheckButton::with_label("Background segmentation with");
+        let segmentation_entry = gtk::Entry::new();
+        segmentation_entry.set_placeholder_text(Some("segmentation element"));
+        if let Some(name) = segmentation_element() {
+            segmentation_check.set_active(true);
+            segmentation_entry.set_text(&name);
+        }
+        let segmentation_row = gtk::Box::new(Orientation::Horizontal, 5);
+        segmentation_row.append(&segmentation_check);
+        segmentation_row.append(&segmentation_entry);
+        scene_box.append(&segmentation_row);
+        let background_entry = gtk::Entry::new();
+        background_entry.set_placeholder_text(Some("/path/to/background.png"));
+        scene_box.append(&background_entry);
+
+        let brightness_scale = gtk::Scale::with_range(Orientation::Horizontal, -1.0, 1.0, 0.05);
+        brightness_scale.set_value(0.0);
+        let contrast_scale = gtk::Scale::with_range(Orientation::Horizontal, 0.0, 2.0, 0.05);
+        contrast_scale.set_value(1.0);
+        let saturation_scale = gtk::Scale::with_range(Orientation::Horizontal, 0.0, 2.0, 0.05);
+        saturation_scale.set_value(1.0);
+        for (label, scale) in [
+            ("Brightness", &brightness_scale),
+            ("Contrast", &contrast_scale),
+            ("Saturation", &saturation_scale),
+        ] {
+            let row = gtk::Box::new(Orientation::Horizontal, 5);
+            scale.set_hexpand(true);
+            row.append(&gtk::Label::new(Some(label)));
+            row.append(scale);
+            scene_box.append(&row);
+        }
+
+        let apply_effects_btn = gtk::Button::with_label("Apply Camera Effects");
+        scene_box.append(&apply_effects_btn);
+
         // -- Overlays Page --
         let overlay_box = gtk::Box::new(Orientation::Vertical, 10);
         let overlay_label = gtk::Label::new(Some("Add text/images, schedule rotating messages."));
@@ -78,6 +284,174 @@ impl SettingsDialog {
         let sponsor_btn = gtk::Button::with_label("Start Sponsor Rotation (hello_overlay)");
         overlay_box.append(&sponsor_btn);
 
+        // Live edits of one overlay, by ID
+        let overlay_id_entry = gtk::Entry::new();
+        overlay_id_entry.set_placeholder_text(Some("Overlay ID, e.g. hello_overlay"));
+        overlay_box.append(&overlay_id_entry);
+        let overlay_text_entry = gtk::Entry::new();
+        overlay_text_entry.set_placeholder_text(Some("New text"));
+        overlay_box.append(&overlay_text_entry);
+        let overlay_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let update_text_btn = gtk::Button::with_label("Update Text");
+        let raise_overlay_btn = gtk::Button::with_label("Raise");
+        let lower_overlay_btn = gtk::Button::with_label("Lower");
+        let toggle_overlay_btn = gtk::Button::with_label("Show/Hide");
+        let remove_overlay_btn = gtk::Button::with_label("Remove");
+        let restart_timer_btn = gtk::Button::with_label("Restart Countdown");
+        for btn in [
+            &update_text_btn,
+            &raise_overlay_btn,
+            &lower_overlay_btn,
+            &toggle_overlay_btn,
+            &remove_overlay_btn,
+            &restart_timer_btn,
+        ] {
+            overlay_row.append(btn);
+        }
+        overlay_box.append(&overlay_row);
+        let image_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let image_path_entry = gtk::Entry::new();
+        image_path_entry.set_hexpand(true);
+        image_path_entry.set_placeholder_text(Some("Image, GIF/APNG/WebP or PNG sequence (badge_%04d.png)"));
+        let add_image_btn = gtk::Button::with_label("Add Image");
+        image_row.append(&image_path_entry);
+        image_row.append(&add_image_btn);
+        overlay_box.append(&image_row);
+        let look_row = gtk::Box::new(Orientation::Horizontal, 6);
+        look_row.append(&gtk::Label::new(Some("Opacity:")));
+        let opacity_spin = gtk::SpinButton::with_range(0.0, 1.0, 0.05);
+        opacity_spin.set_value(1.0);
+        look_row.append(&opacity_spin);
+        look_row.append(&gtk::Label::new(Some("Scale:")));
+        let scale_spin = gtk::SpinButton::with_range(0.05, 10.0, 0.05);
+        scale_spin.set_value(1.0);
+        look_row.append(&scale_spin);
+        let apply_look_btn = gtk::Button::with_label("Apply to Image");
+        look_row.append(&apply_look_btn);
+        overlay_box.append(&look_row);
+        let rotation_file_entry = gtk::Entry::new();
+        rotation_file_entry.set_placeholder_text(Some("Messages file, one per line (\"[10] text\" shows it for 10 s)"));
+        overlay_box.append(&rotation_file_entry);
+        let rotation_mode_combo = gtk::ComboBoxText::new();
+        rotation_mode_combo.append(Some("crossfade"), "Cross-fade");
+        rotation_mode_combo.append(Some("cut"), "Cut");
+        rotation_mode_combo.append(Some("ticker"), "Scrolling Ticker");
+        rotation_mode_combo.set_active_id(Some("crossfade"));
+        overlay_box.append(&rotation_mode_combo);
+        let rotation_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let rotate_file_btn = gtk::Button::with_label("Rotate Messages From File");
+        let stop_rotation_btn = gtk::Button::with_label("Stop Rotation");
+        rotation_row.append(&rotate_file_btn);
+        rotation_row.append(&stop_rotation_btn);
+        overlay_box.append(&rotation_row);
+
+        // Privacy masks, by overlay ID
+        let mask_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let mask_area_entry = gtk::Entry::new();
+        mask_area_entry.set_hexpand(true);
+        mask_area_entry.set_placeholder_text(Some("Mask area: x,y,width,height"));
+        let mask_effect_combo = gtk::ComboBoxText::new();
+        mask_effect_combo.append(Some("pixelate"), "Pixelate");
+        mask_effect_combo.append(Some("blur"), "Blur");
+        mask_effect_combo.append(Some("black"), "Black Out");
+        mask_effect_combo.set_active_id(Some("pixelate"));
+        let add_mask_btn = gtk::Button::with_label("Add Mask");
+        let update_mask_btn = gtk::Button::with_label("Update Mask");
+        mask_row.append(&mask_area_entry);
+        mask_row.append(&mask_effect_combo);
+        mask_row.append(&add_mask_btn);
+        mask_row.append(&update_mask_btn);
+        overlay_box.append(&mask_row);
+        let track_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let track_title_entry = gtk::Entry::new();
+        track_title_entry.set_hexpand(true);
+        track_title_entry.set_placeholder_text(Some("Window title to follow (Hyprland/Sway), empty to stop"));
+        let track_mask_btn = gtk::Button::with_label("Follow Window");
+        track_row.append(&track_title_entry);
+        track_row.append(&track_mask_btn);
+        overlay_box.append(&track_row);
+
+        // Lower thirds: pick or edit a template, preview it, put it on the program
+        overlay_box.append(&gtk::Label::new(Some("Lower third template (TOML):")));
+        let template_combo = gtk::ComboBoxText::with_entry();
+        template_combo.append(Some("default"), "default");
+        for name in LowerThirdTemplate::list().into_iter().filter(|n| n != "default") {
+            template_combo.append(Some(&name), &name);
+        }
+        template_combo.set_active_id(Some("default"));
+        overlay_box.append(&template_combo);
+        let template_view = gtk::TextView::new();
+        template_view.set_monospace(true);
+        template_view.buffer().set_text(&toml::to_string_pretty(&LowerThirdTemplate::default()).unwrap_or_default());
+        let template_scroll = gtk::ScrolledWindow::new();
+        template_scroll.set_min_content_height(150);
+        template_scroll.set_child(Some(&template_view));
+        overlay_box.append(&template_scroll);
+        let lower_third_title_entry = gtk::Entry::new();
+        lower_third_title_entry.set_placeholder_text(Some("Title, e.g. Jane Doe"));
+        overlay_box.append(&lower_third_title_entry);
+        let lower_third_subtitle_entry = gtk::Entry::new();
+        lower_third_subtitle_entry.set_placeholder_text(Some("Subtitle, e.g. Maintainer, Waycord"));
+        overlay_box.append(&lower_third_subtitle_entry);
+        let lower_third_preview = gtk::DrawingArea::new();
+        lower_third_preview.set_content_height(140);
+        overlay_box.append(&lower_third_preview);
+        let lower_third_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let play_lower_third_btn = gtk::Button::with_label("Preview In/Out");
+        let save_template_btn = gtk::Button::with_label("Save Template");
+        let add_lower_third_btn = gtk::Button::with_label("Add Lower Third");
+        let update_lower_third_btn = gtk::Button::with_label("Update Lower Third");
+        for btn in [&play_lower_third_btn, &save_template_btn, &add_lower_third_btn, &update_lower_third_btn] {
+            lower_third_row.append(btn);
+        }
+        overlay_box.append(&lower_third_row);
+
+        // The whole stack, bottom to top
+        overlay_box.append(&gtk::Label::new(Some("Overlay stack (TOML), bottom to top:")));
+        let overlays_view = gtk::TextView::new();
+        overlays_view.set_monospace(true);
+        overlays_view.buffer().set_text(&overlays_toml());
+        let overlays_scroll = gtk::ScrolledWindow::new();
+        overlays_scroll.set_min_content_height(200);
+        overlays_scroll.set_child(Some(&overlays_view));
+        overlay_box.append(&overlays_scroll);
+        let apply_overlays_btn = gtk::Button::with_label("Apply and Save Overlays");
+        overlay_box.append(&apply_overlays_btn);
+
+        // Burned into recordings only, from the next recording on
+        overlay_box.append(&gtk::Label::new(Some(
+            "Recording stamp (TOML): {time}, {host}, {user} and {id} are filled in.",
+        )));
+        let stamp_view = gtk::TextView::new();
+        stamp_view.set_monospace(true);
+        stamp_view.buffer().set_text(&toml::to_string_pretty(&RecordingStamp::load_or_default()).unwrap_or_default());
+        let stamp_scroll = gtk::ScrolledWindow::new();
+        stamp_scroll.set_min_content_height(120);
+        stamp_scroll.set_child(Some(&stamp_view));
+        overlay_box.append(&stamp_scroll);
+        let save_stamp_btn = gtk::Button::with_label("Save Recording Stamp");
+        overlay_box.append(&save_stamp_btn);
+
+        // Rotating messages, lower thirds and captions as subtitles of the recording
+        overlay_box.append(&gtk::Label::new(Some("Recording subtitles (TOML):")));
+        let subtitles_view = gtk::TextView::new();
+        subtitles_view.set_monospace(true);
+        subtitles_view.buffer().set_text(&toml::to_string_pretty(&SubtitleConfig::load_or_default()).unwrap_or_default());
+        overlay_box.append(&subtitles_view);
+        let save_subtitles_btn = gtk::Button::with_label("Save Subtitle Settings");
+        overlay_box.append(&save_subtitles_btn);
+        let caption_row = gtk::Box::new(Orientation::Horizontal, 6);
+        let caption_entry = gtk::Entry::new();
+        caption_entry.set_hexpand(true);
+        caption_entry.set_placeholder_text(Some("Caption for the recording's subtitles"));
+        let caption_secs_spin = gtk::SpinButton::with_range(0.5, 60.0, 0.5);
+        caption_secs_spin.set_value(4.0);
+        let caption_btn = gtk::Button::with_label("Add Caption");
+        caption_row.append(&caption_entry);
+        caption_row.append(&caption_secs_spin);
+        caption_row.append(&caption_btn);
+        overlay_box.append(&caption_row);
+
         // -- Streaming Page --
         let streaming_box = gtk::Box::new(Orientation::Vertical, 10);
         let streaming_label = gtk::Label::new(Some("Configure multi-protocol streaming (RTMP/SRT/HLS)."));
@@ -102,9 +476,33 @@ impl SettingsDialog {
         let load_plugin_btn = gtk::Button::with_label("Load Plugins from Folder");
         plugin_box.append(&load_plugin_btn);
 
+        // -- Automation Page --
+        let automation_box = gtk::Box::new(Orientation::Vertical, 10);
+        automation_box.append(&gtk::Label::new(Some(
+            "Scene switching rules (TOML). Triggers: window_focus, silence, schedule, timer, media_end.",
+        )));
+        let rules_view = gtk::TextView::new();
+        rules_view.set_monospace(true);
+        let rules_text = with_automation(|engine| toml::to_string_pretty(engine.config()).unwrap_or_default())
+            .unwrap_or_default();
+        rules_view.buffer().set_text(&rules_text);
+        let rules_scroll = gtk::ScrolledWindow::new();
+        rules_scroll.set_min_content_height(200);
+        rules_scroll.set_child(Some(&rules_view));
+        automation_box.append(&rules_scroll);
+        let apply_rules_btn = gtk::Button::with_label("Apply and Save Rules");
+        automation_box.append(&apply_rules_btn);
+        automation_box.append(&gtk::Label::new(Some("Fired rules:")));
+        let history_label = gtk::Label::new(None);
+        history_label.set_xalign(0.0);
+        automation_box.append(&history_label);
+        let refresh_history_btn = gtk::Button::with_label("Refresh");
+        automation_box.append(&refresh_history_btn);
+
         // Add pages
         stack.add_titled(&hw_box, Some("hardware"), "Hardware Accel");
         stack.add_titled(&scene_box, Some("scenes"), "Scenes");
+        stack.add_titled(&automation_box, Some("automation"), "Automation");
         stack.add_titled(&overlay_box, Some("overlays"), "Overlays");
         stack.add_titled(&streaming_box, Some("streaming"), "Streaming");
         stack.add_titled(&plugin_box, Some("plugins"), "Plugins");
@@ -113,9 +511,6 @@ impl SettingsDialog {
 
         // Retrieve references
         let pipeline_opt = get_global_pipeline();
-        let scene_switcher_opt = get_global_scene_switcher();
-        let overlay_manager_opt = get_global_overlay_manager();
-        let streaming_manager_opt = get_global_streaming_manager();
         let main_context = MainContext::default();
 
         // (1) Hardware Accel
@@ -140,61 +535,614 @@ impl SettingsDialog {
 
         // (2) Scenes
         fade_btn.connect_clicked(move |_| {
-            if let Some(switcher) = scene_switcher_opt {
-                let _ = switcher.fade_to_scene(1)
-                    .map_err(|e| eprintln!("Scene fade error: {:?}", e));
-            }
+            let duration = Duration::from_millis(transition_duration_spin.value() as u64);
+            let easing = easing_combo
+                .active_id()
+                .and_then(|id| Easing::from_id(&id))
+                .unwrap_or(Easing::EaseInOut);
+            let direction = direction_combo
+                .active_id()
+                .and_then(|id| Direction::from_id(&id))
+                .unwrap_or(Direction::Left);
+            let transition: Box<dyn Transition> = match transition_combo.active_id().as_deref() {
+                Some("cut") => Box::new(Cut),
+                Some("fade_black") => Box::new(FadeThroughColor::new(duration, easing, 0xff000000)),
+                Some("slide") => Box::new(Slide { duration, easing, direction }),
+                Some("swipe") => Box::new(Swipe { duration, easing, direction }),
+                Some("wipe") => {
+                    let pattern = wipe_entry.text().to_string();
+                    Box::new(Wipe {
+                        duration,
+                        easing,
+                        pattern: if pattern.is_empty() { "bar-w1".to_string() } else { pattern },
+                        border: 20000,
+                        invert: false,
+                    })
+                }
+                Some("stinger") => Box::new(Stinger::new(
+                    &stinger_entry.text(),
+                    duration,
+                    Duration::from_millis(stinger_point_spin.value() as u64),
+                )),
+                _ => Box::new(Fade { duration, easing }),
+            };
+            with_scene_switcher(|switcher| {
+                // Also used by the studio mode transition button
+                switcher.set_transition(transition);
+                let next = (switcher.current_scene_index() + 1) % switcher.scenes().len().max(1);
+                let _ = switcher.switch_to_scene(next)
+                    .map_err(|e| eprintln!("Scene transition error: {:?}", e));
+            });
         });
 
-        // (3) Overlays
-        add_text_btn.connect_clicked(move |_| {
-            if let Some(overlay_mgr) = overlay_manager_opt {
-                if let Some(ref pipeline) = pipeline_opt {
-                    let up = pipeline.by_name("videoconvert");
-                    // We might have software x264 or VAAPI etc
-                    let down = pipeline.by_name("vaapi_enc")
-                        .or_else(|| pipeline.by_name("nvenc_enc"))
-                        .or_else(|| pipeline.by_name("amf_enc"))
-                        .or_else(|| pipeline.by_name("soft_x264"));
-                    if let (Some(u), Some(d)) = (up, down) {
-                        let _ = overlay_mgr.add_text_overlay(
-                            "hello_overlay",
-                            "Hello from Overlays!",
-                            100, 100,
-                            "Sans 24",
-                            "white",
-                            &u,
-                            &d
-                        ).map_err(|e| eprintln!("Overlay error: {:?}", e));
+        add_source_btn.connect_clicked(clone!(@weak source_id_entry, @weak source_value_entry => move |_| {
+            let id = source_id_entry.text().to_string();
+            let value = source_value_entry.text().to_string();
+            if id.is_empty() {
+                eprintln!("Please provide a source ID!");
+                return;
+            }
+            let kind = match source_kind_combo.active_id().as_deref() {
+                Some("screen") => SourceKind::Screen,
+                Some("window") => SourceKind::Window,
+                Some("image") => SourceKind::Image { path: value },
+                Some(media @ ("media" | "media_loop")) => SourceKind::MediaFile {
+                    uri: value,
+                    looping: media == "media_loop",
+                    restart_on_activate: true,
+                    audio: true,
+                },
+                Some("slideshow") => SourceKind::Slideshow {
+                    slides: value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|p| !p.is_empty())
+                        .map(|path| SlideshowSlide {
+                            path: path.to_string(),
+                            duration_secs: 5.0,
+                            transition: SlideTransition::Crossfade,
+                            transition_secs: 1.0,
+                        })
+                        .collect(),
+                    looping: true,
+                    width: 1280,
+                    height: 720,
+                },
+                Some("color") => match u32::from_str_radix(value.trim_start_matches("0x"), 16) {
+                    Ok(argb) => SourceKind::Color { argb },
+                    Err(_) => {
+                        eprintln!("Invalid color '{}', expected 0xAARRGGBB", value);
+                        return;
+                    }
+                },
+                Some("scene") => SourceKind::Scene { scene: value },
+                Some(visualizer) if visualizer.starts_with("visualizer_") => {
+                    let color = if value.is_empty() {
+                        Ok(0xffffffff)
+                    } else {
+                        u32::from_str_radix(value.trim_start_matches("0x"), 16)
+                    };
+                    let color = match color {
+                        Ok(color) => color,
+                        Err(_) => {
+                            eprintln!("Invalid color '{}', expected 0xAARRGGBB", value);
+                            return;
+                        }
+                    };
+                    let device = visualizer_device_combo.active_id().map(|d| d.to_string()).filter(|d| !d.is_empty());
+                    SourceKind::AudioVisualizer {
+                        device,
+                        style: VisualizerStyle::from_id(&visualizer["visualizer_".len()..]).unwrap_or_default(),
+                        width: 640,
+                        height: 360,
+                        fps: 30,
+                        color,
+                        background: 0xff000000,
+                        trails: false,
                     }
                 }
+                Some("text") => SourceKind::Text {
+                    text: value,
+                    font: "Sans 32".to_string(),
+                    color: 0xffffffff,
+                    width: 640,
+                    height: 120,
+                },
+                _ => SourceKind::TestPattern {
+                    pattern: if value.is_empty() { "smpte".to_string() } else { value },
+                },
+            };
+            let name = id.clone();
+            if let Some(Err(e)) = with_scene_switcher(|switcher| switcher.add_source(SourceDef { id, name, kind })) {
+                eprintln!("Add source error: {:?}", e);
+            }
+        }));
+
+        show_source_btn.connect_clicked(clone!(@weak source_id_entry => move |_| {
+            let id = source_id_entry.text().to_string();
+            with_scene_switcher(|switcher| {
+                let scene = switcher.current_scene_index();
+                let (w, h) = switcher.canvas_size();
+                let geometry = Geometry { x: w / 4, y: h / 4, width: w / 2, height: h / 2 };
+                let _ = switcher.add_source_to_scene(scene, &id, geometry, &[])
+                    .map_err(|e| eprintln!("Add to scene error: {:?}", e));
+            });
+        }));
+
+        remove_source_btn.connect_clicked(clone!(@weak source_id_entry => move |_| {
+            let id = source_id_entry.text().to_string();
+            if let Some(Err(e)) = with_scene_switcher(|switcher| switcher.remove_source(&id)) {
+                eprintln!("Remove source error: {:?}", e);
             }
+        }));
+
+        import_btn.connect_clicked(clone!(@weak collection_entry => move |_| {
+            let path = PathBuf::from(collection_entry.text().as_str());
+            let result = SceneCollection::load_from_file(&path).and_then(|collection| {
+                load_scene_collection(&collection)?;
+                // The imported collection becomes the working one
+                collection.save_to_file(&SceneCollection::default_path())
+            });
+            match result {
+                Ok(()) => println!("Imported scene collection from {}", path.display()),
+                Err(e) => eprintln!("Scene collection import error: {:?}", e),
+            }
+        }));
+
+        export_btn.connect_clicked(clone!(@weak collection_entry => move |_| {
+            let path = PathBuf::from(collection_entry.text().as_str());
+            if let Some(collection) = with_scene_switcher(|switcher| switcher.to_collection("Exported")) {
+                match collection.save_to_file(&path) {
+                    Ok(()) => println!("Exported scene collection to {}", path.display()),
+                    Err(e) => eprintln!("Scene collection export error: {:?}", e),
+                }
+            }
+        }));
+
+        // Cameras are rebuilt with (or without) the segmentation stage
+        segmentation_check.connect_toggled(clone!(@weak segmentation_entry => move |check| {
+            let name = segmentation_entry.text().trim().to_string();
+            if check.is_active() && name.is_empty() {
+                eprintln!("Enter the name of a segmentation element first.");
+                check.set_active(false);
+                return;
+            }
+            set_segmentation_element(if check.is_active() { Some(&name) } else { None });
+            if let Some(collection) = with_scene_switcher(|switcher| switcher.current_collection()) {
+                if let Err(e) = load_scene_collection(&collection) {
+                    eprintln!("Failed to rebuild cameras: {:?}", e);
+                }
+            }
+        }));
+
+        apply_effects_btn.connect_clicked(move |_| {
+            let effects = CameraEffects {
+                chroma_key: match chroma_combo.active_id().as_deref() {
+                    Some("green") => Some(ChromaKey::new(ChromaKeyColor::Green)),
+                    Some("blue") => Some(ChromaKey::new(ChromaKeyColor::Blue)),
+                    Some("custom") => match parse_hex_rgb(chroma_color_entry.text().trim()) {
+                        Some((r, g, b)) => Some(ChromaKey::new(ChromaKeyColor::Custom(r, g, b))),
+                        None => {
+                            eprintln!("Chroma key color must look like #00ff00.");
+                            return;
+                        }
+                    },
+                    _ => None,
+                },
+                mask: match mask_combo.active_id().as_deref() {
+                    Some("circle") => Some(CameraMask::Circle),
+                    Some("rounded") => Some(CameraMask::RoundedRect { radius: 24.0 }),
+                    _ => None,
+                },
+                mirror: mirror_check.is_active(),
+                balance: ColorBalance {
+                    brightness: brightness_scale.value(),
+                    contrast: contrast_scale.value(),
+                    saturation: saturation_scale.value(),
+                },
+                background: match background_combo.active_id().as_deref() {
+                    Some("blur") => Some(BackgroundEffect::Blur { sigma: 8.0 }),
+                    Some("replace") => Some(BackgroundEffect::Replace {
+                        image_path: background_entry.text().to_string(),
+                    }),
+                    _ => None,
+                },
+            };
+            with_scene_switcher(|switcher| {
+                let scene = switcher.current_scene_index();
+                match switcher.camera_pad_index(scene) {
+                    Some(pad_index) => {
+                        let _ = switcher.set_source_effects(scene, pad_index, effects)
+                            .map_err(|e| eprintln!("Camera effects error: {:?}", e));
+                    }
+                    None => eprintln!("The active scene has no camera."),
+                }
+            });
         });
 
+        // (3) Overlays
+        add_text_btn.connect_clicked(clone!(@weak overlays_view => move |_| {
+            let added = with_overlay_manager(|overlay_mgr| {
+                overlay_mgr
+                    .add_text_overlay("hello_overlay", "Hello from Overlays!", 100, 100, TextStyle::default())
+                    .and_then(|_| overlay_mgr.save())
+            });
+            if let Some(result) = added {
+                if let Err(e) = result {
+                    eprintln!("Overlay error: {:?}", e);
+                }
+                overlays_view.buffer().set_text(&overlays_toml());
+            }
+        }));
+
         sponsor_btn.connect_clicked(move |_| {
-            if let Some(overlay_mgr) = overlay_manager_opt {
+            with_overlay_manager(|overlay_mgr| {
                 // Sample sponsor messages
-                let messages = vec![
-                    "Sponsored by Rust!".to_string(),
-                    "Waycord: Next-gen screen recorder".to_string(),
-                    "Visit example.org for more info".to_string(),
-                ];
-                let _ = overlay_mgr.start_rotating_messages("hello_overlay", messages, Duration::from_secs(5))
+                let messages = [
+                    "Sponsored by Rust!",
+                    "Waycord: Next-gen screen recorder",
+                    "Visit example.org for more info",
+                ]
+                .iter()
+                .map(|text| RotationMessage { text: text.to_string(), secs: None })
+                .collect();
+                let _ = overlay_mgr.start_rotation("hello_overlay", RotationConfig::new(messages))
                     .map_err(|e| eprintln!("Sponsor rotation error: {:?}", e));
+            });
+        });
+
+        // Each live edit is saved right away
+        let edit_overlay = move |id_entry: &gtk::Entry,
+                                 view: &gtk::TextView,
+                                 edit: &dyn Fn(&mut OverlayManager, &str) -> Result<()>| {
+            let id = id_entry.text().to_string();
+            if let Some(result) = with_overlay_manager(|overlay_mgr| edit(overlay_mgr, &id).and_then(|_| overlay_mgr.save())) {
+                if let Err(e) = result {
+                    eprintln!("Overlay '{}': {:?}", id, e);
+                }
+                view.buffer().set_text(&overlays_toml());
+            }
+        };
+        update_text_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak overlay_text_entry, @weak overlays_view => move |_| {
+            let text = overlay_text_entry.text().to_string();
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| mgr.update_text(id, &text));
+        }));
+        raise_overlay_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak overlays_view => move |_| {
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                let index = mgr.overlays().position(|o| o.id == id).unwrap_or(0);
+                mgr.reorder(id, index + 1)
+            });
+        }));
+        lower_overlay_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak overlays_view => move |_| {
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                let index = mgr.overlays().position(|o| o.id == id).unwrap_or(0);
+                mgr.reorder(id, index.saturating_sub(1))
+            });
+        }));
+        toggle_overlay_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak overlays_view => move |_| {
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                let visible = mgr.get(id).map(|o| o.visible).unwrap_or(true);
+                mgr.set_visibility(id, !visible)
+            });
+        }));
+        remove_overlay_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak overlays_view => move |_| {
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| mgr.remove_overlay(id));
+        }));
+
+        add_image_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak image_path_entry, @weak overlays_view => move |_| {
+            let path = image_path_entry.text().to_string();
+            let lower = path.to_lowercase();
+            let animated = path.contains('%') || [".gif", ".webp", ".apng", ".png"].iter().any(|ext| lower.ends_with(ext));
+            let kind = if animated {
+                // Plain PNGs load as a single frame
+                OverlayKind::Animation { path, fps: 30.0, looping: true, opacity: 1.0, scale: 1.0 }
+            } else {
+                OverlayKind::Image { path, opacity: 1.0, scale: 1.0 }
+            };
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                mgr.add_overlay(OverlayDesc { id: id.to_string(), x: 0, y: 0, visible: true, kind: kind.clone() })
+            });
+        }));
+        apply_look_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak opacity_spin, @weak scale_spin, @weak overlays_view => move |_| {
+            let (opacity, scale) = (opacity_spin.value(), scale_spin.value());
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                mgr.set_opacity(id, opacity)?;
+                mgr.set_scale(id, scale)
+            });
+        }));
+        restart_timer_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak overlays_view => move |_| {
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| mgr.restart_provider(id));
+        }));
+        rotate_file_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak rotation_file_entry, @weak rotation_mode_combo, @weak overlays_view => move |_| {
+            let transition = match rotation_mode_combo.active_id().as_deref() {
+                Some("cut") => RotationTransition::Cut,
+                Some("ticker") => RotationTransition::Ticker {
+                    speed: 120.0,
+                    width: None,
+                    separator: "   •   ".to_string(),
+                },
+                _ => RotationTransition::default(),
+            };
+            let config = RotationConfig {
+                file: Some(PathBuf::from(rotation_file_entry.text().as_str())),
+                transition,
+                ..RotationConfig::new(Vec::new())
+            };
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| mgr.start_rotation(id, config.clone()));
+        }));
+        stop_rotation_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak overlays_view => move |_| {
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| mgr.stop_rotation(id));
+        }));
+
+        // Internal: the mask area entry as (x, y, width, height)
+        let mask_area = |entry: &gtk::Entry| -> Option<(i32, i32, i32, i32)> {
+            let parts: Vec<i32> = entry.text().split(',').filter_map(|p| p.trim().parse().ok()).collect();
+            match parts[..] {
+                [x, y, w, h] => Some((x, y, w, h)),
+                _ => {
+                    eprintln!("Mask area must be x,y,width,height");
+                    None
+                }
+            }
+        };
+        let mask_effect = |combo: &gtk::ComboBoxText| match combo.active_id().as_deref() {
+            Some("blur") => MaskEffect::Blur,
+            Some("black") => MaskEffect::Black,
+            _ => MaskEffect::Pixelate,
+        };
+        add_mask_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak mask_area_entry, @weak mask_effect_combo, @weak overlays_view => move |_| {
+            if let Some((x, y, w, h)) = mask_area(&mask_area_entry) {
+                let effect = mask_effect(&mask_effect_combo);
+                edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| mgr.add_privacy_mask(id, x, y, w, h, effect));
+            }
+        }));
+        update_mask_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak mask_area_entry, @weak mask_effect_combo, @weak overlays_view => move |_| {
+            let effect = mask_effect(&mask_effect_combo);
+            let area = if mask_area_entry.text().is_empty() { None } else { mask_area(&mask_area_entry) };
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                let strength = match mgr.get(id).map(|o| &o.kind) {
+                    Some(OverlayKind::PrivacyMask { strength, .. }) => *strength,
+                    _ => 16.0,
+                };
+                mgr.set_mask_effect(id, effect, strength)?;
+                match area {
+                    Some((x, y, w, h)) => mgr.set_mask_area(id, x, y, w, h),
+                    None => Ok(()),
+                }
+            });
+        }));
+        track_mask_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak track_title_entry, @weak overlays_view => move |_| {
+            let title = track_title_entry.text().to_string();
+            let track = (!title.is_empty()).then(|| {
+                // Window coordinates reach the canvas through the shown screen source
+                let (scale, offset_x, offset_y) = with_scene_switcher(|switcher| switcher.screen_mapping())
+                    .flatten()
+                    .unwrap_or_else(|| {
+                        eprintln!("No screen source on screen, following windows at 1:1.");
+                        (1.0, 0, 0)
+                    });
+                TrackWindow {
+                    title,
+                    offset_x,
+                    offset_y,
+                    scale,
+                    padding: 8,
+                }
+            });
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| mgr.set_mask_tracking(id, track.clone()));
+        }));
+
+        // The preview draws the template as edited, not as saved
+        let preview_template = Rc::new(RefCell::new(LowerThirdTemplate::default()));
+        let preview_reveal = Rc::new(RefCell::new(Reveal::new(true)));
+        lower_third_preview.set_draw_func(clone!(@strong preview_template, @strong preview_reveal,
+            @weak lower_third_title_entry, @weak lower_third_subtitle_entry => move |_, cr, w, h| {
+            let template = preview_template.borrow();
+            let progress = preview_reveal.borrow().progress(&template);
+            // Shrink to fit; the template is in canvas pixels
+            let margin = 10.0;
+            let fit = ((w as f64 - 2.0 * margin) / template.width as f64)
+                .min((h as f64 - 2.0 * margin) / template.height as f64)
+                .min(1.0);
+            cr.translate(margin, margin);
+            cr.scale(fit, fit);
+            let title = lower_third_title_entry.text();
+            let subtitle = lower_third_subtitle_entry.text();
+            let title = if title.is_empty() { "Jane Doe" } else { title.as_str() };
+            let subtitle = if subtitle.is_empty() { "Maintainer, Waycord" } else { subtitle.as_str() };
+            draw_lower_third(cr, &template, title, subtitle, 0.0, 0.0, progress);
+        }));
+        // Internal: parse the template view into the preview; None if it is not valid
+        let parse_template = clone!(@weak template_view, @strong preview_template => @default-return None, move || {
+            let buffer = template_view.buffer();
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            match toml::from_str::<LowerThirdTemplate>(text.as_str()) {
+                Ok(template) => {
+                    *preview_template.borrow_mut() = template.clone();
+                    Some(template)
+                }
+                Err(e) => {
+                    eprintln!("Invalid lower third template: {}", e);
+                    None
+                }
+            }
+        });
+        template_combo.connect_changed(clone!(@weak template_view, @weak lower_third_preview, @strong parse_template => move |combo| {
+            // Only names picked from the list; typed names are for saving
+            if let Some(name) = combo.active_id() {
+                match LowerThirdTemplate::load(&name) {
+                    Ok(template) => {
+                        template_view.buffer().set_text(&toml::to_string_pretty(&template).unwrap_or_default());
+                        parse_template();
+                        lower_third_preview.queue_draw();
+                    }
+                    Err(e) => eprintln!("Failed to load lower third template '{}': {:?}", name, e),
+                }
+            }
+        }));
+        for entry in [&lower_third_title_entry, &lower_third_subtitle_entry] {
+            entry.connect_changed(clone!(@weak lower_third_preview => move |_| lower_third_preview.queue_draw()));
+        }
+        play_lower_third_btn.connect_clicked(clone!(@weak lower_third_preview, @strong parse_template, @strong preview_template, @strong preview_reveal => move |_| {
+            parse_template();
+            let template = preview_template.borrow().clone();
+            // Out, then back in
+            preview_reveal.borrow_mut().set(false, &template);
+            let frames = Rc::new(RefCell::new(0u64));
+            let total = (2 * template.duration_ms + 300) / 16;
+            glib::timeout_add_local(Duration::from_millis(16), clone!(@weak lower_third_preview, @strong preview_reveal => @default-return glib::Continue(false), move || {
+                let mut count = frames.borrow_mut();
+                *count += 1;
+                if *count * 16 >= template.duration_ms + 150 {
+                    preview_reveal.borrow_mut().set(true, &template);
+                }
+                lower_third_preview.queue_draw();
+                if *count >= total {
+                    glib::Continue(false)
+                } else {
+                    glib::Continue(true)
+                }
+            }));
+        }));
+        save_template_btn.connect_clicked(clone!(@weak template_combo, @weak lower_third_preview, @strong parse_template => move |_| {
+            let name = template_combo.active_text().map(|t| t.to_string()).unwrap_or_default();
+            if name.trim().is_empty() {
+                eprintln!("Enter a name for the lower third template.");
+                return;
+            }
+            if let Some(template) = parse_template() {
+                match template.save(name.trim()) {
+                    Ok(_) => {
+                        if template_combo.active_id().is_none() {
+                            template_combo.append(Some(name.trim()), name.trim());
+                        }
+                        // Live lower thirds using it pick up the change
+                        with_overlay_manager(|overlay_mgr| {
+                            let ids: Vec<String> = overlay_mgr
+                                .overlays()
+                                .filter(|o| matches!(&o.kind, OverlayKind::LowerThird { template, .. } if template == name.trim()))
+                                .map(|o| o.id.clone())
+                                .collect();
+                            for id in ids {
+                                let _ = overlay_mgr.set_lower_third_template(&id, name.trim());
+                            }
+                        });
+                    }
+                    Err(e) => eprintln!("Failed to save lower third template '{}': {:?}", name, e),
+                }
+            }
+            lower_third_preview.queue_draw();
+        }));
+        add_lower_third_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak template_combo, @weak lower_third_title_entry,
+            @weak lower_third_subtitle_entry, @weak overlays_view => move |_| {
+            let kind = OverlayKind::LowerThird {
+                template: template_combo.active_text().map(|t| t.to_string()).unwrap_or_else(|| "default".to_string()),
+                title: lower_third_title_entry.text().to_string(),
+                subtitle: lower_third_subtitle_entry.text().to_string(),
+                hotkey: None,
+            };
+            // Starts hidden; show it with Show/Hide or its hotkey
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                mgr.add_overlay(OverlayDesc { id: id.to_string(), x: 40, y: 560, visible: false, kind: kind.clone() })
+            });
+        }));
+        update_lower_third_btn.connect_clicked(clone!(@weak overlay_id_entry, @weak template_combo, @weak lower_third_title_entry,
+            @weak lower_third_subtitle_entry, @weak overlays_view => move |_| {
+            let template = template_combo.active_text().map(|t| t.to_string()).unwrap_or_else(|| "default".to_string());
+            let (title, subtitle) = (lower_third_title_entry.text().to_string(), lower_third_subtitle_entry.text().to_string());
+            edit_overlay(&overlay_id_entry, &overlays_view, &|mgr, id| {
+                mgr.set_lower_third_template(id, &template)?;
+                mgr.set_lower_third_text(id, &title, &subtitle)
+            });
+        }));
+
+        save_stamp_btn.connect_clicked(clone!(@weak stamp_view => move |_| {
+            let buffer = stamp_view.buffer();
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            match toml::from_str::<RecordingStamp>(text.as_str()) {
+                Ok(stamp) => {
+                    let _ = stamp
+                        .save_to_file(&RecordingStamp::default_path())
+                        .map_err(|e| eprintln!("Failed to save recording stamp: {:?}", e));
+                }
+                Err(e) => eprintln!("Invalid recording stamp: {}", e),
+            }
+        }));
+
+        save_subtitles_btn.connect_clicked(clone!(@weak subtitles_view => move |_| {
+            let buffer = subtitles_view.buffer();
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            match toml::from_str::<SubtitleConfig>(text.as_str()) {
+                Ok(config) => {
+                    let _ = config
+                        .save_to_file(&SubtitleConfig::default_path())
+                        .map_err(|e| eprintln!("Failed to save subtitle settings: {:?}", e));
+                }
+                Err(e) => eprintln!("Invalid subtitle settings: {}", e),
+            }
+        }));
+        // Enter sends the caption too
+        let send_caption = clone!(@weak caption_entry, @weak caption_secs_spin => move || {
+            let text = caption_entry.text().to_string();
+            if text.is_empty() {
+                return;
+            }
+            match caption(&text, caption_secs_spin.value()) {
+                Ok(_) => caption_entry.set_text(""),
+                Err(e) => eprintln!("Caption error: {:?}", e),
             }
         });
+        caption_btn.connect_clicked(clone!(@strong send_caption => move |_| send_caption()));
+        caption_entry.connect_activate(move |_| send_caption());
+
+        apply_overlays_btn.connect_clicked(clone!(@weak overlays_view => move |_| {
+            let buffer = overlays_view.buffer();
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            let stack = match toml::from_str::<OverlayStack>(text.as_str()) {
+                Ok(stack) => stack,
+                Err(e) => {
+                    eprintln!("Invalid overlay stack: {}", e);
+                    return;
+                }
+            };
+            if let Some(Err(e)) = with_overlay_manager(|overlay_mgr| overlay_mgr.load_stack(&stack).and_then(|_| overlay_mgr.save())) {
+                eprintln!("Overlay error: {:?}", e);
+            }
+        }));
 
         // (4) Streaming
         add_rtmp_btn.connect_clicked(clone!(@weak rtmp_entry => move |_| {
-            if let Some(manager) = streaming_manager_opt {
-                let url = rtmp_entry.text().to_string();
-                if !url.is_empty() {
-                    let _ = manager.add_output(StreamingProtocol::RTMP(url))
-                        .map_err(|e| eprintln!("Add RTMP output error: {:?}", e));
+            let url = rtmp_entry.text().to_string();
+            if !url.is_empty() {
+                if let Some(Err(e)) = with_streaming_manager(|manager| manager.add_output(StreamingProtocol::RTMP(url))) {
+                    eprintln!("Add RTMP output error: {:?}", e);
                 }
             }
         }));
 
+        apply_rules_btn.connect_clicked(clone!(@weak rules_view => move |_| {
+            let buffer = rules_view.buffer();
+            let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false);
+            let config = match toml::from_str::<AutomationConfig>(text.as_str()) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Invalid automation rules: {}", e);
+                    return;
+                }
+            };
+            if let Err(e) = config.save_to_file(&AutomationConfig::default_path()) {
+                eprintln!("Failed to save automation rules: {:?}", e);
+            }
+            println!("Automation: {} rules, profile '{}'", config.rules.len(), config.profile);
+            with_automation(|engine| engine.set_config(config));
+        }));
+
+        refresh_history_btn.connect_clicked(clone!(@weak history_label => move |_| {
+            if let Some(lines) = with_automation(|engine| engine.history().cloned().collect::<Vec<String>>()) {
+                history_label.set_text(&lines.join("\n"));
+            }
+        }));
+
         // (5) Plugin System
         load_plugin_btn.connect_clicked(clone!(@weak plugin_folder_entry => move |_| {
             let folder = plugin_folder_entry.text().to_string();
@@ -230,3 +1178,8 @@ impl SettingsDialog {
         self.dialog.present();
     }
 }
+
+// Internal: the live overlay stack as TOML, for the overlays page
+fn overlays_toml() -> String {
+    with_overlay_manager(|mgr| toml::to_string_pretty(&mgr.to_stack()).unwrap_or_default()).unwrap_or_default()
+}
@@ -2,7 +2,19 @@ use gtk4 as gtk;
 use gtk::{Box as GtkBox, HeaderBar, Orientation, MenuButton};
 use gtk::prelude::*;
 use libadwaita::{Application, Window as AdwWindow};
+use glib::clone;
+use std::cell::RefCell;
+use std::rc::Rc;
 use crate::gui::{controls::build_controls, preview::build_preview, settings_dialog::SettingsDialog};
+use crate::core::encoder::{
+    get_active_camera, get_global_pipeline, get_global_zoom_follow, with_overlay_manager, with_scene_switcher,
+};
+use crate::devices::cursor::{set_click_capture, ClickUser};
+use crate::extensions::camera_position::{CameraCorner, CameraPlacement, CameraSize};
+use crate::extensions::overlays::{OverlayDesc, OverlayKind};
+use crate::extensions::studio_mode::StudioMode;
+use crate::extensions::subtitles::caption;
+use crate::extensions::text_providers::{bump_counter, TextProvider};
 
 pub fn build_ui(app: &Application) {
     let window = AdwWindow::new(app);
@@ -24,39 +36,389 @@ pub fn build_ui(app: &Application) {
 
     // "Settings" button → opens SettingsDialog
     let settings_button = gtk::Button::with_label("Settings");
-    settings_button.connect_clicked(glib::clone!(@weak window => move |_| {
+    settings_button.connect_clicked(clone!(@weak window => move |_| {
         let dialog = SettingsDialog::new(&window);
         dialog.present();
     }));
     header.pack_end(&settings_button);
 
-    // "Camera Position" dropdown
+    // "Studio Mode" toggle: preview and program side by side
+    let studio_button = gtk::ToggleButton::with_label("Studio Mode");
+    header.pack_start(&studio_button);
+    let studio: Rc<RefCell<Option<StudioMode>>> = Rc::new(RefCell::new(None));
+    let studio_view: Rc<RefCell<Option<GtkBox>>> = Rc::new(RefCell::new(None));
+
+    // "Camera Position" dropdown: corners, hidden, size presets and a custom margin
     let camera_button = MenuButton::new();
     camera_button.set_label("Camera Position");
     header.pack_end(&camera_button);
 
     let menu_model = gio::Menu::new();
-    menu_model.append("Top-Left", Some("app.cam_position_top_left"));
-    menu_model.append("Bottom-Right", Some("app.cam_position_bottom_right"));
+    let corner_section = gio::Menu::new();
+    corner_section.append(Some("Top-Left"), Some("app.cam_position::top_left"));
+    corner_section.append(Some("Top-Right"), Some("app.cam_position::top_right"));
+    corner_section.append(Some("Bottom-Left"), Some("app.cam_position::bottom_left"));
+    corner_section.append(Some("Bottom-Right"), Some("app.cam_position::bottom_right"));
+    corner_section.append(Some("Hidden"), Some("app.cam_position::hidden"));
+    menu_model.append_section(None, &corner_section);
+
+    let size_section = gio::Menu::new();
+    size_section.append(Some("Small"), Some("app.cam_size::small"));
+    size_section.append(Some("Medium"), Some("app.cam_size::medium"));
+    size_section.append(Some("Large"), Some("app.cam_size::large"));
+    menu_model.append_section(Some("Size"), &size_section);
+
+    let offset_section = gio::Menu::new();
+    offset_section.append(Some("Custom Offset…"), Some("app.cam_offset"));
+    menu_model.append_section(None, &offset_section);
     camera_button.set_menu_model(Some(&menu_model));
 
-    let action_top_left = gio::SimpleAction::new("cam_position_top_left", None);
-    action_top_left.connect_activate(move |_, _| {
-        println!("Camera: set to top-left. (Adjust overlay or scene geometry in your manager.)");
+    let placement = Rc::new(RefCell::new(CameraPlacement::default()));
+
+    let action_position = gio::SimpleAction::new("cam_position", Some(glib::VariantTy::STRING));
+    action_position.connect_activate(clone!(@strong placement => move |_, param| {
+        let id = param.and_then(|p| p.str()).unwrap_or_default();
+        {
+            let mut p = placement.borrow_mut();
+            if id == "hidden" {
+                p.hidden = true;
+            } else if let Some(corner) = CameraCorner::from_id(id) {
+                p.corner = corner;
+                p.hidden = false;
+            }
+        }
+        apply_camera_placement(&placement.borrow());
+    }));
+
+    let action_size = gio::SimpleAction::new("cam_size", Some(glib::VariantTy::STRING));
+    action_size.connect_activate(clone!(@strong placement => move |_, param| {
+        let id = param.and_then(|p| p.str()).unwrap_or_default();
+        if let Some(size) = CameraSize::from_id(id) {
+            placement.borrow_mut().size = size;
+            apply_camera_placement(&placement.borrow());
+        }
+    }));
+
+    let action_offset = gio::SimpleAction::new("cam_offset", None);
+    action_offset.connect_activate(clone!(@weak window, @strong placement => move |_, _| {
+        show_offset_dialog(&window, placement.clone());
+    }));
+
+    app.add_action(&action_position);
+    app.add_action(&action_size);
+    app.add_action(&action_offset);
+
+    // "Zoom" dropdown: follow-the-cursor zoom for tutorials
+    let zoom_button = MenuButton::new();
+    zoom_button.set_label("Zoom");
+    header.pack_end(&zoom_button);
+
+    let zoom_menu = gio::Menu::new();
+    zoom_menu.append(Some("Toggle Zoom"), Some("app.zoom_toggle"));
+    zoom_menu.append(Some("Zoom on Clicks"), Some("app.zoom_follow_clicks"));
+    let zoom_levels = gio::Menu::new();
+    zoom_levels.append(Some("1.5×"), Some("app.zoom_level::1.5"));
+    zoom_levels.append(Some("2×"), Some("app.zoom_level::2"));
+    zoom_levels.append(Some("3×"), Some("app.zoom_level::3"));
+    zoom_menu.append_section(Some("Level"), &zoom_levels);
+    zoom_button.set_menu_model(Some(&zoom_menu));
+
+    let action_zoom = gio::SimpleAction::new("zoom_toggle", None);
+    action_zoom.connect_activate(|_, _| {
+        if let Some(zoom) = get_global_zoom_follow() {
+            zoom.toggle();
+        }
     });
 
-    let action_bottom_right = gio::SimpleAction::new("cam_position_bottom_right", None);
-    action_bottom_right.connect_activate(move |_, _| {
-        println!("Camera: set to bottom-right. (Adjust overlay or scene geometry in your manager.)");
+    let action_zoom_clicks = gio::SimpleAction::new_stateful("zoom_follow_clicks", None, &false.to_variant());
+    action_zoom_clicks.connect_activate(|action, _| {
+        let enabled = !action.state().and_then(|s| s.get::<bool>()).unwrap_or(false);
+        action.set_state(&enabled.to_variant());
+        set_click_capture(ClickUser::Zoom, enabled);
+        if let Some(zoom) = get_global_zoom_follow() {
+            let mut opts = zoom.options();
+            opts.follow_clicks = enabled;
+            zoom.set_options(opts);
+        }
     });
 
-    app.add_action(&action_top_left);
-    app.add_action(&action_bottom_right);
+    let action_zoom_level = gio::SimpleAction::new("zoom_level", Some(glib::VariantTy::STRING));
+    action_zoom_level.connect_activate(|_, param| {
+        let level = param.and_then(|p| p.str()).and_then(|s| s.parse::<f64>().ok());
+        if let (Some(level), Some(zoom)) = (level, get_global_zoom_follow()) {
+            let mut opts = zoom.options();
+            opts.zoom = level;
+            zoom.set_options(opts);
+        }
+    });
+
+    app.add_action(&action_zoom);
+    app.add_action(&action_zoom_clicks);
+    app.add_action(&action_zoom_level);
+    app.set_accels_for_action("app.zoom_toggle", &["<Ctrl><Alt>z"]);
+
+    // Privacy mode blacks out the whole program until turned off again
+    let privacy_button = gtk::ToggleButton::with_label("Privacy");
+    privacy_button.set_tooltip_text(Some("Black out the program (Ctrl+Alt+P)"));
+    privacy_button.set_action_name(Some("app.privacy_mode"));
+    header.pack_end(&privacy_button);
+    let action_privacy = gio::SimpleAction::new_stateful("privacy_mode", None, &false.to_variant());
+    action_privacy.connect_activate(|action, _| {
+        let enabled = !action.state().and_then(|s| s.get::<bool>()).unwrap_or(false);
+        match with_overlay_manager(|mgr| mgr.set_privacy_mode(enabled)) {
+            Some(()) => action.set_state(&enabled.to_variant()),
+            None => eprintln!("Privacy mode needs the overlay chain of the main pipeline."),
+        }
+    });
+    app.add_action(&action_privacy);
+    app.set_accels_for_action("app.privacy_mode", &["<Ctrl><Alt>p"]);
+
+    // Counters shown by text overlays, bumped from their hotkeys
+    let action_counter = gio::SimpleAction::new("counter_bump", Some(glib::VariantTy::STRING));
+    action_counter.connect_activate(|_, param| {
+        if let Some(name) = param.and_then(|p| p.str()) {
+            bump_counter(name, 1);
+        }
+    });
+    app.add_action(&action_counter);
+
+    // Lower thirds, by overlay ID. Being app actions, these can also be
+    // triggered over D-Bus through org.gtk.Actions.
+    for (name, visible) in [("lower_third_show", Some(true)), ("lower_third_hide", Some(false)), ("lower_third_toggle", None)] {
+        let action = gio::SimpleAction::new(name, Some(glib::VariantTy::STRING));
+        action.connect_activate(move |_, param| {
+            if let Some(id) = param.and_then(|p| p.str()) {
+                let result = with_overlay_manager(|mgr| match visible {
+                    Some(visible) => mgr.set_visibility(id, visible),
+                    None => mgr.toggle_visibility(id).map(|_| ()),
+                });
+                if let Some(Err(e)) = result {
+                    eprintln!("Lower third error: {:?}", e);
+                }
+            }
+        });
+        app.add_action(&action);
+    }
+    // (id, title, subtitle): fill in a lower third and show it
+    let action_lower_third_set = gio::SimpleAction::new("lower_third_set", Some(glib::VariantTy::new("(sss)").unwrap()));
+    action_lower_third_set.connect_activate(|_, param| {
+        if let Some((id, title, subtitle)) = param.and_then(|p| p.get::<(String, String, String)>()) {
+            let result = with_overlay_manager(|mgr| {
+                mgr.set_lower_third_text(&id, &title, &subtitle).and_then(|_| mgr.set_visibility(&id, true))
+            });
+            if let Some(Err(e)) = result {
+                eprintln!("Lower third error: {:?}", e);
+            }
+        }
+    });
+    app.add_action(&action_lower_third_set);
+
+    // Operator captions for the recording's subtitles, shown for 4 s
+    let action_caption = gio::SimpleAction::new("caption", Some(glib::VariantTy::STRING));
+    action_caption.connect_activate(|_, param| {
+        if let Some(text) = param.and_then(|p| p.str()) {
+            if let Err(e) = caption(text, 4.0) {
+                eprintln!("Caption error: {:?}", e);
+            }
+        }
+    });
+    app.add_action(&action_caption);
+
+    // Counter and lower third hotkeys follow the overlay stack
+    let bound_hotkeys = Rc::new(RefCell::new(Vec::new()));
+    with_overlay_manager(|mgr| {
+        let overlays: Vec<OverlayDesc> = mgr.overlays().cloned().collect();
+        bind_overlay_hotkeys(app, &bound_hotkeys, &overlays);
+        let app = app.downgrade();
+        mgr.connect_stack_changed(move |overlays| {
+            if let Some(app) = app.upgrade() {
+                bind_overlay_hotkeys(&app, &bound_hotkeys, overlays);
+            }
+        });
+    });
 
     // Put everything in content
     content.append(&preview);
     content.append(&controls);
 
+    studio_button.connect_toggled(clone!(@weak content, @weak preview => move |button| {
+        if button.is_active() {
+            match build_studio_view(studio.clone()) {
+                Ok(view) => {
+                    preview.set_visible(false);
+                    content.prepend(&view);
+                    *studio_view.borrow_mut() = Some(view);
+                }
+                Err(e) => {
+                    eprintln!("Studio mode error: {:?}", e);
+                    button.set_active(false);
+                }
+            }
+        } else {
+            if let Some(view) = studio_view.borrow_mut().take() {
+                content.remove(&view);
+            }
+            preview.set_visible(true);
+            if let Some(studio_mode) = studio.borrow_mut().take() {
+                with_scene_switcher(|switcher| studio_mode.shutdown(switcher));
+            }
+        }
+    }));
+
     window.set_content(Some(&content));
     window.present();
 }
+
+/// Preview | transition | program, with a scene picker for the preview.
+/// The new studio mode is stored in `studio`.
+fn build_studio_view(studio: Rc<RefCell<Option<StudioMode>>>) -> anyhow::Result<GtkBox> {
+    let pipeline = get_global_pipeline().ok_or_else(|| anyhow::anyhow!("Pipeline not initialized"))?;
+    let program_tee = pipeline
+        .by_name("program_tee")
+        .ok_or_else(|| anyhow::anyhow!("No program tee in the pipeline"))?;
+
+    // Stage the scene after the one on program
+    let (studio_mode, scene_names, next) = with_scene_switcher(|switcher| -> anyhow::Result<_> {
+        let mut studio_mode = StudioMode::new(pipeline, &program_tee, switcher.canvas_size())?;
+        let next = (switcher.current_scene_index() + 1) % switcher.scenes().len().max(1);
+        studio_mode.set_preview_scene(switcher, next)?;
+        let names: Vec<String> = switcher.scenes().iter().map(|scene| scene.name.clone()).collect();
+        Ok((studio_mode, names, next))
+    })
+    .ok_or_else(|| anyhow::anyhow!("Pipeline not initialized"))??;
+    let scene_combo = gtk::ComboBoxText::new();
+    for (idx, name) in scene_names.iter().enumerate() {
+        scene_combo.append(Some(&idx.to_string()), name);
+    }
+    scene_combo.set_active_id(Some(&next.to_string()));
+
+    let view = GtkBox::new(Orientation::Horizontal, 10);
+    let preview_column = GtkBox::new(Orientation::Vertical, 5);
+    preview_column.append(&gtk::Label::new(Some("Preview")));
+    preview_column.append(studio_mode.preview_widget());
+    preview_column.append(&scene_combo);
+    studio_mode.preview_widget().set_size_request(640, 360);
+
+    let transition_btn = gtk::Button::with_label("Transition ▶");
+    transition_btn.set_valign(gtk::Align::Center);
+
+    let program_column = GtkBox::new(Orientation::Vertical, 5);
+    program_column.append(&gtk::Label::new(Some("Program")));
+    program_column.append(studio_mode.program_widget());
+    studio_mode.program_widget().set_size_request(640, 360);
+
+    view.append(&preview_column);
+    view.append(&transition_btn);
+    view.append(&program_column);
+    *studio.borrow_mut() = Some(studio_mode);
+
+    scene_combo.connect_changed(clone!(@strong studio => move |combo| {
+        let index = combo.active_id().and_then(|id| id.parse::<usize>().ok());
+        let mut studio = studio.borrow_mut();
+        if let (Some(index), Some(studio_mode)) = (index, studio.as_mut()) {
+            if studio_mode.preview_scene() != Some(index) {
+                if let Some(Err(e)) = with_scene_switcher(|switcher| studio_mode.set_preview_scene(switcher, index)) {
+                    eprintln!("Preview scene error: {:?}", e);
+                }
+            }
+        }
+    }));
+
+    transition_btn.connect_clicked(clone!(@strong studio, @weak scene_combo => move |_| {
+        let preview_scene = {
+            let mut studio = studio.borrow_mut();
+            let studio_mode = match studio.as_mut() {
+                Some(m) => m,
+                None => return,
+            };
+            match with_scene_switcher(|switcher| studio_mode.transition(switcher)) {
+                Some(Ok(())) => {}
+                Some(Err(e)) => {
+                    eprintln!("Studio transition error: {:?}", e);
+                    return;
+                }
+                None => return,
+            }
+            studio_mode.preview_scene()
+        };
+        // Preview now shows what was on program
+        if let Some(index) = preview_scene {
+            scene_combo.set_active_id(Some(&index.to_string()));
+        }
+    }));
+
+    Ok(view)
+}
+
+/// Bind the hotkeys of counter and lower third overlays, replacing the ones
+/// bound before (`bound` keeps their detailed action names).
+fn bind_overlay_hotkeys(app: &Application, bound: &RefCell<Vec<String>>, overlays: &[OverlayDesc]) {
+    let mut bound = bound.borrow_mut();
+    for action in bound.drain(..) {
+        app.set_accels_for_action(&action, &[]);
+    }
+    for desc in overlays {
+        let (action, hotkey) = match &desc.kind {
+            OverlayKind::Text {
+                provider: Some(TextProvider::Counter { name, hotkey: Some(hotkey), .. }),
+                ..
+            } => (format!("app.counter_bump::{}", name), hotkey),
+            OverlayKind::LowerThird { hotkey: Some(hotkey), .. } => (format!("app.lower_third_toggle::{}", desc.id), hotkey),
+            _ => continue,
+        };
+        app.set_accels_for_action(&action, &[hotkey.as_str()]);
+        bound.push(action);
+    }
+}
+
+/// Push the placement into the active scene of the global scene switcher.
+fn apply_camera_placement(placement: &CameraPlacement) {
+    let camera = match get_active_camera() {
+        Some(c) => c,
+        None => {
+            eprintln!("No camera in the pipeline, nothing to position.");
+            return;
+        }
+    };
+    let aspect = camera.mode.width as f64 / camera.mode.height.max(1) as f64;
+    if let Some(Err(e)) = with_scene_switcher(|switcher| placement.apply(switcher, aspect)) {
+        eprintln!("Camera position error: {:?}", e);
+    }
+}
+
+/// Small dialog to set the camera margin from its corner.
+fn show_offset_dialog(parent: &AdwWindow, placement: Rc<RefCell<CameraPlacement>>) {
+    let dialog = AdwWindow::new(None);
+    dialog.set_title(Some("Camera Offset"));
+    dialog.set_transient_for(Some(parent));
+    dialog.set_modal(true);
+
+    let vbox = GtkBox::new(Orientation::Vertical, 10);
+    let current = *placement.borrow();
+    let x_spin = gtk::SpinButton::with_range(0.0, 4000.0, 1.0);
+    x_spin.set_value(current.offset_x as f64);
+    let y_spin = gtk::SpinButton::with_range(0.0, 4000.0, 1.0);
+    y_spin.set_value(current.offset_y as f64);
+
+    let row = GtkBox::new(Orientation::Horizontal, 5);
+    row.append(&gtk::Label::new(Some("X margin:")));
+    row.append(&x_spin);
+    row.append(&gtk::Label::new(Some("Y margin:")));
+    row.append(&y_spin);
+    vbox.append(&row);
+
+    let apply_btn = gtk::Button::with_label("Apply");
+    apply_btn.connect_clicked(clone!(@weak dialog => move |_| {
+        {
+            let mut p = placement.borrow_mut();
+            p.offset_x = x_spin.value_as_int();
+            p.offset_y = y_spin.value_as_int();
+        }
+        apply_camera_placement(&placement.borrow());
+        dialog.close();
+    }));
+    vbox.append(&apply_btn);
+
+    dialog.set_content(Some(&vbox));
+    dialog.present();
+}